@@ -1,33 +1,44 @@
+use crate::clip_markers::ClipMarker;
+use crate::game_detector::state::GameLifecycle;
 use crate::game_detector::GameDetector;
+use crate::recorder::replay_buffer::ReplayBuffer;
+use crate::recorder::set::RecordingSet;
 use crate::recorder::Recorder;
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Instant, SystemTime};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClipMarker {
-    pub recording_file: String,
-    pub timestamp_seconds: f64,
-}
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 #[derive(Clone)]
 pub struct SlpCacheEntry {
     pub metadata: serde_json::Value,
     pub duration: Option<u64>,
     pub end_time: Option<String>,
+    pub tech_stats: Option<Vec<crate::slippi::TechStats>>,
     pub modified_time: SystemTime,
 }
 
+/// The recorder behind a lock of its own, held while it works, so the app's
+/// lock on it is only held long enough to take a handle
+pub type SharedRecorder = Arc<Mutex<Box<dyn Recorder + Send>>>;
+
 /// Global application state managed by Tauri
 pub struct AppState {
     pub game_detector: Mutex<Option<GameDetector>>,
-    pub recorder: Mutex<Option<Box<dyn Recorder + Send>>>,
+    pub recorder: Mutex<Option<SharedRecorder>>,
+    /// Instant replay buffer, separate from the main recorder
+    pub replay_buffer: Mutex<Option<ReplayBuffer>>,
     pub settings: Mutex<HashMap<String, serde_json::Value>>,
-    pub last_replay_path: Mutex<Option<String>>,
+    /// Shared with the game detector, which drives it from the replay files
+    pub game_lifecycle: Arc<Mutex<GameLifecycle>>,
+    /// Drives recording from `game-lifecycle`, while watching
+    pub lifecycle_listener: Mutex<Option<tauri::EventId>>,
     pub current_recording_file: Mutex<Option<String>>,
-    pub last_file_modification: Mutex<Option<Instant>>,
+    /// Games covered by the current auto-recording
+    pub recording_set: Mutex<Option<RecordingSet>>,
     pub clip_markers: Mutex<Vec<ClipMarker>>,
+    /// Set to stop the thread sending the current recording's stats to the frontend
+    pub stats_watcher: Mutex<Option<Arc<AtomicBool>>>,
     pub slp_cache: Mutex<HashMap<String, SlpCacheEntry>>,
 }
 
@@ -36,11 +47,14 @@ impl AppState {
         Self {
             game_detector: Mutex::new(None),
             recorder: Mutex::new(None),
+            replay_buffer: Mutex::new(None),
             settings: Mutex::new(HashMap::new()),
-            last_replay_path: Mutex::new(None),
+            game_lifecycle: Arc::new(Mutex::new(GameLifecycle::new())),
+            lifecycle_listener: Mutex::new(None),
             current_recording_file: Mutex::new(None),
-            last_file_modification: Mutex::new(None),
+            recording_set: Mutex::new(None),
             clip_markers: Mutex::new(Vec::new()),
+            stats_watcher: Mutex::new(None),
             slp_cache: Mutex::new(HashMap::new()),
         }
     }
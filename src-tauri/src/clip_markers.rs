@@ -0,0 +1,182 @@
+// Clip markers kept next to their recording
+//
+// Markers are written to `<video>.markers.json` as they're made, so quitting
+// or crashing before a recording is processed doesn't lose them. Each one
+// notes the clip it went into once it's cut, or that it was skipped for falling
+// outside the recording, so processing a recording again only cuts what's
+// left, and whatever's left at startup is picked up again.
+
+use crate::commands::errors::Error;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SUFFIX: &str = ".markers.json";
+
+/// What asked for a clip
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipSource {
+    /// The clip hotkey, during a recording
+    #[default]
+    Hotkey,
+    /// Something that happened in the game, like a combo or a kill
+    Event,
+    /// Marked by hand, e.g. from the replay viewer
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipMarker {
+    /// Numbers the recording's markers, and names the clip cut for it
+    #[serde(default)]
+    pub id: u32,
+    pub recording_file: String,
+    pub timestamp_seconds: f64,
+    /// Seconds of recording kept before the marker
+    pub pre_roll_seconds: f64,
+    /// Seconds of recording kept after the marker
+    #[serde(default)]
+    pub post_roll_seconds: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub source: ClipSource,
+    /// The clip the marker went into, once it's been cut
+    #[serde(default)]
+    pub clip_path: Option<String>,
+    /// Set when the marker's clip fell outside the recording, leaving nothing to cut
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+/// A recording's clip markers, as kept next to it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ClipMarkerFile {
+    pub markers: Vec<ClipMarker>,
+}
+
+impl ClipMarkerFile {
+    pub fn path_for(video_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}{}", video_path, SUFFIX))
+    }
+
+    pub fn load(video_path: &str) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path_for(video_path)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn write(&self, video_path: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::RecordingFailed(format!("Failed to serialize markers: {}", e)))?;
+        std::fs::write(Self::path_for(video_path), json)?;
+        Ok(())
+    }
+
+    /// Id for the recording's next marker
+    pub fn next_id(&self) -> u32 {
+        self.markers.iter().map(|m| m.id).max().unwrap_or(0) + 1
+    }
+
+    /// Add markers that aren't in the file yet, e.g. ones that failed to save
+    pub fn merge(&mut self, markers: impl IntoIterator<Item = ClipMarker>) {
+        for marker in markers {
+            if !self.markers.iter().any(|m| m.id == marker.id) {
+                self.markers.push(marker);
+            }
+        }
+        self.markers.sort_by_key(|m| m.id);
+    }
+
+    /// Markers that haven't been cut into a clip yet
+    pub fn pending(&self) -> Vec<ClipMarker> {
+        self.markers
+            .iter()
+            .filter(|m| m.clip_path.is_none() && !m.skipped)
+            .cloned()
+            .collect()
+    }
+
+    /// Note that the markers with `ids` went into `clip_path`
+    pub fn set_clip(&mut self, ids: &[u32], clip_path: &str) {
+        for marker in &mut self.markers {
+            if ids.contains(&marker.id) {
+                marker.clip_path = Some(clip_path.to_string());
+            }
+        }
+    }
+
+    /// Note that the markers with `ids` had nothing to cut
+    pub fn set_skipped(&mut self, ids: &[u32]) {
+        for marker in &mut self.markers {
+            if ids.contains(&marker.id) {
+                marker.skipped = true;
+            }
+        }
+    }
+}
+
+/// Videos in `dir` with markers that haven't been cut yet
+pub fn find_pending(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut videos: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path().to_string_lossy().to_string();
+            path.strip_suffix(SUFFIX).map(str::to_string)
+        })
+        .filter(|video| ClipMarkerFile::load(video).is_some_and(|file| !file.pending().is_empty()))
+        .collect();
+    videos.sort();
+    videos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(id: u32, recording_file: &str) -> ClipMarker {
+        ClipMarker {
+            id,
+            recording_file: recording_file.to_string(),
+            timestamp_seconds: id as f64 * 10.0,
+            pre_roll_seconds: 5.0,
+            post_roll_seconds: 0.0,
+            label: None,
+            source: ClipSource::Hotkey,
+            clip_path: None,
+            skipped: false,
+        }
+    }
+
+    #[test]
+    fn test_only_uncut_markers_are_pending() {
+        let dir = std::env::temp_dir().join("buckwheat_clip_markers_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Game_1.mp4").to_string_lossy().to_string();
+
+        let mut file = ClipMarkerFile::default();
+        file.merge([marker(1, &video), marker(2, &video)]);
+        assert_eq!(file.next_id(), 3);
+        file.write(&video).unwrap();
+        assert_eq!(find_pending(&dir), vec![video.clone()]);
+
+        // The first clip was cut before the app closed
+        let mut file = ClipMarkerFile::load(&video).unwrap();
+        file.set_clip(&[1], "/clips/Clip_1_001.mp4");
+        file.merge([marker(2, &video)]);
+        assert_eq!(file.markers.len(), 2);
+        assert_eq!(file.pending().len(), 1);
+        assert_eq!(file.pending()[0].id, 2);
+
+        // The second was past the end of the recording
+        file.set_skipped(&[2]);
+        file.write(&video).unwrap();
+        assert!(find_pending(&dir).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
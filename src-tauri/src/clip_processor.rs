@@ -1,7 +1,29 @@
 use crate::commands::errors::Error;
+use crate::jobs;
+use crate::recorder::segmented::SegmentRange;
 use ffmpeg_sidecar::command::FfmpegCommand;
 use ffmpeg_sidecar::download::auto_download;
+use ffmpeg_sidecar::event::FfmpegEvent;
+use ffmpeg_sidecar::ffprobe::ffprobe_path;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::process::Command;
+
+mod smart_cut;
+
+/// How clips are cut out of recordings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipCutMode {
+    /// Stream copy; fastest, but starts on the keyframe before the cut
+    Copy,
+    /// Re-encode just the GOPs at either end and copy the rest
+    #[default]
+    Smart,
+    /// Re-encode the whole clip
+    Reencode,
+}
 
 /// Ensures FFmpeg is available, downloading if necessary
 pub fn ensure_ffmpeg() -> Result<(), Error> {
@@ -25,6 +47,62 @@ pub fn extract_clip(
         duration
     );
 
+    prepare_paths(input_path, output_path)?;
+
+    // Build FFmpeg command
+    let mut command = FfmpegCommand::new();
+    command
+        .arg("-ss")
+        .arg(start_time.to_string())
+        .arg("-i")
+        .arg(input_path);
+    // An infinite duration runs to the end of the input
+    if duration.is_finite() {
+        command.arg("-t").arg(duration.to_string());
+    }
+    command
+        .arg("-c")
+        .arg("copy")
+        .arg("-avoid_negative_ts")
+        .arg("1")
+        .arg("-y") // Overwrite output file
+        .arg(output_path);
+    run_ffmpeg(&mut command, duration.is_finite().then_some(duration))?;
+    log::info!("✅ Clip extracted successfully: {}", output_path);
+    Ok(())
+}
+
+/// Extract a clip, cutting it as precisely as `mode` allows. A smart cut that
+/// fails falls back to re-encoding the whole clip.
+pub fn cut_clip(
+    input_path: &str,
+    output_path: &str,
+    start_time: f64,
+    duration: f64,
+    mode: ClipCutMode,
+) -> Result<(), Error> {
+    if mode == ClipCutMode::Copy {
+        return extract_clip(input_path, output_path, start_time, duration);
+    }
+    prepare_paths(input_path, output_path)?;
+
+    if mode == ClipCutMode::Smart {
+        match smart_cut::smart_cut(input_path, output_path, start_time, duration) {
+            Ok(()) => {
+                log::info!("✅ Clip smart cut successfully: {}", output_path);
+                return Ok(());
+            }
+            Err(Error::Cancelled) => return Err(Error::Cancelled),
+            Err(e) => log::warn!("⚠️ Smart cut failed, re-encoding the clip instead: {:?}", e),
+        }
+    }
+    smart_cut::reencode(input_path, output_path, start_time, duration)?;
+    log::info!("✅ Clip re-encoded successfully: {}", output_path);
+    Ok(())
+}
+
+/// Check the input exists and make the output's directory
+fn prepare_paths(input_path: &str, output_path: &str) -> Result<(), Error> {
     // Ensure input file exists
     if !Path::new(input_path).exists() {
         return Err(Error::InvalidPath(format!(
@@ -39,19 +117,134 @@ pub fn extract_clip(
             Error::RecordingFailed(format!("Failed to create output directory: {}", e))
         })?;
     }
+    Ok(())
+}
+
+/// Extract a clip that spans several segments of a recording, cutting each
+/// piece and joining them
+pub fn extract_spanning_clip(
+    pieces: &[SegmentRange],
+    output_path: &str,
+    mode: ClipCutMode,
+) -> Result<(), Error> {
+    match pieces {
+        [] => Err(Error::InvalidPath(
+            "Clip is outside the recording".to_string(),
+        )),
+        [piece] => cut_clip(
+            &piece.path,
+            output_path,
+            piece.start_seconds,
+            piece.duration_seconds,
+            mode,
+        ),
+        _ => {
+            let extension = Path::new(output_path)
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("mp4");
+            let mut piece_paths = Vec::new();
+            let mut outcome = Ok(());
+            for (i, piece) in pieces.iter().enumerate() {
+                let piece_path = format!("{}.piece{}.{}", output_path, i, extension);
+                outcome = cut_clip(
+                    &piece.path,
+                    &piece_path,
+                    piece.start_seconds,
+                    piece.duration_seconds,
+                    mode,
+                );
+                piece_paths.push(piece_path);
+                if outcome.is_err() {
+                    break;
+                }
+            }
+            if outcome.is_ok() {
+                outcome = concat_videos(&piece_paths, output_path);
+            }
+
+            for piece_path in &piece_paths {
+                let _ = std::fs::remove_file(piece_path);
+            }
+            outcome
+        }
+    }
+}
+
+/// Join videos with identical encoding settings into one file, without re-encoding
+pub fn concat_videos(input_paths: &[String], output_path: &str) -> Result<(), Error> {
+    log::info!(
+        "🧵 Concatenating {} video(s) into {}",
+        input_paths.len(),
+        output_path
+    );
+
+    for input in input_paths {
+        if !Path::new(input).exists() {
+            return Err(Error::InvalidPath(format!(
+                "Input file does not exist: {}",
+                input
+            )));
+        }
+    }
+
+    // Ensure output directory exists
+    if let Some(parent) = Path::new(output_path).parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            Error::RecordingFailed(format!("Failed to create output directory: {}", e))
+        })?;
+    }
+
+    // The concat demuxer reads its inputs from a list file
+    let list_path = format!("{}.concat.txt", output_path);
+    let list = input_paths
+        .iter()
+        .map(|p| format!("file '{}'\n", p.replace('\'', "'\\''")))
+        .collect::<String>();
+    std::fs::write(&list_path, list)
+        .map_err(|e| Error::RecordingFailed(format!("Failed to write concat list: {}", e)))?;
+
+    let mut command = FfmpegCommand::new();
+    command
+        .arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
+        .arg("-i")
+        .arg(&list_path)
+        .arg("-c")
+        .arg("copy")
+        .arg("-y") // Overwrite output file
+        .arg(output_path);
+    let outcome = run_ffmpeg(&mut command, None);
+    if outcome.is_ok() {
+        log::info!("✅ Videos concatenated successfully: {}", output_path);
+    }
+
+    let _ = std::fs::remove_file(&list_path);
+    outcome
+}
+
+/// Copy a video's streams into a fresh container without re-encoding, which
+/// rebuilds the index of a file whose recording was cut off
+pub fn remux_video(input_path: &str, output_path: &str) -> Result<(), Error> {
+    log::info!("🩹 Remuxing {} into {}", input_path, output_path);
+
+    if !Path::new(input_path).exists() {
+        return Err(Error::InvalidPath(format!(
+            "Input file does not exist: {}",
+            input_path
+        )));
+    }
 
-    // Build FFmpeg command
     let result = FfmpegCommand::new()
-        .arg("-ss")
-        .arg(start_time.to_string())
+        // Keep going past the truncated end of the file
+        .arg("-err_detect")
+        .arg("ignore_err")
         .arg("-i")
         .arg(input_path)
-        .arg("-t")
-        .arg(duration.to_string())
         .arg("-c")
         .arg("copy")
-        .arg("-avoid_negative_ts")
-        .arg("1")
         .arg("-y") // Overwrite output file
         .arg(output_path)
         .spawn();
@@ -63,7 +256,7 @@ pub fn extract_clip(
                 .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
 
             if status.success() {
-                log::info!("✅ Clip extracted successfully: {}", output_path);
+                log::info!("✅ Video remuxed successfully: {}", output_path);
                 Ok(())
             } else {
                 Err(Error::RecordingFailed(format!(
@@ -79,6 +272,134 @@ pub fn extract_clip(
     }
 }
 
+/// Times in `[start, start + duration)` of a video where a black screen ends,
+/// in seconds from the start of the video
+pub fn detect_black_ends(video_path: &str, start: f64, duration: f64) -> Result<Vec<f64>, Error> {
+    if !Path::new(video_path).exists() {
+        return Err(Error::InvalidPath(format!(
+            "Video file does not exist: {}",
+            video_path
+        )));
+    }
+
+    let mut child = FfmpegCommand::new()
+        .arg("-ss")
+        .arg(start.to_string())
+        .arg("-t")
+        .arg(duration.to_string())
+        .arg("-i")
+        .arg(video_path)
+        .arg("-an")
+        .arg("-vf")
+        .arg("blackdetect=d=0.05:pix_th=0.10")
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .spawn()
+        .map_err(|e| Error::RecordingFailed(format!("Failed to spawn FFmpeg: {}", e)))?;
+
+    // blackdetect reports through the log, as `black_start:.. black_end:..`
+    let mut ends = Vec::new();
+    if let Some(stderr) = child.take_stderr() {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            ends.extend(parse_black_end(&line).map(|end| start + end));
+        }
+    }
+    let status = child
+        .wait()
+        .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
+    if !status.success() {
+        return Err(Error::RecordingFailed(format!(
+            "FFmpeg failed with status: {:?}",
+            status
+        )));
+    }
+    Ok(ends)
+}
+
+/// The `black_end` time in a line of blackdetect output
+fn parse_black_end(line: &str) -> Option<f64> {
+    line.split_whitespace()
+        .find_map(|field| field.strip_prefix("black_end:"))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Length of a video in seconds, as ffprobe reports it
+pub fn probe_duration(video_path: &str) -> Result<f64, Error> {
+    let output = run_ffprobe(&[
+        "-v",
+        "error",
+        "-show_entries",
+        "format=duration",
+        "-of",
+        "default=noprint_wrappers=1:nokey=1",
+        video_path,
+    ])?;
+    output
+        .trim()
+        .parse()
+        .map_err(|_| Error::RecordingFailed(format!("Couldn't read the length of {}", video_path)))
+}
+
+/// Run ffprobe, returning what it printed
+fn run_ffprobe(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new(ffprobe_path())
+        .args(args)
+        .output()
+        .map_err(|e| Error::RecordingFailed(format!("Failed to run ffprobe: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::RecordingFailed(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// A stretch of a recording to cut into one clip
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipWindow {
+    pub start: f64,
+    pub end: f64,
+    /// Indexes of the markers it covers
+    pub markers: Vec<usize>,
+}
+
+impl ClipWindow {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Clamp each marker's `(start, end)` to the recording and merge the ones
+/// that overlap, so nearby markers make one clip instead of near-duplicates
+pub fn merge_clip_windows(windows: &[(f64, f64)], length: Option<f64>) -> Vec<ClipWindow> {
+    let mut order: Vec<usize> = (0..windows.len()).collect();
+    order.sort_by(|&a, &b| windows[a].0.total_cmp(&windows[b].0));
+
+    let mut merged: Vec<ClipWindow> = Vec::new();
+    for i in order {
+        let (start, end) = windows[i];
+        let start = start.max(0.0);
+        let end = length.map_or(end, |length| end.min(length));
+        if end <= start {
+            continue;
+        }
+        match merged.last_mut() {
+            Some(last) if start <= last.end => {
+                last.end = last.end.max(end);
+                last.markers.push(i);
+            }
+            _ => merged.push(ClipWindow {
+                start,
+                end,
+                markers: vec![i],
+            }),
+        }
+    }
+    merged
+}
+
 /// Generate a thumbnail image from a video file
 /// Extracts a frame at the specified time (default: 1 second) and saves as JPEG
 pub fn generate_thumbnail(
@@ -87,7 +408,7 @@ pub fn generate_thumbnail(
     time_offset: Option<f64>,
 ) -> Result<(), Error> {
     let offset = time_offset.unwrap_or(1.0); // Default to 1 second into video
-    
+
     log::debug!(
         "🖼️  Generating thumbnail: video={}, output={}, offset={}s",
         video_path,
@@ -116,7 +437,8 @@ pub fn generate_thumbnail(
     // -vframes 1: extract only 1 frame
     // -vf scale=320:-1: scale to 320px width, maintain aspect ratio
     // -q:v 2: high quality JPEG (lower = better quality, 2-5 is good)
-    let result = FfmpegCommand::new()
+    let mut command = FfmpegCommand::new();
+    command
         .arg("-ss")
         .arg(offset.to_string())
         .arg("-i")
@@ -128,28 +450,109 @@ pub fn generate_thumbnail(
         .arg("-q:v")
         .arg("2")
         .arg("-y") // Overwrite output file
-        .arg(thumbnail_path)
-        .spawn();
+        .arg(thumbnail_path);
+    run_ffmpeg(&mut command, None)?;
+    log::debug!("✅ Thumbnail generated successfully: {}", thumbnail_path);
+    Ok(())
+}
 
-    match result {
-        Ok(mut child) => {
-            let status = child
-                .wait()
-                .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
+/// Run an FFmpeg command to the end. Inside a job, progress through
+/// `expected_seconds` of output is reported, and cancelling the job kills
+/// FFmpeg and gives `Error::Cancelled`.
+pub fn run_ffmpeg(command: &mut FfmpegCommand, expected_seconds: Option<f64>) -> Result<(), Error> {
+    let job = jobs::current();
+    let mut child = command
+        .spawn()
+        .map_err(|e| Error::RecordingFailed(format!("Failed to spawn FFmpeg: {}", e)))?;
 
-            if status.success() {
-                log::debug!("✅ Thumbnail generated successfully: {}", thumbnail_path);
-                Ok(())
-            } else {
-                Err(Error::RecordingFailed(format!(
-                    "FFmpeg failed with status: {:?}",
-                    status
-                )))
+    if let Some(job) = &job {
+        let events = child
+            .iter()
+            .map_err(|e| Error::RecordingFailed(format!("Failed to read FFmpeg output: {}", e)))?;
+        for event in events {
+            if job.is_cancelled() {
+                break;
+            }
+            match event {
+                FfmpegEvent::Progress(progress) => {
+                    let seconds = parse_progress_time(&progress.time);
+                    if let (Some(seconds), Some(expected)) = (seconds, expected_seconds) {
+                        if expected > 0.0 {
+                            job.report(seconds / expected);
+                        }
+                    }
+                }
+                FfmpegEvent::Error(e) => log::warn!("⚠️ FFmpeg: {}", e),
+                _ => {}
             }
         }
-        Err(e) => Err(Error::RecordingFailed(format!(
-            "Failed to spawn FFmpeg: {}",
-            e
-        ))),
+        if job.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Error::Cancelled);
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::RecordingFailed(format!(
+            "FFmpeg failed with status: {:?}",
+            status
+        )))
+    }
+}
+
+/// Seconds from FFmpeg's `HH:MM:SS.xx` progress time
+fn parse_progress_time(time: &str) -> Option<f64> {
+    let mut parts = time.trim().splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping_markers_make_one_clip() {
+        let windows = merge_clip_windows(
+            &[(50.0, 65.0), (-5.0, 10.0), (40.0, 55.0), (95.0, 110.0)],
+            Some(100.0),
+        );
+        assert_eq!(
+            windows,
+            vec![
+                ClipWindow {
+                    start: 0.0,
+                    end: 10.0,
+                    markers: vec![1]
+                },
+                ClipWindow {
+                    start: 40.0,
+                    end: 65.0,
+                    markers: vec![2, 0]
+                },
+                ClipWindow {
+                    start: 95.0,
+                    end: 100.0,
+                    markers: vec![3]
+                },
+            ]
+        );
+        // Past the end of the recording there's nothing to cut
+        assert!(merge_clip_windows(&[(120.0, 130.0)], Some(100.0)).is_empty());
+    }
+
+    #[test]
+    fn test_parse_progress_time() {
+        assert_eq!(parse_progress_time("00:01:02.50"), Some(62.5));
+        assert_eq!(parse_progress_time("01:00:00.00"), Some(3600.0));
+        assert_eq!(parse_progress_time("N/A"), None);
     }
 }
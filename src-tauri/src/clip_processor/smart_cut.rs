@@ -0,0 +1,459 @@
+// Frame-accurate clips without re-encoding all of them
+//
+// A stream copy can only start on a keyframe, so a copied clip starts up to a
+// GOP early. Smart cutting re-encodes from the clip's start to the first
+// keyframe inside it and from the last keyframe inside it to the end, copies
+// everything in between, and joins the three. The re-encoded ends use the
+// source's codec, profile and format, with their headers repeated in-band, so
+// the join doesn't need another pass. Encoders don't always agree on the rest,
+// so the joined clip is decoded once to check it, and `cut_clip` re-encodes
+// the whole clip if it doesn't play.
+
+use super::{concat_videos, run_ffmpeg, run_ffprobe};
+use crate::commands::errors::Error;
+use ffmpeg_sidecar::command::FfmpegCommand;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Keyframes this close to a cut count as being on it
+const EPSILON: f64 = 0.001;
+/// Anything copied shorter than this isn't worth the extra pieces
+const MIN_COPY_SECONDS: f64 = 1.0;
+
+/// A stretch of the clip and how it's made
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Piece {
+    Encode { start: f64, end: f64 },
+    Copy { start: f64, end: f64 },
+}
+
+/// What the re-encoded pieces have to match
+#[derive(Debug, Default)]
+struct SourceFormat {
+    video: Option<ProbedStream>,
+    audio: Option<ProbedStream>,
+    duration: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ProbedStream {
+    codec_type: String,
+    codec_name: String,
+    profile: Option<String>,
+    level: Option<i64>,
+    pix_fmt: Option<String>,
+    r_frame_rate: Option<String>,
+    time_base: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbedStream>,
+    format: Option<ProbedFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbedFormat {
+    duration: Option<String>,
+}
+
+/// Cut `[start, start + duration)` re-encoding only the ends
+pub(super) fn smart_cut(
+    input_path: &str,
+    output_path: &str,
+    start: f64,
+    duration: f64,
+) -> Result<(), Error> {
+    let format = probe_format(input_path)?;
+    let end = clip_end(&format, start, duration)?;
+    let keyframes = probe_keyframes(input_path, start, end)?;
+    let pieces = plan_pieces(start, end, &keyframes);
+    log::info!(
+        "✂️ Smart cutting {:.3}s-{:.3}s of {} in {} piece(s)",
+        start,
+        end,
+        input_path,
+        pieces.len()
+    );
+
+    if let [Piece::Encode { start, end }] = pieces[..] {
+        return encode_piece(input_path, output_path, start, end, &format);
+    }
+
+    let extension = Path::new(output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("mp4");
+    let mut piece_paths = Vec::new();
+    let mut outcome = Ok(());
+    for (i, piece) in pieces.iter().enumerate() {
+        let piece_path = format!("{}.cut{}.{}", output_path, i, extension);
+        outcome = match *piece {
+            Piece::Encode { start, end } => {
+                encode_piece(input_path, &piece_path, start, end, &format)
+            }
+            Piece::Copy { start, end } => copy_piece(input_path, &piece_path, start, end),
+        };
+        piece_paths.push(piece_path);
+        if outcome.is_err() {
+            break;
+        }
+    }
+    if outcome.is_ok() {
+        outcome =
+            concat_videos(&piece_paths, output_path).and_then(|()| check_decodes(output_path));
+    }
+
+    for piece_path in &piece_paths {
+        let _ = std::fs::remove_file(piece_path);
+    }
+    outcome
+}
+
+/// Cut `[start, start + duration)` re-encoding all of it
+pub(super) fn reencode(
+    input_path: &str,
+    output_path: &str,
+    start: f64,
+    duration: f64,
+) -> Result<(), Error> {
+    // Without a probe, encode to the app's usual H.264 and AAC
+    let format = probe_format(input_path).unwrap_or_default();
+    let end = clip_end(&format, start, duration)?;
+    log::info!("✂️ Re-encoding {:.3}s-{:.3}s of {}", start, end, input_path);
+    encode_piece(input_path, output_path, start, end, &format)
+}
+
+/// Split the clip at the first and last keyframes inside it
+fn plan_pieces(start: f64, end: f64, keyframes: &[f64]) -> Vec<Piece> {
+    let inside = || {
+        keyframes
+            .iter()
+            .copied()
+            .filter(|&k| k >= start - EPSILON && k <= end + EPSILON)
+    };
+    let (Some(first), Some(last)) = (
+        inside().min_by(f64::total_cmp),
+        inside().max_by(f64::total_cmp),
+    ) else {
+        return vec![Piece::Encode { start, end }];
+    };
+    if last - first < MIN_COPY_SECONDS {
+        return vec![Piece::Encode { start, end }];
+    }
+
+    let mut pieces = Vec::new();
+    if first > start + EPSILON {
+        pieces.push(Piece::Encode { start, end: first });
+    }
+    pieces.push(Piece::Copy {
+        start: first,
+        end: last,
+    });
+    if end > last + EPSILON {
+        pieces.push(Piece::Encode { start: last, end });
+    }
+    pieces
+}
+
+/// An infinite duration runs to the end of the input
+fn clip_end(format: &SourceFormat, start: f64, duration: f64) -> Result<f64, Error> {
+    if duration.is_finite() {
+        return Ok(start + duration);
+    }
+    format
+        .duration
+        .ok_or_else(|| Error::RecordingFailed("Couldn't read the video's length".to_string()))
+}
+
+fn probe_format(input_path: &str) -> Result<SourceFormat, Error> {
+    let output = run_ffprobe(&[
+        "-v",
+        "error",
+        "-show_entries",
+        "stream=codec_type,codec_name,profile,level,pix_fmt,r_frame_rate,time_base,sample_rate,channels:format=duration",
+        "-of",
+        "json",
+        input_path,
+    ])?;
+    let probed: ProbeOutput = serde_json::from_str(&output)
+        .map_err(|e| Error::RecordingFailed(format!("Failed to read ffprobe output: {}", e)))?;
+
+    let mut format = SourceFormat {
+        duration: probed
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse().ok()),
+        ..SourceFormat::default()
+    };
+    for stream in probed.streams {
+        match stream.codec_type.as_str() {
+            "video" if format.video.is_none() => format.video = Some(stream),
+            "audio" if format.audio.is_none() => format.audio = Some(stream),
+            _ => {}
+        }
+    }
+    Ok(format)
+}
+
+/// Keyframe times of the first video stream between `start` and `end`
+fn probe_keyframes(input_path: &str, start: f64, end: f64) -> Result<Vec<f64>, Error> {
+    let interval = format!("{:.3}%{:.3}", (start - 1.0).max(0.0), end + 1.0);
+    let output = run_ffprobe(&[
+        "-v",
+        "error",
+        "-select_streams",
+        "v:0",
+        "-read_intervals",
+        &interval,
+        "-show_entries",
+        "packet=pts_time,flags",
+        "-of",
+        "csv=p=0",
+        input_path,
+    ])?;
+    Ok(parse_keyframes(&output))
+}
+
+/// Times of the packets flagged `K` in ffprobe's `pts_time,flags` CSV
+fn parse_keyframes(output: &str) -> Vec<f64> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (time, flags) = line.trim().split_once(',')?;
+            flags
+                .starts_with('K')
+                .then(|| time.parse::<f64>().ok())
+                .flatten()
+        })
+        .collect()
+}
+
+/// Stream-copy from the keyframe at `start` up to `end`
+fn copy_piece(input_path: &str, output_path: &str, start: f64, end: f64) -> Result<(), Error> {
+    let mut command = FfmpegCommand::new();
+    command
+        .args(["-ss", &start.to_string(), "-i", input_path])
+        .args(["-t", &(end - start).to_string()])
+        .args(["-map", "0:v:0", "-map", "0:a:0?", "-c", "copy"])
+        .args(["-avoid_negative_ts", "make_zero", "-y", output_path]);
+    run_ffmpeg(&mut command, Some(end - start))
+}
+
+/// Decode from `start` and encode up to `end` the way the source was encoded
+fn encode_piece(
+    input_path: &str,
+    output_path: &str,
+    start: f64,
+    end: f64,
+    format: &SourceFormat,
+) -> Result<(), Error> {
+    let mut command = FfmpegCommand::new();
+    command
+        .args(["-ss", &start.to_string(), "-i", input_path])
+        .args(["-t", &(end - start).to_string()])
+        .args(["-map", "0:v:0", "-map", "0:a:0?"]);
+
+    let video = format.video.as_ref();
+    let codec = video.map_or("h264", |v| v.codec_name.as_str());
+    let profile = video.and_then(|v| v.profile.as_deref());
+    match codec {
+        "h264" => {
+            command.args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "16"]);
+            command.args(["-x264-params", "repeat-headers=1"]);
+            if let Some(profile) = profile.and_then(x264_profile) {
+                command.args(["-profile:v", profile]);
+            }
+            // ffprobe gives H.264 levels times ten
+            if let Some(level) = video.and_then(|v| v.level).filter(|&level| level > 0) {
+                command.args(["-level:v", &format!("{}.{}", level / 10, level % 10)]);
+            }
+            &mut command
+        }
+        "hevc" => {
+            command.args([
+                "-c:v", "libx265", "-preset", "veryfast", "-crf", "18", "-tag:v", "hvc1",
+            ]);
+            command.args(["-x265-params", "repeat-headers=1"]);
+            if let Some(profile) = profile.and_then(x265_profile) {
+                command.args(["-profile:v", profile]);
+            }
+            &mut command
+        }
+        "av1" => command.args(["-c:v", "libsvtav1", "-preset", "8", "-crf", "24"]),
+        other => {
+            return Err(Error::RecordingFailed(format!(
+                "Can't re-encode {} video",
+                other
+            )))
+        }
+    };
+    if let Some(pix_fmt) = video.and_then(|v| v.pix_fmt.as_deref()) {
+        command.args(["-pix_fmt", pix_fmt]);
+    }
+    if let Some(rate) = video.and_then(|v| v.r_frame_rate.as_deref()) {
+        command.args(["-r", rate]);
+    }
+    // MP4 keeps its own timescale per track; matching it keeps the join's timestamps exact
+    let is_mp4 = Path::new(output_path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp4") || ext.eq_ignore_ascii_case("mov"));
+    if let Some(timescale) = video
+        .and_then(|v| v.time_base.as_deref())
+        .and_then(|tb| tb.strip_prefix("1/"))
+        .filter(|_| is_mp4)
+    {
+        command.args(["-video_track_timescale", timescale]);
+    }
+
+    let audio = format.audio.as_ref();
+    match audio.map_or("aac", |a| a.codec_name.as_str()) {
+        "aac" => command.args(["-c:a", "aac", "-b:a", "192k"]),
+        "opus" => command.args(["-c:a", "libopus", "-b:a", "160k"]),
+        other => {
+            return Err(Error::RecordingFailed(format!(
+                "Can't re-encode {} audio",
+                other
+            )))
+        }
+    };
+    if let Some(sample_rate) = audio.and_then(|a| a.sample_rate.as_deref()) {
+        command.args(["-ar", sample_rate]);
+    }
+    if let Some(channels) = audio.and_then(|a| a.channels) {
+        command.args(["-ac", &channels.to_string()]);
+    }
+
+    command.args(["-y", output_path]);
+    run_ffmpeg(&mut command, Some(end - start))
+}
+
+/// x264's name for an H.264 profile as ffprobe reports it
+fn x264_profile(profile: &str) -> Option<&'static str> {
+    match profile {
+        "Baseline" | "Constrained Baseline" => Some("baseline"),
+        "Main" => Some("main"),
+        "High" => Some("high"),
+        "High 10" => Some("high10"),
+        "High 4:2:2" => Some("high422"),
+        "High 4:4:4 Predictive" => Some("high444"),
+        _ => None,
+    }
+}
+
+/// x265's name for an HEVC profile as ffprobe reports it
+fn x265_profile(profile: &str) -> Option<&'static str> {
+    match profile {
+        "Main" => Some("main"),
+        "Main 10" => Some("main10"),
+        _ => None,
+    }
+}
+
+/// Decode the whole of a joined clip; pieces whose headers don't agree join
+/// without complaint but don't play
+fn check_decodes(path: &str) -> Result<(), Error> {
+    let mut child = FfmpegCommand::new()
+        .args([
+            "-nostats", "-v", "error", "-i", path, "-map", "0:v:0", "-f", "null", "-",
+        ])
+        .spawn()
+        .map_err(|e| Error::RecordingFailed(format!("Failed to spawn FFmpeg: {}", e)))?;
+
+    let mut errors = Vec::new();
+    if let Some(stderr) = child.take_stderr() {
+        errors.extend(
+            BufReader::new(stderr)
+                .lines()
+                .map_while(Result::ok)
+                .filter(|line| !line.trim().is_empty()),
+        );
+    }
+    let status = child
+        .wait()
+        .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
+    if !status.success() || !errors.is_empty() {
+        return Err(Error::RecordingFailed(format!(
+            "The joined clip doesn't decode: {}",
+            errors.first().map_or("FFmpeg failed", String::as_str)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smart_cut_clip_plays_through() {
+        if !ffmpeg_sidecar::command::ffmpeg_is_installed() || run_ffprobe(&["-version"]).is_err() {
+            return;
+        }
+
+        let dir = std::env::temp_dir().join("buckwheat_smart_cut_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.mp4").to_string_lossy().to_string();
+        let clip = dir.join("clip.mp4").to_string_lossy().to_string();
+
+        // Keyframes every two seconds, like a recording
+        let mut command = FfmpegCommand::new();
+        command
+            .args(["-f", "lavfi", "-i", "testsrc=size=320x240:rate=30"])
+            .args(["-f", "lavfi", "-i", "sine=frequency=440:sample_rate=48000"])
+            .args([
+                "-t", "8", "-c:v", "libx264", "-g", "60", "-pix_fmt", "yuv420p",
+            ])
+            .args(["-c:a", "aac", "-y", &source]);
+        run_ffmpeg(&mut command, None).unwrap();
+
+        smart_cut(&source, &clip, 1.5, 4.0).unwrap();
+        check_decodes(&clip).unwrap();
+        let duration = crate::clip_processor::probe_duration(&clip).unwrap();
+        assert!((duration - 4.0).abs() < 0.1, "clip is {}s", duration);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reencodes_only_the_ends() {
+        let keyframes = parse_keyframes(
+            "0.000000,K__\n0.033333,___\n2.000000,K__\n4.000000,K_\n6.000000,K__\n8.000000,K__\n",
+        );
+        assert_eq!(keyframes, vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+
+        assert_eq!(
+            plan_pieces(1.5, 7.25, &keyframes),
+            vec![
+                Piece::Encode {
+                    start: 1.5,
+                    end: 2.0
+                },
+                Piece::Copy {
+                    start: 2.0,
+                    end: 6.0
+                },
+                Piece::Encode {
+                    start: 6.0,
+                    end: 7.25
+                },
+            ]
+        );
+        // Starting on a keyframe needs no head
+        assert_eq!(plan_pieces(2.0, 6.0, &keyframes).len(), 1);
+        // Too little to copy: encode the lot
+        assert_eq!(
+            plan_pieces(2.5, 4.5, &keyframes),
+            vec![Piece::Encode {
+                start: 2.5,
+                end: 4.5
+            }]
+        );
+    }
+}
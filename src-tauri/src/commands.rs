@@ -1,5 +1,7 @@
 pub mod cloud;
 pub mod default;
 pub mod errors;
+pub mod jobs;
+pub mod replay_buffer;
 pub mod settings;
 pub mod slippi;
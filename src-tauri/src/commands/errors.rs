@@ -16,6 +16,12 @@ pub enum Error {
     WindowNotFound,
     #[error("Recording failed: {0}")]
     RecordingFailed(String),
+    #[error("Invalid game transition: {0}")]
+    InvalidGameTransition(String),
+    #[error("Unsupported recording profile: {0}")]
+    UnsupportedRecordingProfile(String),
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 #[derive(serde::Serialize)]
@@ -30,6 +36,9 @@ enum ErrorName {
     InitializationError(String),
     WindowNotFound(String),
     RecordingFailed(String),
+    InvalidGameTransition(String),
+    UnsupportedRecordingProfile(String),
+    Cancelled(String),
 }
 
 impl serde::Serialize for Error {
@@ -47,6 +56,9 @@ impl serde::Serialize for Error {
             Self::InitializationError(_) => ErrorName::InitializationError(message),
             Self::WindowNotFound => ErrorName::WindowNotFound(message),
             Self::RecordingFailed(_) => ErrorName::RecordingFailed(message),
+            Self::InvalidGameTransition(_) => ErrorName::InvalidGameTransition(message),
+            Self::UnsupportedRecordingProfile(_) => ErrorName::UnsupportedRecordingProfile(message),
+            Self::Cancelled => ErrorName::Cancelled(message),
         };
         name.serialize(serializer)
     }
@@ -0,0 +1,14 @@
+use crate::jobs::{JobId, JobInfo, JobQueue};
+use tauri::State;
+
+/// FFmpeg jobs queued, running and recently finished
+#[tauri::command]
+pub fn list_jobs(jobs: State<'_, JobQueue>) -> Vec<JobInfo> {
+    jobs.list()
+}
+
+/// Cancel a queued or running job; false if it's already finished
+#[tauri::command]
+pub fn cancel_job(jobs: State<'_, JobQueue>, id: JobId) -> bool {
+    jobs.cancel(id)
+}
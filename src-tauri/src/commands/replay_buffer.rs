@@ -0,0 +1,171 @@
+use crate::app_state::AppState;
+use crate::commands::errors::Error;
+use crate::commands::slippi::{
+    configure_target_window_from_settings, get_clips_directory_internal,
+    hardware_encoder_from_settings, recorder_backend_from_settings, resolve_recording_profile,
+};
+use crate::recorder;
+use crate::recorder::replay_buffer::{self, ReplayBuffer, ReplayBufferConfig};
+use tauri::{Emitter, Manager, State};
+
+/// How often the buffer picks up finished segments and drops old ones
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+fn read_buffer_seconds(app: &tauri::AppHandle) -> u64 {
+    use tauri_plugin_store::StoreExt;
+
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get("replayBufferSeconds"))
+        .and_then(|v| v.as_u64())
+        .filter(|&seconds| seconds > 0)
+        .unwrap_or(replay_buffer::DEFAULT_BUFFER_SECONDS)
+}
+
+/// Start keeping the last few seconds of gameplay in a rolling buffer
+#[tauri::command]
+pub async fn start_replay_buffer(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), Error> {
+    let profile = resolve_recording_profile(&app);
+    let buffer_seconds = read_buffer_seconds(&app);
+
+    {
+        let mut buffer_lock = state.replay_buffer.lock().map_err(|e| {
+            Error::InitializationError(format!("Failed to lock replay buffer: {}", e))
+        })?;
+        if buffer_lock.as_ref().is_some_and(|b| b.is_running()) {
+            return Err(Error::RecordingFailed(
+                "Replay buffer is already running".to_string(),
+            ));
+        }
+
+        let dir = replay_buffer::default_buffer_dir();
+        replay_buffer::clear_buffer_dir(&dir);
+
+        configure_target_window_from_settings(&state);
+        let backend = recorder_backend_from_settings(&app);
+        let hardware = hardware_encoder_from_settings(&app);
+        let mut buffer = ReplayBuffer::new(
+            recorder::create_recorder(backend, hardware),
+            ReplayBufferConfig {
+                buffer_seconds,
+                segment_seconds: replay_buffer::DEFAULT_SEGMENT_SECONDS,
+                dir,
+                profile,
+            },
+        );
+        // Nothing's lost between segments when FFmpeg splits its own capture
+        if recorder::records_with_ffmpeg(backend) {
+            buffer = buffer.with_segment_muxer(hardware);
+        }
+        buffer.start()?;
+        *buffer_lock = Some(buffer);
+    }
+
+    // Keep pruning segments until the buffer is stopped. This deletes files, so
+    // it gets a thread of its own rather than a spot on the async runtime.
+    let app_handle = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK_INTERVAL);
+
+        let state = app_handle.state::<AppState>();
+        let Ok(mut buffer_lock) = state.replay_buffer.lock() else {
+            break;
+        };
+        let Some(buffer) = buffer_lock.as_mut().filter(|b| b.is_running()) else {
+            break;
+        };
+        if let Err(e) = buffer.tick() {
+            log::error!("❌ Replay buffer failed, stopping it: {:?}", e);
+            let _ = buffer.stop();
+            *buffer_lock = None;
+            break;
+        }
+    });
+
+    if let Err(e) = app.emit("replay-buffer-started", buffer_seconds) {
+        log::error!("Failed to emit replay-buffer-started event: {:?}", e);
+    }
+    Ok(())
+}
+
+/// Stop the replay buffer and discard whatever it was holding
+#[tauri::command]
+pub async fn stop_replay_buffer(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), Error> {
+    let buffer = state
+        .replay_buffer
+        .lock()
+        .map_err(|e| Error::InitializationError(format!("Failed to lock replay buffer: {}", e)))?
+        .take();
+    let Some(mut buffer) = buffer else {
+        return Err(Error::RecordingFailed(
+            "Replay buffer is not running".to_string(),
+        ));
+    };
+
+    // Waits for FFmpeg to finish its last segment
+    tauri::async_runtime::spawn_blocking(move || buffer.stop())
+        .await
+        .map_err(|e| {
+            Error::RecordingFailed(format!("Stopping the replay buffer panicked: {}", e))
+        })??;
+    if let Err(e) = app.emit("replay-buffer-stopped", ()) {
+        log::error!("Failed to emit replay-buffer-stopped event: {:?}", e);
+    }
+    Ok(())
+}
+
+/// Save the buffered footage as a clip, e.g. from the clip hotkey
+#[tauri::command]
+pub async fn save_replay_buffer(app: tauri::AppHandle) -> Result<String, Error> {
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string();
+    save_replay_buffer_as(&app, &format!("Replay_{}.mp4", timestamp)).await
+}
+
+/// Save the buffered footage to `<clips dir>/<filename>`
+pub(crate) async fn save_replay_buffer_as(
+    app: &tauri::AppHandle,
+    filename: &str,
+) -> Result<String, Error> {
+    crate::clip_processor::ensure_ffmpeg()?;
+
+    let output_path = get_clips_directory_internal(app).await?.join(filename);
+    let output_path = output_path.to_string_lossy().to_string();
+
+    // Only the snapshot needs the lock; the buffer keeps going while it's saved
+    let segments = {
+        let state = app.state::<AppState>();
+        let mut buffer_lock = state.replay_buffer.lock().map_err(|e| {
+            Error::InitializationError(format!("Failed to lock replay buffer: {}", e))
+        })?;
+        let buffer = buffer_lock
+            .as_mut()
+            .filter(|b| b.is_running())
+            .ok_or_else(|| Error::RecordingFailed("Replay buffer is not running".to_string()))?;
+        buffer.snapshot()?
+    };
+    let saved = tauri::async_runtime::spawn_blocking(move || {
+        replay_buffer::save_segments(&segments, &output_path)
+    })
+    .await
+    .map_err(|e| Error::RecordingFailed(format!("Saving the replay buffer panicked: {}", e)))??;
+
+    if let Err(e) = app.emit("clips-created", vec![saved.clone()]) {
+        log::error!("Failed to emit clips-created event: {:?}", e);
+    }
+    Ok(saved)
+}
+
+/// Whether the replay buffer is currently running
+pub(crate) fn is_replay_buffer_running(state: &AppState) -> bool {
+    state
+        .replay_buffer
+        .lock()
+        .map(|b| b.as_ref().is_some_and(|b| b.is_running()))
+        .unwrap_or(false)
+}
@@ -1,10 +1,16 @@
-use crate::app_state::AppState;
+use crate::app_state::{AppState, SharedRecorder};
 use crate::commands::errors::Error;
-use crate::game_detector::{slippi_paths, GameDetector};
+use crate::game_detector::state::{GamePhase, LifecycleEvent, Transition};
+use crate::game_detector::{self, slippi_paths, GameDetector};
+use crate::jobs::{JobHandle, JobKind, JobPriority, JobQueue};
 use crate::recorder;
+use crate::recorder::set::RecordingSet;
+use crate::slippi::GameEndReason;
 use base64::Engine as _;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use sysinfo::System;
 use tauri::{Emitter, Listener, Manager, State};
 use walkdir::WalkDir;
@@ -59,6 +65,22 @@ pub struct PlayerInfo {
     pub port: u8,
 }
 
+/// Payload of the `recording-stopped` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingStoppedEvent {
+    pub output_path: String,
+    /// Set when the recording was stopped because the game ended
+    pub end_reason: Option<GameEndReason>,
+}
+
+/// Payload of the `recording-degraded` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingDegradedEvent {
+    /// Share of frames dropped since the previous stats update
+    pub drop_rate: f64,
+    pub stats: recorder::RecorderStats,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RecordingSession {
     pub id: String,
@@ -70,58 +92,258 @@ pub struct RecordingSession {
     pub duration: Option<u64>,
     pub file_size: Option<u64>,
     pub slippi_metadata: Option<SlippiMetadata>,
+    /// Per-player tech-skill numbers, so the library can sort by them
+    pub tech_stats: Option<Vec<crate::slippi::TechStats>>,
+    /// The files of a segmented recording, in order. `video_path` is then the
+    /// recording as a whole, which only exists as a manifest
+    pub segments: Option<Vec<String>>,
+    /// Seconds into `video_path` the game starts, for the later games of a set,
+    /// which share the video of its first game
+    pub video_start_seconds: Option<f64>,
+    /// Where a clip came from; None for recordings
+    pub clip: Option<ClipMetadata>,
 }
 
-fn resolve_recording_quality(
-    state: &State<'_, AppState>,
-) -> Result<crate::recorder::RecordingQuality, Error> {
-    let settings = state
-        .settings
-        .lock()
-        .map_err(|e| Error::InitializationError(format!("Failed to lock settings: {}", e)))?;
+/// What a clip was cut from, kept next to it as `<clip>.clip.json` since its
+/// name doesn't lead back to the replay
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClipMetadata {
+    pub source_recording: String,
+    pub slp_path: Option<String>,
+    /// Replay frames the clip covers, when the recording is synced to its replay
+    pub start_frame: Option<i32>,
+    pub end_frame: Option<i32>,
+    /// Seconds into the source recording the clip starts
+    pub video_offset_seconds: f64,
+    pub duration_seconds: f64,
+    pub label: Option<String>,
+    pub players: Vec<PlayerInfo>,
+    pub stage: Option<u16>,
+}
 
-    let quality_str = settings
-        .get("recordingQuality")
-        .and_then(|v| v.as_str())
-        .unwrap_or("high");
-
-    let quality = match quality_str {
-        "low" => crate::recorder::RecordingQuality::Low,
-        "medium" => crate::recorder::RecordingQuality::Medium,
-        "high" => crate::recorder::RecordingQuality::High,
-        "ultra" => crate::recorder::RecordingQuality::Ultra,
-        _ => crate::recorder::RecordingQuality::High,
+impl ClipMetadata {
+    pub fn path_for(clip_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.clip.json", clip_path))
+    }
+
+    pub fn load(clip_path: &str) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path_for(clip_path)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn write(&self, clip_path: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            Error::RecordingFailed(format!("Failed to serialize clip metadata: {}", e))
+        })?;
+        std::fs::write(Self::path_for(clip_path), json)?;
+        Ok(())
+    }
+}
+
+/// The profile to record with: the custom `recordingProfile` if one is saved,
+/// otherwise the preset for `recordingQuality`
+pub(crate) fn resolve_recording_profile(app: &tauri::AppHandle) -> recorder::RecordingProfile {
+    use tauri_plugin_store::StoreExt;
+
+    let Ok(store) = app.store("settings.json") else {
+        return recorder::RecordingProfile::default();
     };
 
-    Ok(quality)
+    if let Some(value) = store.get("recordingProfile").filter(|v| !v.is_null()) {
+        match serde_json::from_value(value) {
+            Ok(profile) => return profile,
+            Err(e) => log::warn!("Ignoring invalid recording profile: {}", e),
+        }
+    }
+
+    let quality: recorder::RecordingQuality = store
+        .get("recordingQuality")
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    recorder::RecordingProfile::preset(quality)
+}
+
+/// Create the recorder picked in settings (`recordingBackend` and `ffmpegEncoder`)
+pub(crate) fn create_recorder_from_settings(
+    app: &tauri::AppHandle,
+) -> Box<dyn recorder::Recorder + Send> {
+    recorder::create_recorder(
+        recorder_backend_from_settings(app),
+        hardware_encoder_from_settings(app),
+    )
+}
+
+/// Capture implementation picked in settings
+pub(crate) fn recorder_backend_from_settings(app: &tauri::AppHandle) -> recorder::RecorderBackend {
+    use tauri_plugin_store::StoreExt;
+
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get("recordingBackend"))
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+/// GPU encoder FFmpeg uses for profiles that ask for hardware encoding
+pub(crate) fn hardware_encoder_from_settings(
+    app: &tauri::AppHandle,
+) -> recorder::ffmpeg::HardwareEncoder {
+    use tauri_plugin_store::StoreExt;
+
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get("ffmpegEncoder"))
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
 }
 
-fn start_recording_with_quality(
+fn start_recording_with_profile(
+    app: &tauri::AppHandle,
     state: &State<'_, AppState>,
     output_path: &str,
-    quality: crate::recorder::RecordingQuality,
+    slp_path: Option<&str>,
+    profile: &recorder::RecordingProfile,
 ) -> Result<(), Error> {
-    let mut recorder_lock = state
+    let recorder = state
         .recorder
         .lock()
-        .map_err(|e| Error::InitializationError(format!("Failed to lock recorder: {}", e)))?;
+        .map_err(|e| Error::InitializationError(format!("Failed to lock recorder: {}", e)))?
+        .get_or_insert_with(|| {
+            Arc::new(Mutex::new(Box::new(recorder::watchdog::Watchdog::new(
+                create_recorder_from_settings(app),
+            ))))
+        })
+        .clone();
 
-    if recorder_lock.is_none() {
-        *recorder_lock = Some(recorder::get_recorder());
+    lock_recorder(&recorder)?.start_recording(output_path, profile)?;
+    // Lets the next launch repair the video if we crash before it's finished
+    if let Err(e) = recorder::recovery::write_marker(output_path, slp_path) {
+        log::warn!("Failed to write recording marker: {:?}", e);
     }
+    watch_recording(app, state);
+    Ok(())
+}
 
-    if let Some(recorder) = recorder_lock.as_mut() {
-        recorder.start_recording(output_path, quality)?;
-        Ok(())
-    } else {
-        Err(Error::InitializationError(
-            "Failed to initialize recorder".to_string(),
-        ))
+/// Handle on the current recorder, letting go of the app's lock on it straight away
+fn current_recorder(state: &AppState) -> Result<Option<SharedRecorder>, Error> {
+    state
+        .recorder
+        .lock()
+        .map(|recorder| recorder.clone())
+        .map_err(|e| Error::RecordingFailed(format!("Failed to lock recorder: {}", e)))
+}
+
+fn lock_recorder(
+    recorder: &SharedRecorder,
+) -> Result<MutexGuard<'_, Box<dyn recorder::Recorder + Send>>, Error> {
+    recorder
+        .lock()
+        .map_err(|e| Error::RecordingFailed(format!("Failed to lock recorder: {}", e)))
+}
+
+/// Stops `recorder` off the async runtime, since stopping waits on the capture
+/// to finish writing
+async fn stop_shared_recorder(recorder: SharedRecorder) -> Result<String, Error> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut recorder = lock_recorder(&recorder)?;
+        stop_and_clear_marker(recorder.as_mut())
+    })
+    .await
+    .map_err(|e| Error::RecordingFailed(format!("Stopping the recorder failed: {}", e)))?
+}
+
+/// Forget `recorder` once it's stopped, unless another has already replaced it
+fn clear_recorder(state: &AppState, recorder: &SharedRecorder) {
+    if let Ok(mut recorder_lock) = state.recorder.lock() {
+        if recorder_lock
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, recorder))
+        {
+            *recorder_lock = None;
+        }
     }
 }
 
-#[cfg(target_os = "windows")]
-fn configure_target_window_from_settings(state: &State<'_, AppState>) {
+/// How often the recorder's stats are sent to the frontend
+const STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Emit `recording-stats` for the recording that just started, and
+/// `recording-degraded` whenever it starts dropping too many frames. Also ticks
+/// the recorder, which is when a segmented recording moves to its next file and
+/// a lost game window is looked for; `recording-target-lost` and
+/// `recording-target-found` tell the frontend about the latter
+fn watch_recording(app: &tauri::AppHandle, state: &State<'_, AppState>) {
+    // A recording started before the last one's watcher noticed it had stopped
+    let stop = Arc::new(AtomicBool::new(false));
+    if let Ok(mut watcher) = state.stats_watcher.lock() {
+        if let Some(previous) = watcher.replace(stop.clone()) {
+            previous.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Ticking can restart FFmpeg and look for windows, so it gets a thread of
+    // its own rather than a spot on the async runtime
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+        let mut monitor =
+            recorder::stats::DropRateMonitor::new(recorder::stats::DEGRADED_DROP_RATE);
+        let mut target_lost = false;
+        loop {
+            std::thread::sleep(STATS_INTERVAL);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let state = app_handle.state::<AppState>();
+            let Ok(Some(recorder)) = current_recorder(&state) else {
+                break;
+            };
+            let (stats, lost) = {
+                let Ok(mut recorder) = recorder.lock() else {
+                    break;
+                };
+                if !recorder.is_recording() {
+                    break;
+                }
+                if let Err(e) = recorder.tick() {
+                    log::error!("❌ Recorder tick failed: {:?}", e);
+                }
+                (recorder.stats(), recorder.target_lost())
+            };
+
+            if lost != target_lost {
+                target_lost = lost;
+                let event = if target_lost {
+                    "recording-target-lost"
+                } else {
+                    "recording-target-found"
+                };
+                if let Err(e) = app_handle.emit(event, ()) {
+                    log::error!("Failed to emit {} event: {:?}", event, e);
+                }
+            }
+            if let Some(drop_rate) = monitor.update(&stats) {
+                log::warn!(
+                    "⚠️ Recording is dropping {:.1}% of frames",
+                    drop_rate * 100.0
+                );
+                let payload = RecordingDegradedEvent {
+                    drop_rate,
+                    stats: stats.clone(),
+                };
+                if let Err(e) = app_handle.emit("recording-degraded", payload) {
+                    log::error!("Failed to emit recording-degraded event: {:?}", e);
+                }
+            }
+            if let Err(e) = app_handle.emit("recording-stats", stats) {
+                log::error!("Failed to emit recording-stats event: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub(crate) fn configure_target_window_from_settings(state: &State<'_, AppState>) {
     let identifier = match state.settings.lock() {
         Ok(settings) => settings
             .get("game_process_name")
@@ -155,19 +377,19 @@ fn configure_target_window_from_settings(state: &State<'_, AppState>) {
     }
 }
 
-#[cfg(not(target_os = "windows"))]
-fn configure_target_window_from_settings(_state: &State<'_, AppState>) {}
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub(crate) fn configure_target_window_from_settings(_state: &State<'_, AppState>) {}
 
-fn generate_generic_recording_path(recording_dir: &str) -> String {
+fn generate_generic_recording_path(recording_dir: &str, extension: &str) -> String {
     let now = chrono::Utc::now();
     let timestamp = now.format("%Y%m%dT%H%M%S").to_string();
 
     let mut counter = 0;
     loop {
         let filename = if counter == 0 {
-            format!("Manual_{}.mp4", timestamp)
+            format!("Manual_{}.{}", timestamp, extension)
         } else {
-            format!("Manual_{}_{}.mp4", timestamp, counter)
+            format!("Manual_{}_{}.{}", timestamp, counter, extension)
         };
 
         let candidate = Path::new(recording_dir).join(&filename);
@@ -193,10 +415,20 @@ pub fn get_default_slippi_path() -> Result<String, Error> {
 #[tauri::command]
 pub fn get_last_replay_path(state: State<'_, AppState>) -> Option<String> {
     state
-        .last_replay_path
+        .game_lifecycle
         .lock()
         .ok()
-        .and_then(|path| path.clone())
+        .and_then(|lifecycle| lifecycle.last_replay_path().map(|p| p.to_string()))
+}
+
+/// Get the current game lifecycle phase
+#[tauri::command]
+pub fn get_game_phase(state: State<'_, AppState>) -> Result<GamePhase, Error> {
+    let lifecycle = state
+        .game_lifecycle
+        .lock()
+        .map_err(|e| Error::InitializationError(format!("Failed to lock game lifecycle: {}", e)))?;
+    Ok(lifecycle.phase().clone())
 }
 
 /// Start watching for new Slippi games
@@ -218,7 +450,7 @@ pub async fn start_watching(
     }
 
     // Create new GameDetector with app handle
-    let mut detector = GameDetector::new(slippi_path);
+    let mut detector = GameDetector::new(slippi_path, state.game_lifecycle.clone());
     detector.set_app_handle(app.clone());
 
     detector.start_watching()?;
@@ -230,130 +462,360 @@ pub async fn start_watching(
         .map_err(|e| Error::InitializationError(format!("Failed to lock game detector: {}", e)))?;
     *game_detector = Some(detector);
 
-    // Set up event listener for auto-recording start
+    // Drive recording from game lifecycle transitions, replacing the listener
+    // from any earlier start so each game is only finalized once
+    log::info!("Setting up event listener for 'game-lifecycle' events");
+    let mut listener = state.lifecycle_listener.lock().map_err(|e| {
+        Error::InitializationError(format!("Failed to lock lifecycle listener: {}", e))
+    })?;
+    if let Some(previous) = listener.take() {
+        app.unlisten(previous);
+    }
     let app_clone = app.clone();
-    log::info!("Setting up event listener for 'slp-file-created' events");
-
-    let app_clone2 = app.clone();
-    app.listen("slp-file-created", move |event| {
-        let slp_path: &str = event.payload();
-        log::info!("========================================");
-        log::info!("Received slp-file-created event!");
-        log::info!("Payload: {}", slp_path);
-        log::info!("========================================");
-
-        let app_handle = app_clone.clone();
-
-        // Get state from app handle
-        let state_ref = app_handle.state::<AppState>();
-
-        // Store the last replay path
-        log::info!("Attempting to store last replay path");
-        if let Ok(mut last_replay) = state_ref.last_replay_path.lock() {
-            *last_replay = Some(slp_path.to_string());
-            log::info!("Last replay path stored: {}", slp_path);
-
-            // Emit event to frontend to update UI
-            log::info!("Emitting last-replay-updated event to frontend");
-            match app_handle.emit("last-replay-updated", slp_path) {
-                Ok(_) => log::info!("Frontend event emitted successfully"),
-                Err(e) => log::error!("Failed to emit last-replay-updated event: {:?}", e),
+    let id = app.listen("game-lifecycle", move |event| {
+        let transition: Transition = match serde_json::from_str(event.payload()) {
+            Ok(transition) => transition,
+            Err(e) => {
+                log::error!("Invalid game-lifecycle payload: {:?}", e);
+                return;
             }
-        } else {
-            log::error!("Failed to lock last_replay_path mutex");
+        };
+
+        match transition.to {
+            GamePhase::GameStarting { slp_path } => on_game_starting(&app_clone, slp_path),
+            GamePhase::GameEnded { slp_path, reason } => {
+                let app_handle = app_clone.clone();
+                tauri::async_runtime::spawn(async move {
+                    finalize_game(&app_handle, &slp_path, reason).await;
+                });
+            }
+            _ => {}
         }
+    });
+    *listener = Some(id);
 
-        // Check if auto-start recording is enabled
-        if let Ok(settings) = state_ref.settings.lock() {
-            let auto_start = settings
-                .get("autoStartRecording")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(true);
+    log::info!("Now watching for .slp files");
+    Ok(())
+}
 
-            if !auto_start {
-                log::info!("Auto-start recording is disabled");
-                return;
+/// A new game is starting: tell the frontend and start recording if enabled
+fn on_game_starting(app: &tauri::AppHandle, slp_path: String) {
+    log::info!("Game starting: {}", slp_path);
+    let state_ref = app.state::<AppState>();
+
+    if let Err(e) = app.emit("last-replay-updated", &slp_path) {
+        log::error!("Failed to emit last-replay-updated event: {:?}", e);
+    }
+
+    // Keep the countdown and whatever happened before the replay was created
+    if crate::commands::replay_buffer::is_replay_buffer_running(&state_ref) {
+        let app_handle = app.clone();
+        let stem = Path::new(&slp_path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Game")
+            .to_string();
+        tauri::async_runtime::spawn(async move {
+            let filename = format!("PreGame_{}.mp4", stem);
+            match crate::commands::replay_buffer::save_replay_buffer_as(&app_handle, &filename)
+                .await
+            {
+                Ok(path) => log::info!("Saved pre-game footage: {}", path),
+                Err(e) => log::error!("Failed to save pre-game footage: {:?}", e),
             }
+        });
+    }
+
+    // Check if auto-start recording is enabled
+    if let Ok(settings) = state_ref.settings.lock() {
+        let auto_start = settings
+            .get("autoStartRecording")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        if !auto_start {
+            log::info!("Auto-start recording is disabled");
+            return;
         }
+    }
 
-        // Check if already recording
-        if let Ok(recorder_lock) = state_ref.recorder.lock() {
-            if recorder_lock.is_some() {
-                log::info!("Already recording, skipping");
-                return;
+    // Check if already recording
+    if let Ok(recorder_lock) = state_ref.recorder.lock() {
+        if recorder_lock.is_some() {
+            log::info!("Already recording, skipping");
+            return;
+        }
+    }
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = trigger_auto_recording(app_handle, slp_path).await {
+            log::error!("Failed to trigger auto-recording: {:?}", e);
+        }
+    });
+}
+
+/// The game ended: stop its recording (if we made one) and move the lifecycle on
+async fn finalize_game(app: &tauri::AppHandle, slp_path: &str, reason: GameEndReason) {
+    log::info!("Game ended ({:?}): {}", reason, slp_path);
+    let state = app.state::<AppState>();
+    let lifecycle = state.game_lifecycle.clone();
+
+    if let Err(e) = game_detector::advance(&lifecycle, app, LifecycleEvent::FinalizeStarted) {
+        log::error!("Failed to start finalizing {}: {}", slp_path, e);
+        return;
+    }
+
+    let recorded = lifecycle
+        .lock()
+        .map(|l| l.is_recorded_game(slp_path))
+        .unwrap_or(false);
+    if recorded {
+        if let Ok(mut set) = state.recording_set.lock() {
+            if let Some(set) = set.as_mut() {
+                set.end_game(slp_path, reason);
             }
         }
 
-        // Start recording and track the file
-        if let Ok(mut current_file) = state_ref.current_recording_file.lock() {
-            *current_file = Some(slp_path.to_string());
-            log::info!(
-                "Tracking recording file for game end detection: {}",
-                slp_path
-            );
+        let (post_roll, continue_into_next) = read_post_roll_settings(app);
+        if !post_roll.is_zero() {
+            log::info!("⏳ Post-roll: recording {:?} after game end", post_roll);
         }
+        let mut outcome = wait_for_post_roll(app, slp_path, post_roll).await;
 
-        let slp_path_for_recording = slp_path.to_string();
-        tauri::async_runtime::spawn(async move {
-            if let Err(e) = trigger_auto_recording(app_handle, slp_path_for_recording).await {
-                log::error!("Failed to trigger auto-recording: {:?}", e);
+        // Sit out the menus paused, so the next game of the set lands in the same file
+        let mut paused = false;
+        if continue_into_next && matches!(outcome, PostRoll::Elapsed) {
+            match set_recording_paused(app, true).await {
+                Ok(()) => {
+                    log::info!(
+                        "⏸️ Waiting up to {:?} for the next game of the set",
+                        SET_PAUSE_TIMEOUT
+                    );
+                    paused = true;
+                    outcome = wait_for_post_roll(app, slp_path, SET_PAUSE_TIMEOUT).await;
+                }
+                Err(e) => log::warn!("Couldn't pause between games: {:?}", e),
             }
-        });
-    });
+        }
 
-    // Set up event listener for file modifications (game ending!)
-    log::info!("Setting up event listener for 'slp-file-modified' events");
-    let app_clone2_inner = app_clone2.clone();
-    app_clone2.listen("slp-file-modified", move |event| {
-        let modified_path = event.payload();
-        log::info!("File modified - game likely ended: {}", modified_path);
-
-        let state_ref = app_clone2_inner.state::<AppState>();
-
-        // Check if this is the file we're currently recording
-        if let Ok(current_file) = state_ref.current_recording_file.lock() {
-            if let Some(recording_file) = current_file.as_ref() {
-                if recording_file == modified_path {
-                    log::info!("Detected modification of recording file - game ended!");
-                    drop(current_file); // Drop the lock before spawning task
-
-                    // Slippi writes all data at once when game ends
-                    // Wait a few seconds to ensure write is complete, then stop recording
-                    let app_handle = app_clone2_inner.clone();
-                    tauri::async_runtime::spawn(async move {
-                        log::info!("Waiting 3 seconds for file write to complete...");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-
-                        log::info!("Stopping recording after game end...");
-                        if let Err(e) = stop_recording_internal(&app_handle).await {
-                            log::error!("Failed to stop recording: {:?}", e);
+        match outcome {
+            PostRoll::NextGame(next) if continue_into_next => {
+                let resumed = !paused
+                    || set_recording_paused(app, false)
+                        .await
+                        .inspect_err(|e| log::error!("Failed to resume recording: {:?}", e))
+                        .is_ok();
+                if resumed {
+                    log::info!("🔗 Continuing recording into next game: {}", next);
+                    if let Ok(mut set) = state.recording_set.lock() {
+                        if let Some(set) = set.as_mut() {
+                            set.start_game(&next);
                         }
-                    });
+                    }
+                    if let Ok(mut lifecycle) = lifecycle.lock() {
+                        lifecycle.set_recorded_game(Some(next));
+                    }
+                } else if let Err(e) = stop_recording_internal(app, Some(reason)).await {
+                    log::error!("Failed to stop recording: {:?}", e);
                 }
             }
-        };
-    });
+            PostRoll::Stopped => {
+                log::info!("Recording was stopped during post-roll");
+            }
+            _ => {
+                log::info!("Stopping recording after game end...");
+                if let Err(e) = stop_recording_internal(app, Some(reason)).await {
+                    log::error!("Failed to stop recording: {:?}", e);
+                }
+            }
+        }
+    }
 
-    log::info!("Now watching for .slp files");
-    Ok(())
+    // May start the next game right away if one was queued behind this one
+    if let Err(e) = game_detector::advance(&lifecycle, app, LifecycleEvent::Finalized) {
+        log::error!("Failed to finish finalizing {}: {}", slp_path, e);
+    }
+}
+
+/// How often the post-roll checks whether the next game has started
+const POST_ROLL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// How long a recording stays paused between games of a set before it's stopped
+const SET_PAUSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(180);
+
+/// How the post-roll after a game finished
+enum PostRoll {
+    /// Recorded for the full post-roll
+    Elapsed,
+    /// The next game's replay appeared first
+    NextGame(String),
+    /// The recording was stopped manually in the meantime
+    Stopped,
+}
+
+/// Read the post-roll length and whether a game starting during it should
+/// continue the same recording
+fn read_post_roll_settings(app: &tauri::AppHandle) -> (std::time::Duration, bool) {
+    use tauri_plugin_store::StoreExt;
+
+    let Ok(store) = app.store("settings.json") else {
+        return (std::time::Duration::ZERO, false);
+    };
+    let post_roll_seconds = store
+        .get("postRollSeconds")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let continue_into_next = store
+        .get("continueIntoNextGame")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    (
+        std::time::Duration::from_secs(post_roll_seconds),
+        continue_into_next,
+    )
 }
 
-async fn stop_recording_internal(app: &tauri::AppHandle) -> Result<(), Error> {
+/// Keep recording after `slp_path` ended, for up to `post_roll`
+async fn wait_for_post_roll(
+    app: &tauri::AppHandle,
+    slp_path: &str,
+    post_roll: std::time::Duration,
+) -> PostRoll {
     let state = app.state::<AppState>();
+    let deadline = std::time::Instant::now() + post_roll;
 
-    // Stop recording
-    let mut recorder_lock = state
-        .recorder
+    loop {
+        if let Ok(lifecycle) = state.game_lifecycle.lock() {
+            if !lifecycle.is_recorded_game(slp_path) {
+                return PostRoll::Stopped;
+            }
+            // Replays created while finalizing are queued, but still become the last replay
+            if let Some(next) = lifecycle.last_replay_path().filter(|p| *p != slp_path) {
+                return PostRoll::NextGame(next.to_string());
+            }
+        }
+
+        if std::time::Instant::now() >= deadline {
+            return PostRoll::Elapsed;
+        }
+        tokio::time::sleep(POST_ROLL_POLL_INTERVAL).await;
+    }
+}
+
+/// Pause or resume the current recording, keeping the set's game boundaries in
+/// step and telling the frontend
+async fn set_recording_paused(app: &tauri::AppHandle, paused: bool) -> Result<(), Error> {
+    let state = app.state::<AppState>();
+    let recorder = current_recorder(&state)?
+        .ok_or_else(|| Error::RecordingFailed("No active recording".to_string()))?;
+    // Off the async runtime, since pausing can wait on the capture to finish
+    // writing its part
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut recorder = lock_recorder(&recorder)?;
+        if paused {
+            recorder.pause()
+        } else {
+            recorder.resume()
+        }
+    })
+    .await
+    .map_err(|e| {
+        Error::RecordingFailed(format!("Pausing or resuming the recorder failed: {}", e))
+    })??;
+
+    if let Ok(mut set) = state.recording_set.lock() {
+        if let Some(set) = set.as_mut() {
+            if paused {
+                set.pause();
+            } else {
+                set.resume();
+            }
+        }
+    }
+
+    let output_path = state
+        .current_recording_file
+        .lock()
+        .ok()
+        .and_then(|file| file.clone());
+    let event = if paused {
+        "recording-paused"
+    } else {
+        "recording-resumed"
+    };
+    if let Err(e) = app.emit(event, output_path) {
+        log::error!("Failed to emit {} event: {:?}", event, e);
+    }
+    Ok(())
+}
+
+/// Write the per-game boundaries of a recording that spanned several games
+fn finish_recording_set(state: &State<'_, AppState>) {
+    let Some(set) = state
+        .recording_set
         .lock()
-        .map_err(|e| Error::RecordingFailed(format!("Failed to lock recorder: {}", e)))?;
+        .ok()
+        .and_then(|mut set| set.take())
+    else {
+        return;
+    };
+    if set.games().len() < 2 {
+        return;
+    }
+
+    match set.write_sidecar() {
+        Ok(path) => log::info!(
+            "📼 Recorded {} games in one set: {:?}",
+            set.games().len(),
+            path
+        ),
+        Err(e) => log::error!("Failed to write set boundaries: {:?}", e),
+    }
+}
+
+/// Stop `recorder` and remove the `.inprogress` marker of what it was
+/// recording once the file is finished. A failed stop can leave the file
+/// unfinished or its parts unjoined, so its marker stays for recovery.
+fn stop_and_clear_marker(recorder: &mut (dyn recorder::Recorder + Send)) -> Result<String, Error> {
+    let output_path = recorder.stop_recording()?;
+    recorder::recovery::clear_marker(&output_path);
+    Ok(output_path)
+}
+
+/// A failed stop still ends the recording, so forget it like a finished one.
+/// The file keeps its marker, for recovery to repair at the next start.
+fn abandon_recording(state: &State<'_, AppState>, recorder: &SharedRecorder) {
+    clear_recorder(state, recorder);
+    finish_recording_set(state);
+    if let Ok(mut current_file) = state.current_recording_file.lock() {
+        *current_file = None;
+    }
+}
+
+async fn stop_recording_internal(
+    app: &tauri::AppHandle,
+    end_reason: Option<GameEndReason>,
+) -> Result<(), Error> {
+    let state = app.state::<AppState>();
 
-    if let Some(recorder) = recorder_lock.as_mut() {
-        let output_path = recorder.stop_recording()?;
+    // Stop recording
+    if let Some(recorder) = current_recorder(&state)? {
+        let output_path = match stop_shared_recorder(recorder.clone()).await {
+            Ok(output_path) => output_path,
+            Err(e) => {
+                abandon_recording(&state, &recorder);
+                if let Err(emit_err) = app.emit("recording-failed", e.to_string()) {
+                    log::error!("Failed to emit recording-failed event: {:?}", emit_err);
+                }
+                return Err(e);
+            }
+        };
         log::info!("Auto-stopped recording: {}", output_path);
 
         // Clear recording state
-        *recorder_lock = None;
-        drop(recorder_lock);
+        clear_recorder(&state, &recorder);
+        finish_recording_set(&state);
 
         let associated_recording = {
             let mut current_file = state.current_recording_file.lock().map_err(|e| {
@@ -381,12 +843,12 @@ async fn stop_recording_internal(app: &tauri::AppHandle) -> Result<(), Error> {
             }
         }
 
-        if let Ok(mut last_mod) = state.last_file_modification.lock() {
-            *last_mod = None;
-        }
-
         // Emit event to frontend
-        if let Err(e) = app.emit("recording-stopped", output_path) {
+        let payload = RecordingStoppedEvent {
+            output_path,
+            end_reason,
+        };
+        if let Err(e) = app.emit("recording-stopped", payload) {
             log::error!("Failed to emit recording-stopped event: {:?}", e);
         }
 
@@ -410,29 +872,35 @@ async fn trigger_auto_recording(app: tauri::AppHandle, slp_path: String) -> Resu
         }
     };
 
-    // Extract filename from .slp path and change extension to .mp4
+    let profile = resolve_recording_profile(&app);
+    log::info!("📊 Auto-recording profile: {}", profile.summary());
+
+    // Name the video after the .slp, with the profile's container extension
     let slp_filename = std::path::Path::new(&slp_path)
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("recording");
 
-    let output_path = format!("{}/{}.mp4", recording_dir, slp_filename);
-    log::info!("Output path: {}", output_path);
-
-    // Get recording quality from settings
-    let quality = resolve_recording_quality(&state)?;
-    log::info!(
-        "📊 Auto-recording quality: {:?} (bitrate: {} Mbps)",
-        quality,
-        quality.bitrate() / 1_000_000
+    let output_path = format!(
+        "{}/{}.{}",
+        recording_dir,
+        slp_filename,
+        profile.container.extension()
     );
+    log::info!("Output path: {}", output_path);
 
     configure_target_window_from_settings(&state);
-    start_recording_with_quality(&state, &output_path, quality)?;
+    start_recording_with_profile(&app, &state, &output_path, Some(&slp_path), &profile)?;
     // Store the video output path (not .slp path) so markers match correctly
     if let Ok(mut current_file) = state.current_recording_file.lock() {
         *current_file = Some(output_path.clone());
     }
+    if let Ok(mut lifecycle) = state.game_lifecycle.lock() {
+        lifecycle.set_recorded_game(Some(slp_path.clone()));
+    }
+    if let Ok(mut set) = state.recording_set.lock() {
+        *set = Some(RecordingSet::new(&output_path, &slp_path));
+    }
 
     // Emit event to frontend with video output path
     if let Err(e) = app.emit("recording-started", output_path.clone()) {
@@ -442,7 +910,9 @@ async fn trigger_auto_recording(app: tauri::AppHandle, slp_path: String) -> Resu
     Ok(())
 }
 
-async fn get_recording_directory_internal(app: &tauri::AppHandle) -> Result<String, Error> {
+pub(crate) async fn get_recording_directory_internal(
+    app: &tauri::AppHandle,
+) -> Result<String, Error> {
     use tauri_plugin_store::StoreExt;
 
     let store = app
@@ -478,9 +948,34 @@ async fn get_recording_directory_internal(app: &tauri::AppHandle) -> Result<Stri
         .ok_or_else(|| Error::InvalidPath("Failed to convert path to string".to_string()))
 }
 
+/// Clips live alongside the recordings directory (e.g. Videos/Clips)
+pub(crate) async fn get_clips_directory_internal(app: &tauri::AppHandle) -> Result<PathBuf, Error> {
+    let recording_dir = get_recording_directory_internal(app).await?;
+    let recording_dir_path = Path::new(&recording_dir);
+    let clips_dir = recording_dir_path
+        .parent()
+        .unwrap_or(recording_dir_path)
+        .join("Clips");
+
+    std::fs::create_dir_all(&clips_dir)
+        .map_err(|e| Error::RecordingFailed(format!("Failed to create clips directory: {}", e)))?;
+    Ok(clips_dir)
+}
+
 /// Stop watching for new games
 #[tauri::command]
-pub async fn stop_watching(state: State<'_, AppState>) -> Result<(), Error> {
+pub async fn stop_watching(app: tauri::AppHandle, state: State<'_, AppState>) -> Result<(), Error> {
+    if let Some(listener) = state
+        .lifecycle_listener
+        .lock()
+        .map_err(|e| {
+            Error::InitializationError(format!("Failed to lock lifecycle listener: {}", e))
+        })?
+        .take()
+    {
+        app.unlisten(listener);
+    }
+
     let mut game_detector = state
         .game_detector
         .lock()
@@ -496,19 +991,43 @@ pub async fn stop_watching(state: State<'_, AppState>) -> Result<(), Error> {
 
 /// Start recording gameplay
 #[tauri::command]
-pub async fn start_recording(output_path: String, state: State<'_, AppState>) -> Result<(), Error> {
-    let quality = resolve_recording_quality(&state)?;
-    log::info!(
-        "📊 Recording quality: {:?} (bitrate: {} Mbps)",
-        quality,
-        quality.bitrate() / 1_000_000
-    );
+pub async fn start_recording(
+    output_path: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), Error> {
+    let profile = resolve_recording_profile(&app);
+    log::info!("📊 Recording profile: {}", profile.summary());
 
     configure_target_window_from_settings(&state);
-    start_recording_with_quality(&state, &output_path, quality)?;
+    start_recording_with_profile(&app, &state, &output_path, None, &profile)?;
     Ok(())
 }
 
+/// When manual recordings should move on to a new file, from `segmentMinutes`
+/// and `segmentSizeMb` (0 turns a limit off)
+fn segment_limits_from_settings(
+    app: &tauri::AppHandle,
+) -> Option<recorder::segmented::SegmentLimits> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app.store("settings.json").ok()?;
+    let minutes = store
+        .get("segmentMinutes")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let megabytes = store
+        .get("segmentSizeMb")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+
+    let limits = recorder::segmented::SegmentLimits {
+        max_duration: (minutes > 0).then(|| std::time::Duration::from_secs(minutes * 60)),
+        max_bytes: (megabytes > 0).then(|| megabytes * 1024 * 1024),
+    };
+    (!limits.is_unlimited()).then_some(limits)
+}
+
 /// Start a generic/manual recording with an auto-generated filename
 #[tauri::command]
 pub async fn start_generic_recording(
@@ -516,17 +1035,34 @@ pub async fn start_generic_recording(
     state: State<'_, AppState>,
 ) -> Result<String, Error> {
     let recording_dir = get_recording_directory_internal(&app).await?;
-    let output_path = generate_generic_recording_path(&recording_dir);
-
-    let quality = resolve_recording_quality(&state)?;
-    log::info!(
-        "📊 Recording quality: {:?} (bitrate: {} Mbps)",
-        quality,
-        quality.bitrate() / 1_000_000
-    );
+    let profile = resolve_recording_profile(&app);
+    log::info!("📊 Recording profile: {}", profile.summary());
+    let output_path =
+        generate_generic_recording_path(&recording_dir, profile.container.extension());
 
     configure_target_window_from_settings(&state);
-    start_recording_with_quality(&state, &output_path, quality)?;
+    if let Some(limits) = segment_limits_from_settings(&app) {
+        log::info!("✂️ Splitting recording into segments: {:?}", limits);
+        let mut recorder_lock = state
+            .recorder
+            .lock()
+            .map_err(|e| Error::InitializationError(format!("Failed to lock recorder: {}", e)))?;
+        if recorder_lock
+            .as_ref()
+            .is_some_and(|r| r.lock().is_ok_and(|r| r.is_recording()))
+        {
+            return Err(Error::RecordingFailed("Already recording".to_string()));
+        }
+        *recorder_lock = Some(Arc::new(Mutex::new(Box::new(
+            recorder::watchdog::Watchdog::new(Box::new(
+                recorder::segmented::SegmentedRecorder::new(
+                    create_recorder_from_settings(&app),
+                    limits,
+                ),
+            )),
+        ))));
+    }
+    start_recording_with_profile(&app, &state, &output_path, None, &profile)?;
 
     if let Ok(mut current_file) = state.current_recording_file.lock() {
         *current_file = Some(output_path.clone());
@@ -541,16 +1077,14 @@ pub async fn stop_recording(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<String, Error> {
-    let mut recorder_lock = state
-        .recorder
-        .lock()
-        .map_err(|e| Error::RecordingFailed(format!("Failed to lock recorder: {}", e)))?;
-
-    if let Some(recorder) = recorder_lock.as_mut() {
-        let output_path = recorder.stop_recording()?;
+    if let Some(recorder) = current_recorder(&state)? {
+        let output_path = stop_shared_recorder(recorder.clone())
+            .await
+            .inspect_err(|_| abandon_recording(&state, &recorder))?;
 
         // Clean up recorder
-        *recorder_lock = None;
+        clear_recorder(&state, &recorder);
+        finish_recording_set(&state);
 
         let marker_snapshot = {
             let markers = state.clip_markers.lock().map_err(|e| {
@@ -569,7 +1103,11 @@ pub async fn stop_recording(
             log::info!("Clip markers for {}: {:?}", output_path, marker_snapshot);
         }
 
-        if let Err(e) = app.emit("recording-stopped", output_path.clone()) {
+        let payload = RecordingStoppedEvent {
+            output_path: output_path.clone(),
+            end_reason: None,
+        };
+        if let Err(e) = app.emit("recording-stopped", payload) {
             log::error!("Failed to emit recording-stopped event: {:?}", e);
         }
 
@@ -583,6 +1121,11 @@ pub async fn stop_recording(
             }
         }
 
+        // A manual stop ends any auto-recording, so game end has nothing left to stop
+        if let Ok(mut lifecycle) = state.game_lifecycle.lock() {
+            lifecycle.set_recorded_game(None);
+        }
+
         Ok(output_path)
     } else {
         Err(Error::RecordingFailed(
@@ -591,15 +1134,46 @@ pub async fn stop_recording(
     }
 }
 
+/// Pause the current recording; resuming continues the same file
+#[tauri::command]
+pub async fn pause_recording(app: tauri::AppHandle) -> Result<(), Error> {
+    set_recording_paused(&app, true).await
+}
+
+/// Resume a paused recording
+#[tauri::command]
+pub async fn resume_recording(app: tauri::AppHandle) -> Result<(), Error> {
+    set_recording_paused(&app, false).await
+}
+
 /// Delete a recording (video and optionally .slp file)
 #[tauri::command]
 pub async fn delete_recording(video_path: Option<String>, _slp_path: String) -> Result<(), Error> {
     // Delete video file if it exists
     if let Some(video) = video_path {
+        if let Some(manifest) = recorder::segmented::SegmentManifest::load(&video) {
+            for segment in &manifest.segments {
+                if Path::new(&segment.path).exists() {
+                    std::fs::remove_file(&segment.path).map_err(|e| {
+                        Error::RecordingFailed(format!("Failed to delete segment: {}", e))
+                    })?;
+                }
+                let _ = std::fs::remove_file(recorder::frames::FrameTimestamps::path_for(
+                    &segment.path,
+                ));
+            }
+            std::fs::remove_file(recorder::segmented::SegmentManifest::path_for(&video))
+                .map_err(|e| Error::RecordingFailed(format!("Failed to delete manifest: {}", e)))?;
+        }
         if !video.is_empty() && std::path::Path::new(&video).exists() {
             std::fs::remove_file(&video)
                 .map_err(|e| Error::RecordingFailed(format!("Failed to delete video: {}", e)))?;
         }
+        let _ = std::fs::remove_file(recorder::frames::FrameTimestamps::path_for(&video));
+        let _ = std::fs::remove_file(crate::slippi::sync::SyncOffset::path_for(&video));
+        let _ = std::fs::remove_file(recorder::set::SetManifest::path_for(&video));
+        let _ = std::fs::remove_file(crate::clip_markers::ClipMarkerFile::path_for(&video));
+        let _ = std::fs::remove_file(ClipMetadata::path_for(&video));
     }
 
     // Delete .slp file if it exists and user wants to
@@ -680,11 +1254,66 @@ pub async fn open_recording_folder(video_path: String) -> Result<(), Error> {
     Ok(())
 }
 
+/// Repair recordings left unfinished by a crash; run once at startup
+#[tauri::command]
+pub async fn recover_recordings(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    jobs: State<'_, JobQueue>,
+) -> Result<Vec<recorder::recovery::RecoveredRecording>, Error> {
+    // A marker of a recording that's still going isn't an orphan
+    let recording = state
+        .recorder
+        .lock()
+        .map(|recorder| {
+            recorder
+                .as_ref()
+                .is_some_and(|r| r.lock().is_ok_and(|r| r.is_recording()))
+        })
+        .unwrap_or(false);
+    if recording {
+        return Ok(Vec::new());
+    }
+
+    let recording_dir = get_recording_directory_internal(&app).await?;
+    let orphans = recorder::recovery::find_orphans(Path::new(&recording_dir));
+    if orphans.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    log::info!("🩹 Found {} interrupted recording(s)", orphans.len());
+    crate::clip_processor::ensure_ffmpeg()?;
+    // Remuxing runs FFmpeg over whole recordings, so it queues with the rest
+    let handles: Vec<_> = orphans
+        .into_iter()
+        .map(|orphan| {
+            let label = Path::new(&orphan.video_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| orphan.video_path.clone());
+            jobs.submit(JobKind::Remux, JobPriority::Normal, label, move || {
+                Ok(recorder::recovery::recover(&orphan))
+            })
+        })
+        .collect();
+
+    // A cancelled one keeps its marker, so it's tried again next time
+    let mut recovered = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match wait_for_job(handle).await {
+            Ok(recording) => recovered.push(recording),
+            Err(e) => log::warn!("Recovering a recording didn't finish: {:?}", e),
+        }
+    }
+    Ok(recovered)
+}
+
 /// Get list of recorded sessions
 #[tauri::command]
 pub async fn get_recordings(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
+    jobs: State<'_, JobQueue>,
 ) -> Result<Vec<RecordingSession>, Error> {
     log::debug!("📂 Scanning for recordings...");
 
@@ -700,46 +1329,81 @@ pub async fn get_recordings(
     log::debug!("📁 Recording directory: {}", recording_dir);
 
     // Get Slippi directory
-    let slippi_dir = {
-        use tauri_plugin_store::StoreExt;
-        let store = app.store("settings.json").map_err(|e| {
-            Error::InitializationError(format!("Failed to open settings store: {}", e))
-        })?;
-
-        if let Some(value) = store.get("slippiPath") {
-            if let Some(path) = value.as_str() {
-                if !path.is_empty() {
-                    Some(path.to_string())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    };
+    let slippi_dir = slippi_directory_from_settings(&app)?;
 
-    let slippi_dir = match slippi_dir {
-        Some(path) => path,
-        None => slippi_paths::get_default_slippi_path()
-            .to_str()
-            .unwrap_or("")
-            .to_string(),
-    };
+    // Segments are listed once, as the recording they belong to
+    let manifests: Vec<recorder::segmented::SegmentManifest> = WalkDir::new(&recording_dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.ends_with(recorder::segmented::MANIFEST_SUFFIX))
+        })
+        .filter_map(|entry| recorder::segmented::SegmentManifest::read(entry.path()))
+        .collect();
+    let segment_files: std::collections::HashSet<PathBuf> = manifests
+        .iter()
+        .flat_map(|manifest| manifest.segments.iter())
+        .map(|segment| PathBuf::from(&segment.path))
+        .collect();
 
     // Scan for MP4 files in recording directory
     let mut recordings = Vec::new();
 
+    for manifest in &manifests {
+        match create_segmented_session(manifest, &slippi_dir, &state, &jobs).await {
+            Ok(session) => recordings.push(session),
+            Err(e) => log::warn!(
+                "⚠️ Failed to load segmented recording {}: {:?}",
+                manifest.video_path,
+                e
+            ),
+        }
+    }
+
+    // A set's video is listed as its first game; the later games come from its sidecar
+    let sets = WalkDir::new(&recording_dir)
+        .max_depth(3)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.ends_with(recorder::set::SIDECAR_SUFFIX))
+        })
+        .filter_map(|entry| recorder::set::SetManifest::read(entry.path()));
+    for manifest in sets {
+        for index in 1..manifest.games.len() {
+            match create_set_game_session(&manifest, index, &slippi_dir, &state, &jobs).await {
+                Ok(session) => recordings.push(session),
+                Err(e) => log::warn!(
+                    "⚠️ Failed to load game {} of set {}: {:?}",
+                    index + 1,
+                    manifest.video_path,
+                    e
+                ),
+            }
+        }
+    }
+
     for entry in WalkDir::new(&recording_dir)
         .max_depth(3)
         .into_iter()
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("mp4") {
-            if let Ok(session) = create_recording_session(path, &slippi_dir, &state).await {
+        if segment_files.contains(path) {
+            continue;
+        }
+        if matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("mp4") | Some("mkv")
+        ) {
+            if let Ok(session) = create_recording_session(path, &slippi_dir, &state, &jobs).await {
                 recordings.push(session);
             } else {
                 log::warn!("⚠️ Failed to load recording metadata for {:?}", path);
@@ -752,10 +1416,42 @@ pub async fn get_recordings(
     Ok(recordings)
 }
 
+/// Wait for a background job without blocking the async runtime
+async fn wait_for_job<T: Send + 'static>(job: JobHandle<T>) -> Result<T, Error> {
+    tauri::async_runtime::spawn_blocking(move || job.wait())
+        .await
+        .map_err(|e| Error::RecordingFailed(format!("Job wait failed: {}", e)))?
+}
+
+/// Sync a replay to its video as a job, since calibrating it the first time
+/// runs FFmpeg over the video
+async fn sync_replay(
+    jobs: &JobQueue,
+    video_path: &str,
+    slp_path: &str,
+    game: peppi::game::immutable::Game,
+    priority: JobPriority,
+) -> Result<Option<crate::slippi::sync::ReplaySync>, Error> {
+    let label = Path::new(video_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| video_path.to_string());
+    let (video_path, slp_path) = (video_path.to_string(), slp_path.to_string());
+    let job = jobs.submit(JobKind::Sync, priority, label, move || {
+        Ok(crate::slippi::sync::ReplaySync::new(
+            &video_path,
+            &slp_path,
+            &game,
+        ))
+    });
+    wait_for_job(job).await
+}
+
 async fn create_recording_session(
     video_path: &Path,
     slippi_dir: &str,
     state: &State<'_, AppState>,
+    jobs: &JobQueue,
 ) -> Result<RecordingSession, Error> {
     let video_path_str = video_path.to_string_lossy().to_string();
 
@@ -788,10 +1484,10 @@ async fn create_recording_session(
     let slp_path = find_matching_slp(video_filename, slippi_dir).await;
 
     // Parse .slp file if found (with caching)
-    let (slippi_metadata, duration, end_time) = if let Some(ref slp) = slp_path {
+    let (slippi_metadata, duration, end_time, tech_stats) = if let Some(ref slp) = slp_path {
         parse_slp_file_cached(slp, state).await
     } else {
-        (None, None, None)
+        (None, None, None, None)
     };
 
     // Generate ID from filename
@@ -801,35 +1497,7 @@ async fn create_recording_session(
         .unwrap_or("unknown")
         .to_string();
 
-    // Generate thumbnail path (in Thumbnails directory next to video)
-    let thumbnail_path = if let Some(parent) = video_path.parent() {
-        let thumbnails_dir = parent.join("Thumbnails");
-        if let Err(e) = std::fs::create_dir_all(&thumbnails_dir) {
-            log::warn!("Failed to create thumbnails directory: {}", e);
-        }
-        
-        let thumbnail_filename = format!("{}.jpg", id);
-        let thumbnail_path = thumbnails_dir.join(&thumbnail_filename);
-        
-        // Generate thumbnail if it doesn't exist
-        if !thumbnail_path.exists() {
-            crate::clip_processor::ensure_ffmpeg().ok();
-            if let Err(e) = crate::clip_processor::generate_thumbnail(
-                &video_path_str,
-                thumbnail_path.to_str().unwrap_or(""),
-                None,
-            ) {
-                log::warn!("Failed to generate thumbnail: {}", e);
-                None
-            } else {
-                thumbnail_path.to_str().map(|s| s.to_string())
-            }
-        } else {
-            thumbnail_path.to_str().map(|s| s.to_string())
-        }
-    } else {
-        None
-    };
+    let thumbnail_path = video_thumbnail(video_path, &id, None, jobs).await;
 
     Ok(RecordingSession {
         id,
@@ -841,9 +1509,159 @@ async fn create_recording_session(
         duration,
         file_size: Some(file_size),
         slippi_metadata,
+        tech_stats,
+        segments: None,
+        video_start_seconds: None,
+        clip: None,
     })
 }
 
+/// Thumbnail of `video_path` `offset` seconds in, generated the first time into
+/// the Thumbnails directory next to it as `<id>.jpg`
+async fn video_thumbnail(
+    video_path: &Path,
+    id: &str,
+    offset: Option<f64>,
+    jobs: &JobQueue,
+) -> Option<String> {
+    let parent = video_path.parent()?;
+    let thumbnails_dir = parent.join("Thumbnails");
+    if let Err(e) = std::fs::create_dir_all(&thumbnails_dir) {
+        log::warn!("Failed to create thumbnails directory: {}", e);
+    }
+
+    let thumbnail_filename = format!("{}.jpg", id);
+    let thumbnail_path = thumbnails_dir.join(&thumbnail_filename);
+
+    // Generate thumbnail if it doesn't exist
+    if !thumbnail_path.exists() {
+        crate::clip_processor::ensure_ffmpeg().ok();
+        // Ahead of other jobs, since the library is waiting on it
+        let video = video_path.to_string_lossy().to_string();
+        let output = thumbnail_path.to_string_lossy().to_string();
+        let job = jobs.submit(
+            JobKind::Thumbnail,
+            JobPriority::High,
+            id.to_string(),
+            move || crate::clip_processor::generate_thumbnail(&video, &output, offset),
+        );
+        if let Err(e) = wait_for_job(job).await {
+            log::warn!("Failed to generate thumbnail: {}", e);
+            return None;
+        }
+    }
+    thumbnail_path.to_str().map(|s| s.to_string())
+}
+
+/// A later game of a set, listed as its own session within the set's video
+async fn create_set_game_session(
+    manifest: &recorder::set::SetManifest,
+    index: usize,
+    slippi_dir: &str,
+    state: &State<'_, AppState>,
+    jobs: &JobQueue,
+) -> Result<RecordingSession, Error> {
+    let game = manifest
+        .games
+        .get(index)
+        .ok_or_else(|| Error::InvalidPath(format!("Set has no game {}", index + 1)))?;
+    let video_path = Path::new(&manifest.video_path);
+    let mut session = create_recording_session(video_path, slippi_dir, state, jobs).await?;
+
+    let (slippi_metadata, duration, end_time, tech_stats) =
+        parse_slp_file_cached(&game.slp_path, state).await;
+    session.id = format!("{}#{}", session.id, index + 1);
+    if let Some(metadata) = &slippi_metadata {
+        session.start_time = metadata.start_time.clone();
+    }
+    session.slp_path = game.slp_path.clone();
+    session.slippi_metadata = slippi_metadata;
+    session.duration = duration.or_else(|| {
+        game.end_seconds
+            .map(|end| (end - game.start_seconds).max(0.0) as u64)
+    });
+    session.end_time = end_time;
+    session.tech_stats = tech_stats;
+    // The whole set's video is one file, so it has no size of its own
+    session.file_size = None;
+    session.video_start_seconds = Some(game.start_seconds);
+    session.thumbnail_path = video_thumbnail(
+        video_path,
+        &session.id,
+        Some(game.start_seconds + 1.0),
+        jobs,
+    )
+    .await;
+    Ok(session)
+}
+
+/// A segmented recording listed as one session, built from its first file
+async fn create_segmented_session(
+    manifest: &recorder::segmented::SegmentManifest,
+    slippi_dir: &str,
+    state: &State<'_, AppState>,
+    jobs: &JobQueue,
+) -> Result<RecordingSession, Error> {
+    let first = manifest
+        .segments
+        .first()
+        .ok_or_else(|| Error::InvalidPath("Segment manifest lists no files".to_string()))?;
+    let mut session =
+        create_recording_session(Path::new(&first.path), slippi_dir, state, jobs).await?;
+
+    session.id = Path::new(&manifest.video_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    session.video_path = Some(manifest.video_path.clone());
+    session.file_size = Some(
+        manifest
+            .segments
+            .iter()
+            .filter_map(|segment| std::fs::metadata(&segment.path).ok())
+            .map(|metadata| metadata.len())
+            .sum(),
+    );
+    session.duration = session
+        .duration
+        .or_else(|| manifest.duration_seconds().map(|d| d as u64));
+    session.segments = Some(
+        manifest
+            .segments
+            .iter()
+            .map(|segment| segment.path.clone())
+            .collect(),
+    );
+    Ok(session)
+}
+
+/// Slippi replay directory from settings, or the default one
+fn slippi_directory_from_settings(app: &tauri::AppHandle) -> Result<String, Error> {
+    use tauri_plugin_store::StoreExt;
+    let store = app
+        .store("settings.json")
+        .map_err(|e| Error::InitializationError(format!("Failed to open settings store: {}", e)))?;
+
+    let configured = store
+        .get("slippiPath")
+        .and_then(|value| value.as_str().map(|s| s.to_string()))
+        .filter(|path| !path.is_empty());
+    Ok(configured.unwrap_or_else(|| {
+        slippi_paths::get_default_slippi_path()
+            .to_str()
+            .unwrap_or("")
+            .to_string()
+    }))
+}
+
+/// The replay recorded with a video, if there is one
+async fn slp_for_video(app: &tauri::AppHandle, video_path: &str) -> Option<String> {
+    let slippi_dir = slippi_directory_from_settings(app).ok()?;
+    let video_filename = Path::new(video_path).file_stem()?.to_str()?;
+    find_matching_slp(video_filename, &slippi_dir).await
+}
+
 async fn find_matching_slp(video_filename: &str, slippi_dir: &str) -> Option<String> {
     if !video_filename.starts_with("Game_") {
         log::debug!(
@@ -884,7 +1702,12 @@ async fn find_matching_slp(video_filename: &str, slippi_dir: &str) -> Option<Str
 async fn parse_slp_file_cached(
     slp_path: &str,
     state: &State<'_, AppState>,
-) -> (Option<SlippiMetadata>, Option<u64>, Option<String>) {
+) -> (
+    Option<SlippiMetadata>,
+    Option<u64>,
+    Option<String>,
+    Option<Vec<crate::slippi::TechStats>>,
+) {
     use peppi::io::slippi::read;
     use std::fs::File;
     use std::io::BufReader;
@@ -901,7 +1724,12 @@ async fn parse_slp_file_cached(
             if entry.modified_time == modified_time {
                 log::debug!("✅ Using cached .slp data for: {}", slp_path);
                 let metadata = serde_json::from_value(entry.metadata.clone()).ok();
-                return (metadata, entry.duration, entry.end_time.clone());
+                return (
+                    metadata,
+                    entry.duration,
+                    entry.end_time.clone(),
+                    entry.tech_stats.clone(),
+                );
             }
         }
     }
@@ -910,7 +1738,7 @@ async fn parse_slp_file_cached(
         Ok(f) => f,
         Err(e) => {
             log::error!("Failed to open .slp file: {:?}", e);
-            return (None, None, None);
+            return (None, None, None, None);
         }
     };
 
@@ -996,6 +1824,14 @@ async fn parse_slp_file_cached(
 
             let total_frames = game.frames.len() as i32;
 
+            let tech_stats = match crate::slippi::compute_tech_stats(&game) {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    log::warn!("Failed to compute tech stats for {}: {:?}", slp_path, e);
+                    None
+                }
+            };
+
             let metadata = SlippiMetadata {
                 characters,
                 stage,
@@ -1018,6 +1854,7 @@ async fn parse_slp_file_cached(
                                 metadata: metadata_json,
                                 duration: Some(duration_secs),
                                 end_time: Some(start_time.clone()),
+                                tech_stats: tech_stats.clone(),
                                 modified_time,
                             },
                         );
@@ -1026,11 +1863,16 @@ async fn parse_slp_file_cached(
                 }
             }
 
-            (Some(metadata), Some(duration_secs), Some(start_time))
+            (
+                Some(metadata),
+                Some(duration_secs),
+                Some(start_time),
+                tech_stats,
+            )
         }
         Err(e) => {
             log::error!("Failed to parse .slp file: {:?}", e);
-            (None, None, None)
+            (None, None, None, None)
         }
     }
 }
@@ -1631,6 +2473,47 @@ pub async fn set_game_process_name(
     Ok(())
 }
 
+/// Get the profile recordings will use, custom or from the quality preset
+#[tauri::command]
+pub async fn get_recording_profile(
+    app: tauri::AppHandle,
+) -> Result<recorder::RecordingProfile, Error> {
+    Ok(resolve_recording_profile(&app))
+}
+
+/// Save a custom recording profile, or clear it with `None` to go back to the
+/// quality presets. The profile is checked against the selected backend first.
+#[tauri::command]
+pub async fn set_recording_profile(
+    profile: Option<recorder::RecordingProfile>,
+    app: tauri::AppHandle,
+) -> Result<(), Error> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app
+        .store("settings.json")
+        .map_err(|e| Error::InitializationError(format!("Failed to open settings store: {}", e)))?;
+
+    match profile {
+        Some(profile) => {
+            profile.validate(&create_recorder_from_settings(&app).capabilities())?;
+            log::info!("Saving recording profile: {}", profile.summary());
+            let value = serde_json::to_value(&profile).map_err(|e| {
+                Error::InitializationError(format!("Failed to serialize profile: {}", e))
+            })?;
+            store.set("recordingProfile", value);
+        }
+        None => {
+            log::info!("Clearing custom recording profile");
+            store.delete("recordingProfile");
+        }
+    }
+
+    store
+        .save()
+        .map_err(|e| Error::InitializationError(format!("Failed to save settings: {}", e)))
+}
+
 /// Open a file location in the system file explorer
 #[tauri::command]
 pub fn open_file_location(path: String) -> Result<(), Error> {
@@ -1672,17 +2555,82 @@ pub fn open_file_location(path: String) -> Result<(), Error> {
     Ok(())
 }
 
-/// Parse a .slp file and extract game events (deaths, combos, etc.)
+/// Parse a .slp file and extract game events (deaths, combos, etc.). With the
+/// video recorded alongside, timestamps are positions in that video.
 #[tauri::command]
-pub async fn parse_slp_events(slp_path: String) -> Result<Vec<crate::slippi::GameEvent>, Error> {
+pub async fn parse_slp_events(
+    jobs: State<'_, JobQueue>,
+    slp_path: String,
+    video_path: Option<String>,
+) -> Result<Vec<crate::slippi::GameEvent>, Error> {
     // Parse the .slp file
     let game = crate::slippi::parse_slp_file(&slp_path)?;
 
-    // Extract death events
-    let events = crate::slippi::extract_death_events(&game)?;
+    // Extract death and combo events, ordered by frame for the timeline
+    let mut events = crate::slippi::extract_death_events(&game)?;
+    events.extend(crate::slippi::extract_combo_events(&game)?);
+    events.sort_by_key(|e| e.frame());
+
+    // A clip's events are placed through the recording it was cut from
+    let clip = video_path.as_deref().and_then(ClipMetadata::load);
+    let synced_video = match &clip {
+        Some(clip) => Some(clip.source_recording.as_str()),
+        None => video_path.as_deref(),
+    };
+    // Someone's waiting on the timeline, so this skips the queue
+    let sync = match synced_video {
+        Some(video) => sync_replay(&jobs, video, &slp_path, game, JobPriority::High).await?,
+        None => None,
+    };
+    if let Some(sync) = &sync {
+        sync.retime(&mut events);
+    }
+
+    if let Some(clip) = clip {
+        // Unsynced, frame 0 is at 0s of the recording, so the clip's frames
+        // place it if it has them and its offset does otherwise
+        let start = match (&sync, clip.start_frame) {
+            (None, Some(frame)) => crate::slippi::events::frame_to_seconds(frame),
+            _ => clip.video_offset_seconds,
+        };
+        crate::slippi::sync::retime_to_clip(&mut events, start, clip.duration_seconds);
+    }
     Ok(events)
 }
 
+/// Where a replay starts in its video, calibrating it if that hasn't been done
+/// yet, or again when asked to
+#[tauri::command]
+pub async fn get_replay_sync(
+    jobs: State<'_, JobQueue>,
+    video_path: String,
+    slp_path: String,
+    recalibrate: bool,
+) -> Result<Option<crate::slippi::sync::SyncOffset>, Error> {
+    if recalibrate {
+        let _ = std::fs::remove_file(crate::slippi::sync::SyncOffset::path_for(&video_path));
+    }
+    let game = crate::slippi::parse_slp_file(&slp_path)?;
+    let sync = sync_replay(&jobs, &video_path, &slp_path, game, JobPriority::High).await?;
+    Ok(sync.map(|sync| sync.offset().clone()))
+}
+
+/// Parse a .slp file and compute conversion / punish stats for each player
+#[tauri::command]
+pub async fn parse_slp_stats(slp_path: String) -> Result<crate::slippi::GameStats, Error> {
+    let game = crate::slippi::parse_slp_file(&slp_path)?;
+    crate::slippi::compute_game_stats(&game)
+}
+
+/// Parse a .slp file and compute tech-skill stats (L-cancels, wavedashes, etc.) for each player
+#[tauri::command]
+pub async fn parse_slp_tech_stats(
+    slp_path: String,
+) -> Result<Vec<crate::slippi::TechStats>, Error> {
+    let game = crate::slippi::parse_slp_file(&slp_path)?;
+    crate::slippi::compute_tech_stats(&game)
+}
+
 // --- Windows-only helpers for preview capture ---
 
 #[cfg(target_os = "windows")]
@@ -1902,26 +2850,175 @@ fn capture_hwnd_png(hwnd: HWND) -> Result<Vec<u8>, String> {
 // CLIP COMMANDS
 // ============================================================================
 
-/// Mark a timestamp for clip creation
+/// Mark a timestamp for clip creation. Without a pre-roll the clip keeps the
+/// `clipDuration` setting's worth of recording before the marker. Markers are
+/// saved next to the recording straight away.
 #[tauri::command]
-pub fn mark_clip_timestamp(
+#[allow(clippy::too_many_arguments)]
+pub async fn mark_clip_timestamp(
     recording_file: String,
     timestamp: f64,
+    pre_roll_seconds: Option<f64>,
+    post_roll_seconds: Option<f64>,
+    label: Option<String>,
+    source: Option<crate::clip_markers::ClipSource>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), Error> {
+    let pre_roll_seconds = pre_roll_seconds.unwrap_or_else(|| {
+        use tauri_plugin_store::StoreExt;
+        app.store("settings.json")
+            .ok()
+            .and_then(|store| store.get("clipDuration"))
+            .and_then(|v| v.as_f64())
+            .unwrap_or(30.0)
+    });
+
+    let video_path = recording_video_path(&app, &recording_file).await?;
+
     let mut markers = state
         .clip_markers
         .lock()
         .map_err(|e| Error::InitializationError(format!("Failed to lock clip markers: {}", e)))?;
 
-    markers.push(crate::app_state::ClipMarker {
+    let mut file = crate::clip_markers::ClipMarkerFile::load(&video_path).unwrap_or_default();
+    file.merge(
+        markers
+            .iter()
+            .filter(|m| m.recording_file == recording_file)
+            .cloned(),
+    );
+    let marker = crate::clip_markers::ClipMarker {
+        id: file.next_id(),
         recording_file,
         timestamp_seconds: timestamp,
-    });
+        pre_roll_seconds: pre_roll_seconds.max(0.0),
+        post_roll_seconds: post_roll_seconds.unwrap_or(0.0).max(0.0),
+        label,
+        source: source.unwrap_or_default(),
+        clip_path: None,
+        skipped: false,
+    };
+    file.merge([marker.clone()]);
+    if let Err(e) = file.write(&video_path) {
+        log::warn!("Failed to save clip marker for {}: {:?}", video_path, e);
+    }
+
+    markers.push(marker);
     Ok(())
 }
 
-/// Process all clip markers for a recording file
+/// The video behind a recording identifier: a `.slp` maps to the video
+/// recorded alongside it, in whichever container that was made, and relative
+/// paths are in the recording directory
+async fn recording_video_path(
+    app: &tauri::AppHandle,
+    recording_file: &str,
+) -> Result<String, Error> {
+    let recording_file = if Path::new(recording_file).is_absolute() {
+        recording_file.to_string()
+    } else {
+        let recording_dir = get_recording_directory_internal(app).await?;
+        format!("{}/{}", recording_dir, recording_file)
+    };
+    let Some(stem) = recording_file.strip_suffix(".slp") else {
+        return Ok(recording_file);
+    };
+
+    // The recording still going, then one already made
+    let current = app
+        .state::<AppState>()
+        .current_recording_file
+        .lock()
+        .ok()
+        .and_then(|current| current.clone());
+    if let Some(current) =
+        current.filter(|current| Path::new(current).with_extension("") == Path::new(stem))
+    {
+        return Ok(current);
+    }
+    for container in [
+        recorder::profile::Container::Mp4,
+        recorder::profile::Container::Mkv,
+    ] {
+        let video_path = format!("{}.{}", stem, container.extension());
+        if Path::new(&video_path).exists()
+            || recorder::segmented::SegmentManifest::path_for(&video_path).exists()
+        {
+            return Ok(video_path);
+        }
+    }
+
+    // Not recorded yet, so it will be in the container recordings are made in now
+    Ok(format!(
+        "{}.{}",
+        stem,
+        resolve_recording_profile(app).container.extension()
+    ))
+}
+
+/// The recording's markers file, re-read under the markers lock with any
+/// markers that couldn't be saved merged in, then `update`d and written back.
+/// Markers can be added while clips are being cut, so every write goes through
+/// here rather than writing out an older copy.
+fn update_marker_file(
+    state: &AppState,
+    recording_file: &str,
+    video_path: &str,
+    update: impl FnOnce(&mut crate::clip_markers::ClipMarkerFile),
+) -> Result<crate::clip_markers::ClipMarkerFile, Error> {
+    let mut markers = state
+        .clip_markers
+        .lock()
+        .map_err(|e| Error::InitializationError(format!("Failed to lock clip markers: {}", e)))?;
+
+    let mut file = crate::clip_markers::ClipMarkerFile::load(video_path).unwrap_or_default();
+    file.merge(
+        markers
+            .iter()
+            .filter(|m| m.recording_file == recording_file)
+            .cloned(),
+    );
+    update(&mut file);
+    match file.write(video_path) {
+        // The file has them from here on
+        Ok(()) => markers.retain(|m| m.recording_file != recording_file),
+        Err(e) => log::warn!("Failed to save clip markers for {}: {:?}", video_path, e),
+    }
+    Ok(file)
+}
+
+/// Cut clips for markers left over from before the app was last closed
+#[tauri::command]
+pub async fn resume_clip_markers(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, Error> {
+    let recording_dir = get_recording_directory_internal(&app).await?;
+    let current = state
+        .current_recording_file
+        .lock()
+        .ok()
+        .and_then(|current| current.clone());
+
+    let mut created_clips = Vec::new();
+    for video_path in crate::clip_markers::find_pending(Path::new(&recording_dir)) {
+        // The recording still going will be processed when it stops
+        if current.as_deref() == Some(video_path.as_str()) {
+            continue;
+        }
+        log::info!("📌 Resuming clip markers for {}", video_path);
+        match process_clip_markers(video_path.clone(), app.clone(), state.clone()).await {
+            Ok(clips) => created_clips.extend(clips),
+            Err(e) => log::error!("Failed to resume clip markers for {}: {:?}", video_path, e),
+        }
+    }
+    Ok(created_clips)
+}
+
+/// Process the clip markers of a recording file that haven't been cut yet.
+/// Each clip is noted in the markers file once it's made, so this can be run
+/// again after an interruption without cutting anything twice.
 #[tauri::command]
 pub async fn process_clip_markers(
     recording_file: String,
@@ -1938,38 +3035,28 @@ pub async fn process_clip_markers(
         }
     }
 
-    // Get clip duration from settings
-    let clip_duration = {
+    // Get clip cut mode from settings
+    let cut_mode: crate::clip_processor::ClipCutMode = {
         use tauri_plugin_store::StoreExt;
         let store = app.store("settings.json").map_err(|e| {
             Error::InitializationError(format!("Failed to open settings store: {}", e))
         })?;
 
         store
-            .get("clipDuration")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(30.0)
+            .get("clipCutMode")
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
     };
 
-    log::info!("⏱Clip duration: {}s", clip_duration);
-
-    // Get markers for this recording
-    let markers = {
-        let mut markers_lock = state.clip_markers.lock().map_err(|e| {
-            Error::InitializationError(format!("Failed to lock clip markers: {}", e))
-        })?;
-
-        let recording_markers: Vec<_> = markers_lock
-            .iter()
-            .filter(|m| m.recording_file == recording_file)
-            .cloned()
-            .collect();
+    log::info!("✂️ Clip cut mode: {:?}", cut_mode);
 
-        // Remove processed markers
-        markers_lock.retain(|m| m.recording_file != recording_file);
+    // Get recording directory
+    let recording_dir = get_recording_directory_internal(&app).await?;
+    let input_path = recording_video_path(&app, &recording_file).await?;
 
-        recording_markers
-    };
+    // Get markers for this recording: those saved next to it, and any from
+    // this session that couldn't be saved
+    let markers = update_marker_file(&state, &recording_file, &input_path, |_| {})?.pending();
 
     if markers.is_empty() {
         log::info!("ℹNo clip markers found for this recording");
@@ -1979,30 +3066,25 @@ pub async fn process_clip_markers(
     log::info!("Found {} clip marker(s) to process", markers.len());
     for (i, marker) in markers.iter().enumerate() {
         log::info!(
-            "  Marker {}: timestamp={}s",
+            "  Marker {}: timestamp={}s, -{}s/+{}s, {:?}{}",
             i + 1,
-            marker.timestamp_seconds
+            marker.timestamp_seconds,
+            marker.pre_roll_seconds,
+            marker.post_roll_seconds,
+            marker.source,
+            marker
+                .label
+                .as_ref()
+                .map(|label| format!(" \"{}\"", label))
+                .unwrap_or_default()
         );
     }
 
-    // Get recording directory
-    let recording_dir = get_recording_directory_internal(&app).await?;
-
-    // Determine video path (should have .mp4 extension)
-    let video_path = if recording_file.ends_with(".mp4") {
-        recording_file.clone()
-    } else {
-        format!("{}.mp4", recording_file.trim_end_matches(".slp"))
-    };
-
-    let input_path = if std::path::Path::new(&video_path).is_absolute() {
-        video_path.clone()
-    } else {
-        format!("{}/{}", recording_dir, video_path)
-    };
+    // A segmented recording only exists as its manifest
+    let segments = recorder::segmented::SegmentManifest::load(&input_path);
 
     // Verify input file exists
-    if !std::path::Path::new(&input_path).exists() {
+    if segments.is_none() && !std::path::Path::new(&input_path).exists() {
         log::error!("Recording file not found: {}", input_path);
         return Err(Error::InvalidPath(format!(
             "Recording file not found: {}",
@@ -2012,6 +3094,29 @@ pub async fn process_clip_markers(
 
     log::debug!("Input video exists: {}", input_path);
 
+    // The replay recorded with it places the markers, when the recording is
+    // synced to it, and describes the clips
+    let slp_path = slp_for_video(&app, &input_path).await;
+    let sync = match (&segments, &slp_path) {
+        (None, Some(slp)) => match crate::slippi::parse_slp_file(slp) {
+            Ok(game) => {
+                let jobs = app.state::<JobQueue>();
+                sync_replay(&jobs, &input_path, slp, game, JobPriority::Normal)
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to sync {} to its replay: {:?}", input_path, e);
+                        None
+                    })
+            }
+            Err(_) => None,
+        },
+        _ => None,
+    };
+    let slippi_metadata = match &slp_path {
+        Some(slp) => parse_slp_file_cached(slp, &state).await.0,
+        None => None,
+    };
+
     // Create clips directory alongside the recordings directory (Peppi/Clips)
     let recording_dir_path = Path::new(&recording_dir);
     let clips_parent_dir = recording_dir_path.parent().unwrap_or(recording_dir_path);
@@ -2031,11 +3136,61 @@ pub async fn process_clip_markers(
     })?;
     log::info!("Clips directory ready");
 
+    // Each marker's window in the video, clamped to the recording and merged
+    // where they overlap
+    let recording_length = match &segments {
+        Some(manifest) => manifest.duration_seconds(),
+        None => crate::clip_processor::probe_duration(&input_path)
+            .map_err(|e| log::warn!("Couldn't read the recording's length: {:?}", e))
+            .ok(),
+    };
+    let marker_windows: Vec<(f64, f64)> = markers
+        .iter()
+        .map(|marker| {
+            let marker_time = sync.as_ref().map_or(marker.timestamp_seconds, |sync| {
+                sync.marker_seconds(marker.timestamp_seconds)
+            });
+            (
+                marker_time - marker.pre_roll_seconds,
+                marker_time + marker.post_roll_seconds,
+            )
+        })
+        .collect();
+    let windows = crate::clip_processor::merge_clip_windows(&marker_windows, recording_length);
+    if windows.len() < markers.len() {
+        log::info!(
+            "Merged {} marker(s) into {} clip(s)",
+            markers.len(),
+            windows.len()
+        );
+    }
+
+    // Markers entirely outside the recording have nothing to cut; without
+    // noting that they'd be pending for good
+    let outside: Vec<u32> = markers
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !windows.iter().any(|window| window.markers.contains(i)))
+        .map(|(_, marker)| marker.id)
+        .collect();
+    if !outside.is_empty() {
+        log::warn!(
+            "⚠️ Skipping {} clip marker(s) outside the recording",
+            outside.len()
+        );
+        update_marker_file(&state, &recording_file, &input_path, |file| {
+            file.set_skipped(&outside)
+        })?;
+    }
+
     let mut created_clips = Vec::new();
+    let jobs = app.state::<JobQueue>();
 
-    // Process each marker
-    for (idx, marker) in markers.iter().enumerate() {
-        let start_time = (marker.timestamp_seconds - clip_duration).max(0.0);
+    // Cut each window
+    for (idx, window) in windows.iter().enumerate() {
+        let start_time = window.start;
+        let clip_duration = window.duration();
+        let ids: Vec<u32> = window.markers.iter().map(|&i| markers[i].id).collect();
 
         // Extract timestamp from filename for naming
         let timestamp = std::path::Path::new(&recording_file)
@@ -2044,31 +3199,86 @@ pub async fn process_clip_markers(
             .map(|s| s.strip_prefix("Game_").unwrap_or(s))
             .unwrap_or("unknown");
 
-        let clip_filename = format!("Clip_{}_{:03}.mp4", timestamp, idx + 1);
+        // Named after the window's first marker, so cutting it again after an
+        // interruption replaces any partial file
+        let first_id = ids.iter().copied().min().unwrap_or(idx as u32 + 1);
+        let clip_filename = format!("Clip_{}_{:03}.mp4", timestamp, first_id);
         let output_path = clips_dir_path.join(&clip_filename);
         let output_path_str = output_path
             .to_str()
             .map(|s| s.to_string())
             .ok_or_else(|| Error::InvalidPath("Failed to build clip output path".to_string()))?;
 
-        // Extract clip using FFmpeg
-        match crate::clip_processor::extract_clip(
-            &input_path,
-            &output_path_str,
-            start_time,
-            clip_duration,
-        ) {
+        // Extract clip using FFmpeg, queued behind any other media work
+        let pieces = segments
+            .as_ref()
+            .map(|manifest| manifest.locate(start_time, clip_duration));
+        let input = input_path.clone();
+        let output = output_path_str.clone();
+        let job = jobs.submit(
+            JobKind::Clip,
+            JobPriority::Normal,
+            clip_filename.clone(),
+            move || match pieces {
+                Some(pieces) => {
+                    crate::clip_processor::extract_spanning_clip(&pieces, &output, cut_mode)
+                }
+                None => crate::clip_processor::cut_clip(
+                    &input,
+                    &output,
+                    start_time,
+                    clip_duration,
+                    cut_mode,
+                ),
+            },
+        );
+        match wait_for_job(job).await {
             Ok(_) => {
                 log::info!(
                     "Clip created ({}/{}): {} (start {}s, duration {}s)",
                     idx + 1,
-                    markers.len(),
+                    windows.len(),
                     clip_filename,
                     start_time,
                     clip_duration
                 );
+                let labels: Vec<&str> = window
+                    .markers
+                    .iter()
+                    .filter_map(|&i| markers[i].label.as_deref())
+                    .collect();
+                let metadata = ClipMetadata {
+                    source_recording: input_path.clone(),
+                    slp_path: slp_path.clone(),
+                    start_frame: sync.as_ref().map(|sync| sync.frame_at(start_time)),
+                    end_frame: sync
+                        .as_ref()
+                        .map(|sync| sync.frame_at(start_time + clip_duration)),
+                    video_offset_seconds: start_time,
+                    duration_seconds: clip_duration,
+                    label: (!labels.is_empty()).then(|| labels.join(", ")),
+                    players: slippi_metadata
+                        .as_ref()
+                        .map(|metadata| metadata.players.clone())
+                        .unwrap_or_default(),
+                    stage: slippi_metadata.as_ref().map(|metadata| metadata.stage),
+                };
+                if let Err(e) = metadata.write(&output_path_str) {
+                    log::warn!("Failed to save clip metadata: {:?}", e);
+                }
+
+                if let Err(e) = update_marker_file(&state, &recording_file, &input_path, |file| {
+                    file.set_clip(&ids, &output_path_str)
+                }) {
+                    log::warn!("Failed to note processed clip markers: {:?}", e);
+                }
                 created_clips.push(output_path_str);
             }
+            Err(Error::Cancelled) => {
+                // Its markers stay pending, so it's cut the next time round
+                log::info!("🛑 Clip {} cancelled", clip_filename);
+                let _ = std::fs::remove_file(&output_path_str);
+            }
             Err(e) => {
                 log::error!("Failed to create clip: {:?}", e);
                 return Err(e);
@@ -2088,21 +3298,75 @@ pub async fn process_clip_markers(
     Ok(created_clips)
 }
 
-/// Compress video for cloud upload
+/// Compress video for cloud upload, optionally just the range starting at
+/// `start_seconds`. Runs as a background job, so it can be followed and
+/// cancelled like any other.
 #[tauri::command]
-pub async fn compress_video_for_upload(input_path: String) -> Result<String, Error> {
+pub async fn compress_video_for_upload(
+    input_path: String,
+    start_seconds: Option<f64>,
+    duration_seconds: Option<f64>,
+    jobs: State<'_, JobQueue>,
+) -> Result<String, Error> {
     log::info!("Compressing video for upload: {}", input_path);
 
     crate::clip_processor::ensure_ffmpeg()?;
 
+    let label = std::path::Path::new(&input_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("video")
+        .to_string();
+    let job = jobs.submit(JobKind::Compress, JobPriority::Normal, label, move || {
+        compress_video(&input_path, start_seconds, duration_seconds)
+    });
+    let output_path = wait_for_job(job).await?;
+
+    log::info!("Video compressed successfully");
+    Ok(output_path)
+}
+
+fn compress_video(
+    input_path: &str,
+    start_seconds: Option<f64>,
+    duration_seconds: Option<f64>,
+) -> Result<String, Error> {
     // Generate output path in temp directory
-    let input_file = std::path::Path::new(&input_path);
+    let input_file = std::path::Path::new(input_path);
     let file_stem = input_file
         .file_stem()
         .and_then(|s| s.to_str())
         .ok_or_else(|| Error::InvalidPath("Invalid input path".into()))?;
 
     let temp_dir = std::env::temp_dir();
+
+    // Segments and ranges are cut out first, so there's a single file to compress
+    let segments = recorder::segmented::SegmentManifest::load(input_path);
+    let source_path = if segments.is_some() || start_seconds.is_some() {
+        let extension = input_file
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("mp4");
+        let source = temp_dir
+            .join(format!("{}_source.{}", file_stem, extension))
+            .to_string_lossy()
+            .to_string();
+        let start = start_seconds.unwrap_or(0.0);
+        let duration = duration_seconds.unwrap_or(f64::INFINITY);
+        match &segments {
+            Some(manifest) => crate::clip_processor::extract_spanning_clip(
+                &manifest.locate(start, duration),
+                &source,
+                crate::clip_processor::ClipCutMode::Copy,
+            )?,
+            None => crate::clip_processor::extract_clip(input_path, &source, start, duration)?,
+        }
+        Some(source)
+    } else {
+        None
+    };
+    let source = source_path.as_deref().unwrap_or(input_path);
+
     let output_path = temp_dir.join(format!("{}_compressed.mp4", file_stem));
     let output_path_str = output_path
         .to_str()
@@ -2119,7 +3383,7 @@ pub async fn compress_video_for_upload(input_path: String) -> Result<String, Err
 
     let mut command = FfmpegCommand::new();
     command
-        .input(&input_path)
+        .input(source)
         .args(&[
             "-c:v",
             "libx264",
@@ -2137,20 +3401,19 @@ pub async fn compress_video_for_upload(input_path: String) -> Result<String, Err
         .output(&output_path_str)
         .overwrite();
 
-    let output = command
-        .spawn()
-        .map_err(|e| Error::RecordingFailed(format!("Failed to start FFmpeg: {}", e)))?
-        .wait()
-        .map_err(|e| Error::RecordingFailed(format!("FFmpeg failed: {}", e)))?;
-
-    if !output.success() {
-        return Err(Error::RecordingFailed(format!(
-            "FFmpeg exited with error: {:?}",
-            output
-        )));
+    // Progress is measured against the length of what's being compressed
+    let expected_seconds = duration_seconds
+        .filter(|d| d.is_finite())
+        .or_else(|| crate::clip_processor::probe_duration(source).ok());
+    let outcome = crate::clip_processor::run_ffmpeg(&mut command, expected_seconds);
+    if let Some(source) = &source_path {
+        let _ = std::fs::remove_file(source);
+    }
+    if let Err(e) = outcome {
+        let _ = std::fs::remove_file(&output_path_str);
+        return Err(e);
     }
 
-    log::info!("Video compressed successfully");
     Ok(output_path_str)
 }
 
@@ -2168,6 +3431,7 @@ pub async fn delete_temp_file(path: String) -> Result<(), Error> {
 pub async fn get_clips(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
+    jobs: State<'_, JobQueue>,
 ) -> Result<Vec<RecordingSession>, Error> {
     log::debug!("📂 Scanning for clips...");
 
@@ -2201,34 +3465,7 @@ pub async fn get_clips(
     }
 
     // Get Slippi directory for metadata matching
-    let slippi_dir = {
-        use tauri_plugin_store::StoreExt;
-        let store = app.store("settings.json").map_err(|e| {
-            Error::InitializationError(format!("Failed to open settings store: {}", e))
-        })?;
-
-        if let Some(value) = store.get("slippiPath") {
-            if let Some(path) = value.as_str() {
-                if !path.is_empty() {
-                    Some(path.to_string())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    };
-
-    let slippi_dir = match slippi_dir {
-        Some(path) => path,
-        None => slippi_paths::get_default_slippi_path()
-            .to_str()
-            .unwrap_or("")
-            .to_string(),
-    };
+    let slippi_dir = slippi_directory_from_settings(&app)?;
 
     log::debug!("📁 Slippi directory: {}", slippi_dir);
 
@@ -2242,7 +3479,26 @@ pub async fn get_clips(
     {
         let path = entry.path();
         if path.extension().and_then(|s| s.to_str()) == Some("mp4") {
-            if let Ok(session) = create_recording_session(path, &slippi_dir, &state).await {
+            if let Ok(mut session) =
+                create_recording_session(path, &slippi_dir, &state, &jobs).await
+            {
+                // Clips aren't named after their replay, so the sidecar leads back to it
+                if let Some(clip) = ClipMetadata::load(&path.to_string_lossy()) {
+                    if let Some(slp) = clip
+                        .slp_path
+                        .as_deref()
+                        .filter(|slp| session.slp_path.is_empty() && Path::new(slp).exists())
+                    {
+                        let (slippi_metadata, _, end_time, tech_stats) =
+                            parse_slp_file_cached(slp, &state).await;
+                        session.slp_path = slp.to_string();
+                        session.slippi_metadata = slippi_metadata;
+                        session.end_time = end_time;
+                        session.tech_stats = tech_stats;
+                    }
+                    session.duration = Some(clip.duration_seconds.round() as u64);
+                    session.clip = Some(clip);
+                }
                 clips.push(session);
             } else {
                 log::warn!("⚠️ Failed to load clip metadata for {:?}", path);
@@ -2256,3 +3512,79 @@ pub async fn get_clips(
     log::info!("✅ Found {} clip(s)", clips.len());
     Ok(clips)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::Recorder;
+
+    #[test]
+    fn test_failed_stop_keeps_the_marker() {
+        let dir = std::env::temp_dir().join("buckwheat_stop_marker_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Game.mp4").to_string_lossy().to_string();
+        let marker = recorder::recovery::marker_path(&video);
+        recorder::recovery::write_marker(&video, None).unwrap();
+
+        // Stopping a recorder that isn't recording fails, like a stop that
+        // couldn't finish the file
+        let mut failed = recorder::mock::MockRecorder::without_video();
+        assert!(stop_and_clear_marker(&mut failed).is_err());
+        assert!(marker.exists());
+
+        let mut finished = recorder::mock::MockRecorder::without_video();
+        finished
+            .start_recording(&video, &recorder::RecordingQuality::Low.into())
+            .unwrap();
+        assert_eq!(stop_and_clear_marker(&mut finished).unwrap(), video);
+        assert!(!marker.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_clip_metadata_round_trips() {
+        let dir = std::env::temp_dir().join("buckwheat_clip_metadata_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let clip = dir
+            .join("Clip_20260101T120000_001.mp4")
+            .to_string_lossy()
+            .to_string();
+
+        let metadata = ClipMetadata {
+            source_recording: "/recordings/Game_20260101T120000.mp4".to_string(),
+            slp_path: Some("/replays/Game_20260101T120000.slp".to_string()),
+            start_frame: Some(1200),
+            end_frame: Some(2100),
+            video_offset_seconds: 22.5,
+            duration_seconds: 15.0,
+            label: Some("Edgeguard".to_string()),
+            players: vec![PlayerInfo {
+                character_id: 2,
+                character_color: 0,
+                player_tag: "FOX".to_string(),
+                port: 1,
+            }],
+            stage: Some(31),
+        };
+        metadata.write(&clip).unwrap();
+
+        let loaded = ClipMetadata::load(&clip).unwrap();
+        assert_eq!(loaded.source_recording, metadata.source_recording);
+        assert_eq!(loaded.slp_path, metadata.slp_path);
+        assert_eq!(
+            (loaded.start_frame, loaded.end_frame),
+            (Some(1200), Some(2100))
+        );
+        assert_eq!(loaded.video_offset_seconds, 22.5);
+        assert_eq!(loaded.duration_seconds, 15.0);
+        assert_eq!(loaded.label.as_deref(), Some("Edgeguard"));
+        assert_eq!(loaded.players[0].player_tag, "FOX");
+        assert_eq!(loaded.stage, Some(31));
+        assert!(ClipMetadata::load(&format!("{}.missing", clip)).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
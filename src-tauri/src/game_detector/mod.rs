@@ -1,22 +1,32 @@
 pub mod slippi_paths;
+pub mod state;
 
 use crate::commands::errors::Error;
+use crate::slippi::{GameEndReason, LiveEvent};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use state::{GameLifecycle, LifecycleEvent, Transition};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Emitter};
 
 pub struct GameDetector {
     slippi_path: PathBuf,
     watcher: Option<Box<dyn Watcher + Send>>,
     app_handle: Option<AppHandle>,
+    lifecycle: Arc<Mutex<GameLifecycle>>,
+    /// Signals any live replay tailers to stop
+    live_stop: Arc<AtomicBool>,
 }
 
 impl GameDetector {
-    pub fn new(slippi_path: PathBuf) -> Self {
+    pub fn new(slippi_path: PathBuf, lifecycle: Arc<Mutex<GameLifecycle>>) -> Self {
         Self {
             slippi_path,
             watcher: None,
             app_handle: None,
+            lifecycle,
+            live_stop: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -28,6 +38,11 @@ impl GameDetector {
         let app_handle = self.app_handle.clone();
         let watch_path = self.slippi_path.clone();
 
+        // Fresh flag so tailers from a previous session don't keep a stale one alive
+        self.live_stop = Arc::new(AtomicBool::new(false));
+        let live_stop = self.live_stop.clone();
+        let lifecycle = self.lifecycle.clone();
+
         log::info!("🔧 Setting up file watcher for path: {:?}", watch_path);
         log::info!("🔧 Path exists: {}", watch_path.exists());
         log::info!("🔧 Path is directory: {}", watch_path.is_dir());
@@ -58,21 +73,24 @@ impl GameDetector {
                                 if ext == "slp" {
                                     log::info!("🎮 New Slippi replay detected: {:?}", path);
 
-                                    // Emit event to trigger auto-recording
                                     if let Some(handle) = &app_handle {
-                                        let path_string = path.to_string_lossy().to_string();
-                                        log::info!(
-                                            "📤 Emitting slp-file-created event with path: {}",
-                                            path_string
-                                        );
-
-                                        match handle.emit("slp-file-created", path_string.clone()) {
-                                            Ok(_) => log::info!("✅ Event emitted successfully"),
-                                            Err(e) => log::error!(
-                                                "❌ Failed to emit slp-file-created event: {:?}",
-                                                e
-                                            ),
+                                        let slp_path = path.to_string_lossy().to_string();
+                                        if let Err(e) = advance(
+                                            &lifecycle,
+                                            handle,
+                                            LifecycleEvent::ReplayCreated { slp_path },
+                                        ) {
+                                            // Duplicate create events for the same file land here
+                                            log::warn!("⚠️ Ignoring replay creation: {}", e);
+                                            continue;
                                         }
+
+                                        spawn_live_tailer(
+                                            path,
+                                            handle.clone(),
+                                            lifecycle.clone(),
+                                            live_stop.clone(),
+                                        );
                                     } else {
                                         log::error!("❌ App handle is None, cannot emit event");
                                     }
@@ -84,30 +102,6 @@ impl GameDetector {
                             }
                         }
                     }
-
-                    // Handle MODIFY events (game in progress)
-                    if let EventKind::Modify(_) = event.kind {
-                        for path in &event.paths {
-                            if let Some(ext) = path.extension() {
-                                if ext == "slp" {
-                                    // Emit event to update last modification time
-                                    if let Some(handle) = &app_handle {
-                                        let path_string = path.to_string_lossy().to_string();
-                                        log::debug!("📝 .slp file modified: {}", path_string);
-
-                                        if let Err(e) =
-                                            handle.emit("slp-file-modified", path_string)
-                                        {
-                                            log::error!(
-                                                "❌ Failed to emit slp-file-modified event: {:?}",
-                                                e
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
                 }
                 Err(e) => log::error!("❌ Watch error: {:?}", e),
             }
@@ -128,6 +122,84 @@ impl GameDetector {
 
     pub fn stop_watching(&mut self) {
         self.watcher = None;
+        self.live_stop.store(true, Ordering::SeqCst);
         log::info!("⏹️  Stopped watching for .slp files");
     }
 }
+
+/// Apply a lifecycle event and emit each resulting transition as `game-lifecycle`
+pub fn advance(
+    lifecycle: &Mutex<GameLifecycle>,
+    handle: &AppHandle,
+    event: LifecycleEvent,
+) -> Result<Vec<Transition>, Error> {
+    let transitions = lifecycle
+        .lock()
+        .map_err(|e| Error::InitializationError(format!("Failed to lock game lifecycle: {}", e)))?
+        .apply(event)?;
+
+    for transition in &transitions {
+        log::info!(
+            "🔄 Game lifecycle: {:?} -> {:?}",
+            transition.from,
+            transition.to
+        );
+        if let Err(e) = handle.emit("game-lifecycle", transition) {
+            log::error!("❌ Failed to emit game-lifecycle event: {:?}", e);
+        }
+    }
+
+    Ok(transitions)
+}
+
+/// Follow a newly created replay on its own thread, emitting live game events
+///
+/// Game start and end are decided here from the replay's own Game Start and
+/// Game End events and fed into the lifecycle.
+fn spawn_live_tailer(
+    path: &Path,
+    handle: AppHandle,
+    lifecycle: Arc<Mutex<GameLifecycle>>,
+    stop: Arc<AtomicBool>,
+) {
+    let path = path.to_path_buf();
+    let slp_path = path.to_string_lossy().to_string();
+    std::thread::spawn(move || {
+        let mut end_reason = None;
+        let result = crate::slippi::stream::tail_slp_file(&path, &stop, |event| {
+            if let LiveEvent::GameStart { .. } = event {
+                let started = LifecycleEvent::GameStarted {
+                    slp_path: slp_path.clone(),
+                };
+                if let Err(e) = advance(&lifecycle, &handle, started) {
+                    log::warn!("⚠️ {}", e);
+                }
+            }
+            if let Some(reason) = event.end_reason() {
+                end_reason = Some(reason);
+            }
+            if let Err(e) = handle.emit(event.event_name(), &event) {
+                log::error!("❌ Failed to emit {} event: {:?}", event.event_name(), e);
+            }
+        });
+        if let Err(e) = &result {
+            log::warn!("⚠️ Live replay tailing stopped for {:?}: {}", path, e);
+        }
+
+        // Watching was turned off, nobody is waiting for this game any more
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let reason = end_reason.unwrap_or(GameEndReason::Abandoned);
+        log::info!("🏁 Game ended ({:?}): {:?}", reason, path);
+        if let Err(e) = advance(
+            &lifecycle,
+            &handle,
+            LifecycleEvent::GameEnded { slp_path, reason },
+        ) {
+            // Already ended, e.g. treated as abandoned when the next game started
+            log::warn!("⚠️ {}", e);
+        }
+    });
+}
@@ -0,0 +1,355 @@
+// Game lifecycle state machine
+//
+// Idle -> GameStarting -> InGame -> GameEnded -> Finalizing -> Idle
+//
+// Every change goes through `GameLifecycle::apply`, which either returns the
+// transitions it made (to be emitted as `game-lifecycle` events) or rejects the
+// event if it makes no sense for the current phase.
+
+use crate::commands::errors::Error;
+use crate::slippi::GameEndReason;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Where the current game is in its lifecycle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum GamePhase {
+    #[default]
+    Idle,
+    /// Replay file created, waiting for the Game Start event
+    GameStarting {
+        slp_path: String,
+    },
+    InGame {
+        slp_path: String,
+    },
+    GameEnded {
+        slp_path: String,
+        reason: GameEndReason,
+    },
+    /// Recording is being stopped and post-processed
+    Finalizing {
+        slp_path: String,
+    },
+}
+
+/// Inputs that drive the state machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// A new .slp file appeared in the watched folder
+    ReplayCreated {
+        slp_path: String,
+    },
+    /// The replay's Game Start event was read
+    GameStarted {
+        slp_path: String,
+    },
+    /// The replay's Game End event was read (or the file was abandoned)
+    GameEnded {
+        slp_path: String,
+        reason: GameEndReason,
+    },
+    FinalizeStarted,
+    Finalized,
+}
+
+/// A single phase change, emitted to the frontend as `game-lifecycle`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: GamePhase,
+    pub to: GamePhase,
+}
+
+/// A replay created while the previous game was still ending
+#[derive(Debug)]
+struct PendingGame {
+    slp_path: String,
+    started: bool,
+    ended: Option<GameEndReason>,
+}
+
+#[derive(Debug, Default)]
+pub struct GameLifecycle {
+    phase: GamePhase,
+    pending: VecDeque<PendingGame>,
+    last_replay_path: Option<String>,
+    /// Replay whose game is being auto-recorded
+    recorded_game: Option<String>,
+}
+
+impl GameLifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> &GamePhase {
+        &self.phase
+    }
+
+    /// Most recently created replay, whether or not its game is still running
+    pub fn last_replay_path(&self) -> Option<&str> {
+        self.last_replay_path.as_deref()
+    }
+
+    /// Remember that the game for `slp_path` is being auto-recorded
+    pub fn set_recorded_game(&mut self, slp_path: Option<String>) {
+        self.recorded_game = slp_path;
+    }
+
+    pub fn is_recorded_game(&self, slp_path: &str) -> bool {
+        self.recorded_game.as_deref() == Some(slp_path)
+    }
+
+    fn is_pending(&self, slp_path: &str) -> bool {
+        self.pending.iter().any(|p| p.slp_path == slp_path)
+    }
+
+    fn pending_mut(&mut self, slp_path: &str) -> Option<&mut PendingGame> {
+        self.pending.iter_mut().find(|p| p.slp_path == slp_path)
+    }
+
+    fn queue(&mut self, slp_path: String) {
+        self.last_replay_path = Some(slp_path.clone());
+        self.pending.push_back(PendingGame {
+            slp_path,
+            started: false,
+            ended: None,
+        });
+    }
+
+    /// Apply an event, returning the transitions it caused
+    pub fn apply(&mut self, event: LifecycleEvent) -> Result<Vec<Transition>, Error> {
+        let mut transitions = Vec::new();
+
+        // Queued games keep running while we wait, so remember how far they got
+        match &event {
+            LifecycleEvent::GameStarted { slp_path } => {
+                if let Some(pending) = self.pending_mut(slp_path) {
+                    pending.started = true;
+                    return Ok(transitions);
+                }
+            }
+            LifecycleEvent::GameEnded { slp_path, reason } => {
+                let reason = *reason;
+                if let Some(pending) = self.pending_mut(slp_path) {
+                    pending.ended = Some(reason);
+                    return Ok(transitions);
+                }
+            }
+            _ => {}
+        }
+
+        match (&self.phase, event) {
+            (GamePhase::Idle, LifecycleEvent::ReplayCreated { slp_path }) => {
+                self.last_replay_path = Some(slp_path.clone());
+                self.transition(GamePhase::GameStarting { slp_path }, &mut transitions);
+            }
+
+            // Back to back games: the next replay can show up before we're done
+            // with the previous one, so hold it until finalizing completes
+            (
+                GamePhase::GameEnded {
+                    slp_path: current, ..
+                }
+                | GamePhase::Finalizing { slp_path: current },
+                LifecycleEvent::ReplayCreated { slp_path },
+            ) if *current != slp_path && !self.is_pending(&slp_path) => {
+                log::info!(
+                    "⏳ Queueing replay until the previous game is finalized: {}",
+                    slp_path
+                );
+                self.queue(slp_path);
+            }
+
+            // A new replay while a game is still running means we never saw the
+            // old one end (Dolphin crashed, Slippi restarted, ...)
+            (
+                GamePhase::GameStarting { slp_path: current }
+                | GamePhase::InGame { slp_path: current },
+                LifecycleEvent::ReplayCreated { slp_path },
+            ) if *current != slp_path => {
+                log::warn!(
+                    "⚠️ New replay before {} ended, treating it as abandoned",
+                    current
+                );
+                let ended = GamePhase::GameEnded {
+                    slp_path: current.clone(),
+                    reason: GameEndReason::Abandoned,
+                };
+                self.queue(slp_path);
+                self.transition(ended, &mut transitions);
+            }
+
+            (
+                GamePhase::GameStarting { slp_path: current },
+                LifecycleEvent::GameStarted { slp_path },
+            ) if *current == slp_path => {
+                self.transition(GamePhase::InGame { slp_path }, &mut transitions);
+            }
+
+            (
+                GamePhase::GameStarting { slp_path: current }
+                | GamePhase::InGame { slp_path: current },
+                LifecycleEvent::GameEnded { slp_path, reason },
+            ) if *current == slp_path => {
+                self.transition(GamePhase::GameEnded { slp_path, reason }, &mut transitions);
+            }
+
+            (GamePhase::GameEnded { slp_path, .. }, LifecycleEvent::FinalizeStarted) => {
+                let slp_path = slp_path.clone();
+                self.transition(GamePhase::Finalizing { slp_path }, &mut transitions);
+            }
+
+            (GamePhase::Finalizing { slp_path }, LifecycleEvent::Finalized) => {
+                if self.is_recorded_game(slp_path) {
+                    self.recorded_game = None;
+                }
+                self.transition(GamePhase::Idle, &mut transitions);
+
+                if let Some(next) = self.pending.pop_front() {
+                    let slp_path = next.slp_path;
+                    self.transition(
+                        GamePhase::GameStarting {
+                            slp_path: slp_path.clone(),
+                        },
+                        &mut transitions,
+                    );
+                    if next.started {
+                        self.transition(
+                            GamePhase::InGame {
+                                slp_path: slp_path.clone(),
+                            },
+                            &mut transitions,
+                        );
+                    }
+                    if let Some(reason) = next.ended {
+                        self.transition(
+                            GamePhase::GameEnded { slp_path, reason },
+                            &mut transitions,
+                        );
+                    }
+                }
+            }
+
+            (phase, event) => {
+                return Err(Error::InvalidGameTransition(format!(
+                    "{:?} is not valid while {:?}",
+                    event, phase
+                )));
+            }
+        }
+
+        Ok(transitions)
+    }
+
+    fn transition(&mut self, to: GamePhase, transitions: &mut Vec<Transition>) {
+        let from = std::mem::replace(&mut self.phase, to.clone());
+        transitions.push(Transition { from, to });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created(path: &str) -> LifecycleEvent {
+        LifecycleEvent::ReplayCreated {
+            slp_path: path.to_string(),
+        }
+    }
+
+    fn started(path: &str) -> LifecycleEvent {
+        LifecycleEvent::GameStarted {
+            slp_path: path.to_string(),
+        }
+    }
+
+    fn ended(path: &str) -> LifecycleEvent {
+        LifecycleEvent::GameEnded {
+            slp_path: path.to_string(),
+            reason: GameEndReason::Game,
+        }
+    }
+
+    #[test]
+    fn test_full_game_returns_to_idle() {
+        let mut lifecycle = GameLifecycle::new();
+        lifecycle.apply(created("a.slp")).unwrap();
+        lifecycle.apply(started("a.slp")).unwrap();
+        lifecycle.apply(ended("a.slp")).unwrap();
+        lifecycle.apply(LifecycleEvent::FinalizeStarted).unwrap();
+        let transitions = lifecycle.apply(LifecycleEvent::Finalized).unwrap();
+
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(lifecycle.phase(), &GamePhase::Idle);
+        assert_eq!(lifecycle.last_replay_path(), Some("a.slp"));
+    }
+
+    #[test]
+    fn test_rejects_impossible_transitions() {
+        let mut lifecycle = GameLifecycle::new();
+        assert!(lifecycle.apply(started("a.slp")).is_err());
+        assert!(lifecycle.apply(LifecycleEvent::Finalized).is_err());
+
+        lifecycle.apply(created("a.slp")).unwrap();
+        assert!(lifecycle.apply(created("a.slp")).is_err());
+        assert!(lifecycle.apply(ended("b.slp")).is_err());
+        assert_eq!(
+            lifecycle.phase(),
+            &GamePhase::GameStarting {
+                slp_path: "a.slp".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_replay_created_while_finalizing_is_queued() {
+        let mut lifecycle = GameLifecycle::new();
+        lifecycle.apply(created("a.slp")).unwrap();
+        lifecycle.apply(started("a.slp")).unwrap();
+        lifecycle.apply(ended("a.slp")).unwrap();
+        lifecycle.apply(LifecycleEvent::FinalizeStarted).unwrap();
+
+        assert!(lifecycle.apply(created("b.slp")).unwrap().is_empty());
+        assert!(lifecycle.apply(started("b.slp")).unwrap().is_empty());
+        assert_eq!(lifecycle.last_replay_path(), Some("b.slp"));
+
+        // Idle, GameStarting and then InGame, since b.slp started while queued
+        let transitions = lifecycle.apply(LifecycleEvent::Finalized).unwrap();
+        assert_eq!(transitions.len(), 3);
+        assert_eq!(
+            lifecycle.phase(),
+            &GamePhase::InGame {
+                slp_path: "b.slp".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_back_to_back_games_end_the_previous_one() {
+        let mut lifecycle = GameLifecycle::new();
+        lifecycle.apply(created("a.slp")).unwrap();
+        lifecycle.apply(started("a.slp")).unwrap();
+
+        let transitions = lifecycle.apply(created("b.slp")).unwrap();
+        assert_eq!(
+            transitions[0].to,
+            GamePhase::GameEnded {
+                slp_path: "a.slp".to_string(),
+                reason: GameEndReason::Abandoned
+            }
+        );
+
+        lifecycle.apply(LifecycleEvent::FinalizeStarted).unwrap();
+        lifecycle.apply(LifecycleEvent::Finalized).unwrap();
+        lifecycle.apply(started("b.slp")).unwrap();
+        assert!(lifecycle.apply(ended("a.slp")).is_err());
+        assert_eq!(
+            lifecycle.phase(),
+            &GamePhase::InGame {
+                slp_path: "b.slp".to_string()
+            }
+        );
+    }
+}
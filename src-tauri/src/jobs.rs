@@ -0,0 +1,452 @@
+// Background FFmpeg work
+//
+// Cutting clips, making thumbnails, compressing uploads, remuxing recovered
+// recordings and syncing replays run as jobs on a small pool of worker
+// threads, highest priority first, so only a few FFmpeg processes run at once.
+// One more worker only takes high priority jobs, so a thumbnail or a replay
+// the user is waiting on doesn't sit behind a long compress. Each job is
+// reported through a callback whenever its state or progress changes, which
+// the app sends to the frontend as `job-progress` events. The FFmpeg runs in
+// `clip_processor` find the job they're part of through `current()`, to report
+// progress and to stop when the job is cancelled.
+
+use crate::commands::errors::Error;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+/// Jobs run at once, besides the high priority worker, unless the
+/// `jobConcurrency` setting asks for a different limit
+pub const DEFAULT_CONCURRENCY: usize = 2;
+/// Finished jobs kept for `list`, newest last
+const FINISHED_KEPT: usize = 50;
+/// Smaller progress changes aren't worth an event
+const PROGRESS_STEP: f64 = 0.01;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Clip,
+    Thumbnail,
+    Compress,
+    Remux,
+    Sync,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// A job as the frontend sees it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub priority: JobPriority,
+    /// What's being worked on, usually the output's file name
+    pub label: String,
+    pub status: JobStatus,
+    /// 0 to 1, through the FFmpeg run in progress
+    pub progress: f64,
+    pub error: Option<String>,
+}
+
+/// Runs the job and says how it went; its result goes to the `JobHandle`
+type Work = Box<dyn FnOnce() -> (JobStatus, Option<String>) + Send>;
+
+struct QueuedJob {
+    id: JobId,
+    priority: JobPriority,
+    work: Work,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: JobId,
+    queued: Vec<QueuedJob>,
+    jobs: Vec<JobInfo>,
+    cancel_flags: HashMap<JobId, Arc<AtomicBool>>,
+}
+
+impl QueueState {
+    /// Highest priority first, then oldest first, skipping jobs below `min`
+    fn take_next(&mut self, min: JobPriority) -> Option<QueuedJob> {
+        let index = self
+            .queued
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| job.priority >= min)
+            .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(b.id.cmp(&a.id)))
+            .map(|(index, _)| index)?;
+        Some(self.queued.remove(index))
+    }
+
+    fn update(&mut self, id: JobId, change: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let info = self.jobs.iter_mut().find(|info| info.id == id)?;
+        change(info);
+        let info = info.clone();
+
+        if info.status.is_finished() {
+            self.cancel_flags.remove(&id);
+            let finished = self.jobs.iter().filter(|j| j.status.is_finished()).count();
+            if finished > FINISHED_KEPT {
+                if let Some(oldest) = self.jobs.iter().position(|j| j.status.is_finished()) {
+                    self.jobs.remove(oldest);
+                }
+            }
+        }
+        Some(info)
+    }
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    notify: Box<dyn Fn(&JobInfo) + Send + Sync>,
+}
+
+impl Shared {
+    fn update(&self, id: JobId, change: impl FnOnce(&mut JobInfo)) {
+        let info = match self.state.lock() {
+            Ok(mut state) => state.update(id, change),
+            Err(_) => None,
+        };
+        // Outside the lock, so the callback can look at the queue
+        if let Some(info) = info {
+            (self.notify)(&info);
+        }
+    }
+}
+
+/// The queue FFmpeg work is submitted to; cheap to clone
+#[derive(Clone)]
+pub struct JobQueue {
+    shared: Arc<Shared>,
+}
+
+impl JobQueue {
+    /// Start `concurrency` workers plus one for high priority jobs, calling
+    /// `notify` with every change to a job
+    pub fn new(concurrency: usize, notify: impl Fn(&JobInfo) + Send + Sync + 'static) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState {
+                next_id: 1,
+                ..QueueState::default()
+            }),
+            ready: Condvar::new(),
+            notify: Box::new(notify),
+        });
+        for _ in 0..concurrency.max(1) {
+            let shared = shared.clone();
+            std::thread::spawn(move || run_worker(shared, JobPriority::Low));
+        }
+        let reserved = shared.clone();
+        std::thread::spawn(move || run_worker(reserved, JobPriority::High));
+        Self { shared }
+    }
+
+    /// Queue `work`; wait on the handle for its result
+    pub fn submit<T, F>(
+        &self,
+        kind: JobKind,
+        priority: JobPriority,
+        label: impl Into<String>,
+        work: F,
+    ) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, Error> + Send + 'static,
+    {
+        let (sender, result) = mpsc::channel();
+        let work: Work = Box::new(move || {
+            let outcome = work();
+            let status = match &outcome {
+                Ok(_) => (JobStatus::Completed, None),
+                Err(Error::Cancelled) => (JobStatus::Cancelled, None),
+                Err(e) => (JobStatus::Failed, Some(e.to_string())),
+            };
+            let _ = sender.send(outcome);
+            status
+        });
+
+        let info = {
+            let Ok(mut state) = self.shared.state.lock() else {
+                drop(work);
+                return JobHandle { id: 0, result };
+            };
+            let id = state.next_id;
+            state.next_id += 1;
+            let info = JobInfo {
+                id,
+                kind,
+                priority,
+                label: label.into(),
+                status: JobStatus::Queued,
+                progress: 0.0,
+                error: None,
+            };
+            state.jobs.push(info.clone());
+            state.queued.push(QueuedJob { id, priority, work });
+            info
+        };
+        log::debug!("🧾 Queued {:?} job {}: {}", kind, info.id, info.label);
+        (self.shared.notify)(&info);
+        // Every worker, since the one woken might not take this priority
+        self.shared.ready.notify_all();
+
+        JobHandle {
+            id: info.id,
+            result,
+        }
+    }
+
+    /// Cancel a queued or running job. A running job stops at its next FFmpeg
+    /// progress report. False when there's no such job or it's already done.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let (removed, running) = {
+            let Ok(mut state) = self.shared.state.lock() else {
+                return false;
+            };
+            let removed = state
+                .queued
+                .iter()
+                .position(|job| job.id == id)
+                .map(|index| state.queued.remove(index));
+            let running = state.cancel_flags.get(&id).cloned();
+            (removed, running)
+        };
+
+        if removed.is_some() {
+            // Dropping the work drops its sender, so the handle sees it cancelled
+            drop(removed);
+            self.shared
+                .update(id, |info| info.status = JobStatus::Cancelled);
+            log::info!("🛑 Cancelled queued job {}", id);
+            true
+        } else if let Some(flag) = running {
+            flag.store(true, Ordering::Relaxed);
+            log::info!("🛑 Cancelling running job {}", id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Jobs queued, running and recently finished, oldest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.shared
+            .state
+            .lock()
+            .map(|state| state.jobs.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// A submitted job's result, once it's done
+pub struct JobHandle<T> {
+    pub id: JobId,
+    result: mpsc::Receiver<Result<T, Error>>,
+}
+
+impl<T> JobHandle<T> {
+    /// Block until the job is done. A job cancelled before it started
+    /// gives `Error::Cancelled`.
+    pub fn wait(self) -> Result<T, Error> {
+        self.result.recv().unwrap_or(Err(Error::Cancelled))
+    }
+}
+
+/// The job running on this thread, as seen by the FFmpeg runs inside it
+#[derive(Clone)]
+pub struct JobContext {
+    id: JobId,
+    cancelled: Arc<AtomicBool>,
+    shared: Arc<Shared>,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Share of the current FFmpeg run that's done, 0 to 1
+    pub fn report(&self, fraction: f64) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let changed = self
+            .shared
+            .state
+            .lock()
+            .ok()
+            .and_then(|state| {
+                state
+                    .jobs
+                    .iter()
+                    .find(|info| info.id == self.id)
+                    .map(|info| (info.progress - fraction).abs() >= PROGRESS_STEP)
+            })
+            .unwrap_or(false);
+        if changed {
+            self.shared.update(self.id, |info| info.progress = fraction);
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<JobContext>> = const { RefCell::new(None) };
+}
+
+/// The job this thread is running, if it's a job worker
+pub fn current() -> Option<JobContext> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Run queued jobs of at least `min` priority, forever
+fn run_worker(shared: Arc<Shared>, min: JobPriority) {
+    loop {
+        let (job, cancelled) = {
+            let Ok(mut state) = shared.state.lock() else {
+                return;
+            };
+            loop {
+                if let Some(job) = state.take_next(min) {
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    state.cancel_flags.insert(job.id, cancelled.clone());
+                    break (job, cancelled);
+                }
+                state = match shared.ready.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return,
+                };
+            }
+        };
+
+        shared.update(job.id, |info| info.status = JobStatus::Running);
+        CURRENT.with(|current| {
+            *current.borrow_mut() = Some(JobContext {
+                id: job.id,
+                cancelled,
+                shared: shared.clone(),
+            })
+        });
+        let (status, error) = (job.work)();
+        CURRENT.with(|current| *current.borrow_mut() = None);
+
+        if let Some(error) = &error {
+            log::warn!("Job {} failed: {}", job.id, error);
+        }
+        shared.update(job.id, |info| {
+            info.status = status;
+            info.error = error;
+            if status == JobStatus::Completed {
+                info.progress = 1.0;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_runs_by_priority_within_the_limit() {
+        let (events, received) = mpsc::channel();
+        let events = Mutex::new(events);
+        let queue = JobQueue::new(1, move |info: &JobInfo| {
+            let _ = events.lock().unwrap().send(info.clone());
+        });
+
+        // Hold the only general worker until everything else is queued
+        let (release, gate) = mpsc::channel::<()>();
+        let blocker = queue.submit(JobKind::Clip, JobPriority::Normal, "blocker", move || {
+            gate.recv().ok();
+            Ok("blocker")
+        });
+        while !queue
+            .list()
+            .iter()
+            .any(|job| job.id == blocker.id && job.status == JobStatus::Running)
+        {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let low = queue.submit(JobKind::Compress, JobPriority::Low, "low", || Ok("low"));
+        let cancelled = queue.submit(JobKind::Clip, JobPriority::Normal, "cancelled", || {
+            Ok("cancelled")
+        });
+        let normal = queue.submit(JobKind::Clip, JobPriority::Normal, "normal", || {
+            Ok("normal")
+        });
+        assert!(queue.cancel(cancelled.id));
+
+        // The high priority worker doesn't wait for the busy one
+        let high = queue.submit(JobKind::Thumbnail, JobPriority::High, "high", || {
+            let job = current().expect("running as a job");
+            job.report(0.5);
+            Ok("high")
+        });
+        let high_id = high.id;
+        assert_eq!(high.wait().unwrap(), "high");
+
+        // But it leaves the rest for the general worker
+        let queued: Vec<String> = queue
+            .list()
+            .into_iter()
+            .filter(|job| job.status == JobStatus::Queued)
+            .map(|job| job.label)
+            .collect();
+        assert_eq!(queued, vec!["low", "normal"]);
+        release.send(()).unwrap();
+
+        assert_eq!(blocker.wait().unwrap(), "blocker");
+        assert_eq!(normal.wait().unwrap(), "normal");
+        assert_eq!(low.wait().unwrap(), "low");
+        assert!(matches!(cancelled.wait(), Err(Error::Cancelled)));
+
+        // The normal job started before the low one, though queued after it
+        let started: Vec<String> = received
+            .try_iter()
+            .filter(|info| info.status == JobStatus::Running && info.progress == 0.0)
+            .map(|info| info.label)
+            .collect();
+        assert_eq!(started, vec!["blocker", "high", "normal", "low"]);
+
+        let statuses: Vec<(String, JobStatus)> = queue
+            .list()
+            .into_iter()
+            .map(|job| (job.label, job.status))
+            .collect();
+        assert!(statuses.contains(&("cancelled".to_string(), JobStatus::Cancelled)));
+        assert!(statuses.contains(&("high".to_string(), JobStatus::Completed)));
+        assert!(!queue.cancel(high_id));
+    }
+}
@@ -1,23 +1,41 @@
 mod app_state;
+mod clip_markers;
 mod clip_processor;
 mod commands;
 mod game_detector;
+mod jobs;
 mod recorder;
 mod slippi;
 use commands::cloud::get_device_id;
 use commands::default::{read, write};
+use commands::jobs::{cancel_job, list_jobs};
+use commands::replay_buffer::{save_replay_buffer, start_replay_buffer, stop_replay_buffer};
 use commands::settings::{
     get_recording_directory, get_setting, get_settings_path, open_settings_folder,
 };
 use commands::slippi::{
     capture_window_preview, check_game_window, compress_video_for_upload, delete_recording,
-    delete_temp_file, get_clips, get_default_slippi_path, get_game_process_name,
-    get_last_replay_path, get_recordings, list_game_windows, mark_clip_timestamp,
-    open_file_location, open_recording_folder, open_video, parse_slp_events, process_clip_markers,
-    set_game_process_name, start_generic_recording, start_recording, start_watching,
+    delete_temp_file, get_clips, get_default_slippi_path, get_game_phase, get_game_process_name,
+    get_last_replay_path, get_recording_profile, get_recordings, get_replay_sync,
+    list_game_windows, mark_clip_timestamp, open_file_location, open_recording_folder, open_video,
+    parse_slp_events, parse_slp_stats, parse_slp_tech_stats, pause_recording, process_clip_markers,
+    recover_recordings, resume_clip_markers, resume_recording, set_game_process_name,
+    set_recording_profile, start_generic_recording, start_recording, start_watching,
     stop_recording, stop_watching,
 };
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+
+/// How many FFmpeg jobs run at once; read once, so a change applies on restart
+fn read_job_concurrency(app: &tauri::AppHandle) -> usize {
+    use tauri_plugin_store::StoreExt;
+
+    app.store("settings.json")
+        .ok()
+        .and_then(|store| store.get("jobConcurrency"))
+        .and_then(|v| v.as_u64())
+        .filter(|&jobs| jobs > 0)
+        .map_or(jobs::DEFAULT_CONCURRENCY, |jobs| jobs as usize)
+}
 
 #[allow(clippy::missing_panics_doc)]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -30,6 +48,15 @@ pub fn run() {
             // Initialize app state
             app.manage(app_state::AppState::new());
 
+            // FFmpeg work runs in the background, reporting to the frontend
+            let handle = app.handle().clone();
+            let concurrency = read_job_concurrency(&handle);
+            app.manage(jobs::JobQueue::new(concurrency, move |job| {
+                if let Err(e) = handle.emit("job-progress", job) {
+                    log::error!("Failed to emit job-progress event: {:?}", e);
+                }
+            }));
+
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -50,7 +77,10 @@ pub fn run() {
             start_recording,
             start_generic_recording,
             stop_recording,
+            pause_recording,
+            resume_recording,
             get_recordings,
+            recover_recordings,
             delete_recording,
             open_video,
             open_recording_folder,
@@ -59,21 +89,35 @@ pub fn run() {
             list_game_windows,
             get_game_process_name,
             set_game_process_name,
+            get_recording_profile,
+            set_recording_profile,
             get_settings_path,
             open_settings_folder,
             get_setting,
             get_recording_directory,
             open_file_location,
+            get_game_phase,
             get_last_replay_path,
             parse_slp_events,
+            parse_slp_stats,
+            parse_slp_tech_stats,
+            get_replay_sync,
             // Clip commands
             mark_clip_timestamp,
             process_clip_markers,
+            resume_clip_markers,
             get_clips,
+            // Replay buffer commands
+            start_replay_buffer,
+            stop_replay_buffer,
+            save_replay_buffer,
             // Cloud commands
             compress_video_for_upload,
             delete_temp_file,
             get_device_id,
+            // Job commands
+            list_jobs,
+            cancel_job,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
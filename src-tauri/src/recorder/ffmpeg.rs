@@ -0,0 +1,1051 @@
+// Cross-platform recorder that drives FFmpeg's own capture devices
+//
+// x11grab on Linux, gdigrab/ddagrab on Windows and avfoundation on macOS. None
+// of this needs the `real-recording` feature, so it doubles as the fallback
+// when the native capture APIs fail to start.
+
+use super::frames::FrameLog;
+use super::parts::PartedOutput;
+use super::profile::{Container, EncoderKind, RateControl, VideoCodec};
+use super::stats::RecordingClock;
+use super::target::TargetSelection;
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
+use ffmpeg_sidecar::child::FfmpegChild;
+use ffmpeg_sidecar::command::FfmpegCommand;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long to give FFmpeg to fail on bad input before assuming it's recording
+const STARTUP_GRACE: Duration = Duration::from_millis(500);
+/// FFmpeg output lines kept for error messages
+const STDERR_TAIL_LINES: usize = 20;
+
+/// GPU encoder FFmpeg uses when a profile asks for hardware encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HardwareEncoder {
+    /// NVIDIA
+    Nvenc,
+    /// Intel Quick Sync
+    Qsv,
+    /// AMD
+    Amf,
+    /// Apple
+    VideoToolbox,
+}
+
+impl Default for HardwareEncoder {
+    fn default() -> Self {
+        if cfg!(target_os = "macos") {
+            HardwareEncoder::VideoToolbox
+        } else {
+            HardwareEncoder::Nvenc
+        }
+    }
+}
+
+impl HardwareEncoder {
+    fn encoder_name(&self, codec: VideoCodec) -> Option<&'static str> {
+        match (self, codec) {
+            (HardwareEncoder::Nvenc, VideoCodec::H264) => Some("h264_nvenc"),
+            (HardwareEncoder::Nvenc, VideoCodec::Hevc) => Some("hevc_nvenc"),
+            (HardwareEncoder::Nvenc, VideoCodec::Av1) => Some("av1_nvenc"),
+            (HardwareEncoder::Qsv, VideoCodec::H264) => Some("h264_qsv"),
+            (HardwareEncoder::Qsv, VideoCodec::Hevc) => Some("hevc_qsv"),
+            (HardwareEncoder::Qsv, VideoCodec::Av1) => Some("av1_qsv"),
+            (HardwareEncoder::Amf, VideoCodec::H264) => Some("h264_amf"),
+            (HardwareEncoder::Amf, VideoCodec::Hevc) => Some("hevc_amf"),
+            (HardwareEncoder::Amf, VideoCodec::Av1) => Some("av1_amf"),
+            (HardwareEncoder::VideoToolbox, VideoCodec::H264) => Some("h264_videotoolbox"),
+            (HardwareEncoder::VideoToolbox, VideoCodec::Hevc) => Some("hevc_videotoolbox"),
+            (HardwareEncoder::VideoToolbox, VideoCodec::Av1) => None,
+        }
+    }
+}
+
+fn software_encoder_name(codec: VideoCodec) -> &'static str {
+    match codec {
+        VideoCodec::H264 => "libx264",
+        VideoCodec::Hevc => "libx265",
+        VideoCodec::Av1 => "libsvtav1",
+    }
+}
+
+/// FFmpeg encoder for a profile; `Auto` means software, which always works
+fn encoder_name(
+    profile: &RecordingProfile,
+    hardware: HardwareEncoder,
+) -> Result<&'static str, Error> {
+    match profile.encoder {
+        EncoderKind::Auto | EncoderKind::Software => Ok(software_encoder_name(profile.codec)),
+        EncoderKind::Hardware => hardware.encoder_name(profile.codec).ok_or_else(|| {
+            Error::UnsupportedRecordingProfile(format!(
+                "{:?} can't encode {:?}",
+                hardware, profile.codec
+            ))
+        }),
+    }
+}
+
+/// Speed over size: we're encoding live next to the game
+fn tuning_args(encoder: &str) -> &'static [&'static str] {
+    match encoder {
+        "libx264" | "libx265" => &["-preset", "veryfast"],
+        "libsvtav1" => &["-preset", "10"],
+        e if e.ends_with("_nvenc") => &["-preset", "p4"],
+        e if e.ends_with("_qsv") => &["-preset", "veryfast"],
+        e if e.ends_with("_amf") => &["-quality", "speed"],
+        e if e.ends_with("_videotoolbox") => &["-realtime", "1"],
+        _ => &[],
+    }
+}
+
+fn rate_control_args(encoder: &str, profile: &RecordingProfile) -> Vec<String> {
+    let bitrate = profile.bitrate;
+    let qp = profile.qp.to_string();
+    let mut args: Vec<String> = Vec::new();
+
+    match profile.rate_control {
+        RateControl::Cbr => {
+            if encoder.ends_with("_nvenc") || encoder.ends_with("_amf") {
+                args.extend(["-rc".into(), "cbr".into()]);
+            }
+            args.extend([
+                "-b:v".into(),
+                bitrate.to_string(),
+                "-minrate".into(),
+                bitrate.to_string(),
+                "-maxrate".into(),
+                bitrate.to_string(),
+                "-bufsize".into(),
+                bitrate.to_string(),
+            ]);
+        }
+        RateControl::Vbr => {
+            if encoder.ends_with("_nvenc") {
+                args.extend(["-rc".into(), "vbr".into()]);
+            } else if encoder.ends_with("_amf") {
+                args.extend(["-rc".into(), "vbr_peak".into()]);
+            }
+            args.extend([
+                "-b:v".into(),
+                bitrate.to_string(),
+                "-maxrate".into(),
+                (bitrate / 2 * 3).to_string(),
+                "-bufsize".into(),
+                (bitrate * 2).to_string(),
+            ]);
+        }
+        RateControl::Cqp => match encoder {
+            "libx264" | "libx265" => args.extend(["-qp".into(), qp]),
+            "libsvtav1" => args.extend(["-crf".into(), qp]),
+            e if e.ends_with("_nvenc") => {
+                args.extend(["-rc".into(), "constqp".into(), "-qp".into(), qp])
+            }
+            e if e.ends_with("_amf") => args.extend([
+                "-rc".into(),
+                "cqp".into(),
+                "-qp_i".into(),
+                qp.clone(),
+                "-qp_p".into(),
+                qp,
+            ]),
+            e if e.ends_with("_qsv") => args.extend(["-global_quality".into(), qp]),
+            // VideoToolbox only has a 1-100 quality scale, higher is better
+            _ => {
+                let quality = 100 - (profile.qp as u32 * 99 / 51);
+                args.extend(["-q:v".into(), quality.to_string()]);
+            }
+        },
+    }
+
+    args
+}
+
+/// FFmpeg input arguments for the game's video, and its audio if we have any
+pub(super) struct CaptureInput {
+    pub(super) args: Vec<String>,
+    pub(super) has_audio: bool,
+    /// Process of the window being captured, when FFmpeg grabs its area of the
+    /// screen and so won't notice it closing
+    pub(super) window_pid: Option<u32>,
+}
+
+/// `PEPPI_AUDIO_SOURCE`, the platform-specific audio device to record
+fn audio_source_from_env() -> Option<String> {
+    std::env::var("PEPPI_AUDIO_SOURCE")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+pub(super) fn capture_input(selection: &TargetSelection, fps: u32) -> Result<CaptureInput, Error> {
+    x11_capture_input(&super::linux::default_display(), selection, fps)
+}
+
+/// x11grab of the selected window on `display`
+#[cfg(target_os = "linux")]
+pub(super) fn x11_capture_input(
+    display: &str,
+    selection: &TargetSelection,
+    fps: u32,
+) -> Result<CaptureInput, Error> {
+    let mut args: Vec<String> = vec![
+        "-f".into(),
+        "x11grab".into(),
+        "-draw_mouse".into(),
+        "0".into(),
+    ];
+    args.extend(["-framerate".into(), fps.to_string()]);
+
+    // Without a window we grab the whole screen, which is what Xvfb gives us
+    let window = super::linux::find_best_window(display, selection);
+    match &window {
+        Some(window) => {
+            log::info!(
+                "Capturing X11 window '{}' (pid {}, {}x{} at {},{})",
+                window.title,
+                window.pid,
+                window.width,
+                window.height,
+                window.x,
+                window.y
+            );
+            args.extend([
+                "-video_size".into(),
+                format!("{}x{}", window.width, window.height),
+                "-i".into(),
+                format!("{}+{},{}", display, window.x, window.y),
+            ]);
+        }
+        None => {
+            log::warn!("No game window found, capturing the whole screen");
+            args.extend(["-i".into(), display.to_string()]);
+        }
+    }
+
+    let audio = audio_source_from_env().or_else(super::linux::default_audio_source);
+    if let Some(source) = &audio {
+        args.extend(["-f".into(), "pulse".into(), "-i".into(), source.clone()]);
+    } else {
+        log::warn!("No PulseAudio/PipeWire server found, recording without audio");
+    }
+
+    Ok(CaptureInput {
+        args,
+        has_audio: audio.is_some(),
+        window_pid: window.map(|window| window.pid),
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub(super) fn capture_input(selection: &TargetSelection, fps: u32) -> Result<CaptureInput, Error> {
+    let mut args: Vec<String> = Vec::new();
+
+    match &selection.title {
+        // gdigrab can follow a single window by its title
+        Some(title) => {
+            log::info!("Capturing window '{}' with gdigrab", title);
+            args.extend([
+                "-f".into(),
+                "gdigrab".into(),
+                "-draw_mouse".into(),
+                "0".into(),
+            ]);
+            args.extend(["-framerate".into(), fps.to_string()]);
+            args.extend(["-i".into(), format!("title={}", title)]);
+        }
+        // Otherwise Desktop Duplication is much cheaper for the whole monitor
+        None => {
+            log::info!("No target window set, capturing the primary monitor with ddagrab");
+            args.extend([
+                "-f".into(),
+                "lavfi".into(),
+                "-i".into(),
+                format!(
+                    "ddagrab=framerate={}:draw_mouse=0,hwdownload,format=bgra",
+                    fps
+                ),
+            ]);
+        }
+    }
+
+    // DirectShow has no loopback device of its own, so audio has to be named
+    let audio = audio_source_from_env();
+    if let Some(source) = &audio {
+        args.extend([
+            "-f".into(),
+            "dshow".into(),
+            "-i".into(),
+            format!("audio={}", source),
+        ]);
+    } else {
+        log::warn!("PEPPI_AUDIO_SOURCE not set, recording without audio");
+    }
+
+    Ok(CaptureInput {
+        args,
+        has_audio: audio.is_some(),
+        window_pid: None,
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub(super) fn capture_input(_selection: &TargetSelection, fps: u32) -> Result<CaptureInput, Error> {
+    // avfoundation can't capture a single window, only whole screens
+    let screen =
+        std::env::var("PEPPI_AVFOUNDATION_SCREEN").unwrap_or_else(|_| "Capture screen 0".into());
+
+    // Game audio needs a loopback device such as BlackHole
+    let audio = audio_source_from_env();
+    if audio.is_none() {
+        log::warn!("PEPPI_AUDIO_SOURCE not set, recording without audio");
+    }
+
+    let args = vec![
+        "-f".into(),
+        "avfoundation".into(),
+        "-capture_cursor".into(),
+        "0".into(),
+        "-framerate".into(),
+        fps.to_string(),
+        "-i".into(),
+        format!("{}:{}", screen, audio.as_deref().unwrap_or("none")),
+    ];
+
+    Ok(CaptureInput {
+        args,
+        has_audio: audio.is_some(),
+        window_pid: None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+pub(super) fn capture_input(
+    _selection: &TargetSelection,
+    _fps: u32,
+) -> Result<CaptureInput, Error> {
+    Err(Error::RecordingFailed(
+        "FFmpeg capture isn't supported on this platform".into(),
+    ))
+}
+
+/// Everything after the capture inputs: encoding and the output file
+pub(super) fn output_args(
+    profile: &RecordingProfile,
+    hardware: HardwareEncoder,
+    has_audio: bool,
+    output_path: &str,
+) -> Result<Vec<String>, Error> {
+    let mut args = encode_args(profile, hardware, has_audio)?;
+
+    match profile.container {
+        Container::Mp4 => args.extend([
+            "-f".into(),
+            "mp4".into(),
+            "-movflags".into(),
+            "+faststart".into(),
+        ]),
+        Container::FragmentedMp4 => args.extend([
+            "-f".into(),
+            "mp4".into(),
+            "-movflags".into(),
+            "+frag_keyframe+empty_moov+default_base_moof".into(),
+        ]),
+        Container::Mkv => args.extend(["-f".into(), "matroska".into()]),
+    }
+
+    args.extend(["-y".into(), output_path.into()]);
+    Ok(args)
+}
+
+/// Like `output_args`, but rolling over to a new file every `segment_seconds`
+/// within the one FFmpeg. `pattern` names the files (`%05d` is the index) and
+/// each finished one is listed in `list_path` as `name,start,end`.
+pub(super) fn segment_output_args(
+    profile: &RecordingProfile,
+    hardware: HardwareEncoder,
+    has_audio: bool,
+    segment_seconds: u64,
+    pattern: &str,
+    list_path: &str,
+) -> Result<Vec<String>, Error> {
+    let mut args = encode_args(profile, hardware, has_audio)?;
+
+    // The segment muxer can only cut on a keyframe
+    args.extend([
+        "-force_key_frames".into(),
+        format!("expr:gte(t,n_forced*{})", segment_seconds.max(1)),
+        "-f".into(),
+        "segment".into(),
+        "-segment_time".into(),
+        segment_seconds.max(1).to_string(),
+        "-reset_timestamps".into(),
+        "1".into(),
+        "-segment_list".into(),
+        list_path.into(),
+        "-segment_list_type".into(),
+        "csv".into(),
+    ]);
+    match profile.container {
+        // Fragmented either way, so the segment still being written can be read
+        Container::Mp4 | Container::FragmentedMp4 => args.extend([
+            "-segment_format".into(),
+            "mp4".into(),
+            "-segment_format_options".into(),
+            "movflags=+frag_keyframe+empty_moov+default_base_moof".into(),
+        ]),
+        Container::Mkv => args.extend(["-segment_format".into(), "matroska".into()]),
+    }
+
+    args.extend(["-y".into(), pattern.into()]);
+    Ok(args)
+}
+
+/// Encoder arguments shared by every kind of output
+fn encode_args(
+    profile: &RecordingProfile,
+    hardware: HardwareEncoder,
+    has_audio: bool,
+) -> Result<Vec<String>, Error> {
+    let encoder = encoder_name(profile, hardware)?;
+    let pixel_format = if encoder.ends_with("_qsv") {
+        "nv12"
+    } else {
+        "yuv420p"
+    };
+
+    let mut args: Vec<String> = vec!["-c:v".into(), encoder.into()];
+    args.extend(tuning_args(encoder).iter().map(|s| s.to_string()));
+    args.extend(["-pix_fmt".into(), pixel_format.into()]);
+    args.extend(rate_control_args(encoder, profile));
+    args.extend(["-r".into(), profile.fps.to_string()]);
+
+    // showinfo logs every frame as it comes in, for the frame timestamps
+    let mut filters = vec!["showinfo=checksum=0".to_string()];
+    if let Some(height) = profile.output_height {
+        filters.push(format!("scale=-2:{}", height));
+    }
+    args.extend(["-vf".into(), filters.join(",")]);
+
+    if has_audio {
+        args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "160k".into()]);
+    }
+
+    Ok(args)
+}
+
+/// Counters from FFmpeg's `-progress` output and its log
+#[derive(Debug, Clone, Default)]
+struct FfmpegProgress {
+    frames: u64,
+    dropped: u64,
+    bytes: u64,
+    audio_underruns: u64,
+}
+
+impl FfmpegProgress {
+    /// Take in one `key=value` line of `-progress` output
+    fn apply(&mut self, line: &str) {
+        let Some((key, value)) = line.split_once('=') else {
+            return;
+        };
+        let Ok(value) = value.trim().parse::<u64>() else {
+            // e.g. `total_size=N/A` before anything is written
+            return;
+        };
+        match key.trim() {
+            "frame" => self.frames = value,
+            "drop_frames" => self.dropped = value,
+            "total_size" => self.bytes = value,
+            _ => {}
+        }
+    }
+
+    fn add(&mut self, other: &FfmpegProgress) {
+        self.frames += other.frames;
+        self.dropped += other.dropped;
+        self.bytes += other.bytes;
+        self.audio_underruns += other.audio_underruns;
+    }
+}
+
+/// Seconds into the capture of the frame a `showinfo` log line describes
+fn showinfo_pts(line: &str) -> Option<f64> {
+    if !line.contains("showinfo") {
+        return None;
+    }
+    let (_, rest) = line.split_once("pts_time:")?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Whether an FFmpeg log line is worth keeping; `showinfo` needs info logging,
+/// but the rest of that is noise
+fn is_log_error(line: &str) -> bool {
+    ["[error]", "[fatal]", "[panic]"]
+        .iter()
+        .any(|level| line.contains(level))
+}
+
+/// Whether an FFmpeg log line is the audio device running dry
+fn is_audio_underrun(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line.contains("underrun") || line.contains("xrun")
+}
+
+/// A running FFmpeg capture, with its output drained in the background
+struct FfmpegProcess {
+    child: FfmpegChild,
+    started: Instant,
+    fps: u32,
+    reader_threads: Vec<JoinHandle<()>>,
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    progress: Arc<Mutex<FfmpegProgress>>,
+    /// Set once FFmpeg closes its output, which it only does on its own when
+    /// the capture source has gone
+    exited: Arc<AtomicBool>,
+}
+
+impl FfmpegProcess {
+    /// Every frame is logged as it passes `showinfo`, following on from what
+    /// `frames` already holds
+    fn spawn(args: Vec<String>, fps: u32, frames: Option<Arc<FrameLog>>) -> Result<Self, Error> {
+        crate::clip_processor::ensure_ffmpeg()?;
+
+        let mut child = FfmpegCommand::new()
+            .args(["-hide_banner", "-nostats", "-loglevel", "level+info"])
+            .args(["-progress", "pipe:1"])
+            .args(args)
+            .spawn()
+            .map_err(|err| Error::RecordingFailed(format!("Failed to spawn FFmpeg: {err}")))?;
+
+        // FFmpeg blocks once a pipe is full, so keep reading both for the whole recording
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+        let progress = Arc::new(Mutex::new(FfmpegProgress::default()));
+        let mut reader_threads = Vec::new();
+        if let Some(stderr) = child.take_stderr() {
+            let tail = stderr_tail.clone();
+            let progress = progress.clone();
+            let offset = frames
+                .as_ref()
+                .map_or(0.0, |frames| frames.recorded_seconds());
+            reader_threads.push(std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    if let Some(pts) = showinfo_pts(&line) {
+                        if let Some(frames) = &frames {
+                            frames.record(offset + pts);
+                        }
+                        continue;
+                    }
+                    if is_audio_underrun(&line) {
+                        if let Ok(mut progress) = progress.lock() {
+                            progress.audio_underruns += 1;
+                        }
+                    }
+                    if !is_log_error(&line) {
+                        continue;
+                    }
+                    log::warn!("ffmpeg: {}", line);
+                    if let Ok(mut tail) = tail.lock() {
+                        if tail.len() == STDERR_TAIL_LINES {
+                            tail.pop_front();
+                        }
+                        tail.push_back(line);
+                    }
+                }
+            }));
+        }
+        let exited = Arc::new(AtomicBool::new(false));
+        if let Some(stdout) = child.take_stdout() {
+            let progress = progress.clone();
+            let exited = exited.clone();
+            reader_threads.push(std::thread::spawn(move || {
+                for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                    if let Ok(mut progress) = progress.lock() {
+                        progress.apply(&line);
+                    }
+                }
+                exited.store(true, Ordering::Relaxed);
+            }));
+        }
+
+        let mut process = Self {
+            child,
+            started: Instant::now(),
+            fps,
+            reader_threads,
+            stderr_tail,
+            progress,
+            exited,
+        };
+
+        // A bad device or encoder makes FFmpeg exit straight away
+        std::thread::sleep(STARTUP_GRACE);
+        if let Ok(Some(status)) = process.child.as_inner_mut().try_wait() {
+            process.join_readers();
+            return Err(Error::RecordingFailed(format!(
+                "FFmpeg exited during startup ({status}): {}",
+                process.stderr_summary()
+            )));
+        }
+
+        Ok(process)
+    }
+
+    fn progress(&self) -> FfmpegProgress {
+        self.progress
+            .lock()
+            .map(|progress| progress.clone())
+            .unwrap_or_default()
+    }
+
+    fn has_exited(&self) -> bool {
+        self.exited.load(Ordering::Relaxed)
+    }
+
+    /// Frames FFmpeg should have handled by now but hasn't; FFmpeg doesn't
+    /// report its queue, so this is the best estimate there is
+    fn frames_behind(&self) -> u64 {
+        let progress = self.progress();
+        let expected = (self.started.elapsed().as_secs_f64() * self.fps as f64) as u64;
+        expected.saturating_sub(progress.frames + progress.dropped)
+    }
+
+    /// Ask FFmpeg to finish the file and wait for it to exit, returning its
+    /// final counters
+    fn finish(mut self) -> Result<FfmpegProgress, Error> {
+        // 'q' lets FFmpeg write the moov atom; killing it would leave a broken file
+        if let Err(err) = self.child.quit() {
+            log::warn!("Failed to ask FFmpeg to stop, killing it: {err}");
+            let _ = self.child.kill();
+        }
+
+        let status = self
+            .child
+            .wait()
+            .map_err(|err| Error::RecordingFailed(format!("FFmpeg process error: {err}")))?;
+        self.join_readers();
+
+        if !status.success() {
+            return Err(Error::RecordingFailed(format!(
+                "FFmpeg failed with status {status}: {}",
+                self.stderr_summary()
+            )));
+        }
+        Ok(self.progress())
+    }
+
+    fn join_readers(&mut self) {
+        for handle in self.reader_threads.drain(..) {
+            let _ = handle.join();
+        }
+    }
+
+    fn stderr_summary(&self) -> String {
+        self.stderr_tail
+            .lock()
+            .map(|tail| tail.iter().cloned().collect::<Vec<_>>().join("\n"))
+            .unwrap_or_default()
+    }
+}
+
+pub struct FfmpegRecorder {
+    hardware: HardwareEncoder,
+    /// The FFmpeg writing the current part; `None` while paused
+    process: Option<FfmpegProcess>,
+    output: Option<PartedOutput>,
+    profile: Option<RecordingProfile>,
+    clock: Option<RecordingClock>,
+    frames: Option<Arc<FrameLog>>,
+    /// Counters of the parts finished before the current one
+    finished_parts: FfmpegProgress,
+    /// See `CaptureInput::window_pid`
+    window_pid: Option<u32>,
+}
+
+impl FfmpegRecorder {
+    pub fn new(hardware: HardwareEncoder) -> Self {
+        Self {
+            hardware,
+            process: None,
+            output: None,
+            profile: None,
+            clock: None,
+            frames: None,
+            finished_parts: FfmpegProgress::default(),
+            window_pid: None,
+        }
+    }
+
+    fn ensure_output_dir(&self, output_path: &str) -> Result<(), Error> {
+        if let Some(parent) = std::path::Path::new(output_path).parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).map_err(|err| {
+                    Error::RecordingFailed(format!("Failed to create output directory: {err}"))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn spawn_capture(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<FfmpegProcess, Error> {
+        let input = capture_input(&TargetSelection::from_env(), profile.fps)?;
+        self.window_pid = input.window_pid;
+        let mut args = input.args;
+        args.extend(output_args(
+            profile,
+            self.hardware,
+            input.has_audio,
+            output_path,
+        )?);
+        let frames = self
+            .frames
+            .clone()
+            .ok_or_else(|| Error::RecordingFailed("Not recording".into()))?;
+        FfmpegProcess::spawn(args, profile.fps, Some(frames))
+    }
+}
+
+/// One FFmpeg capture rolling over to a new file every few seconds, for the
+/// replay buffer. Unlike restarting a recorder per file, nothing is lost at the
+/// boundaries.
+pub struct SegmentedCapture {
+    process: FfmpegProcess,
+}
+
+impl SegmentedCapture {
+    /// See `segment_output_args` for `pattern` and `list_path`
+    pub fn start(
+        profile: &RecordingProfile,
+        hardware: HardwareEncoder,
+        segment_seconds: u64,
+        pattern: &str,
+        list_path: &str,
+    ) -> Result<Self, Error> {
+        log::info!(
+            "🎬 Starting segmented FFmpeg capture with {} ({})",
+            encoder_name(profile, hardware)?,
+            profile.summary()
+        );
+
+        let input = capture_input(&TargetSelection::from_env(), profile.fps)?;
+        let mut args = input.args;
+        args.extend(segment_output_args(
+            profile,
+            hardware,
+            input.has_audio,
+            segment_seconds,
+            pattern,
+            list_path,
+        )?);
+        // Nothing syncs to the buffer's segments, so their frames aren't logged
+        let process = FfmpegProcess::spawn(args, profile.fps, None)?;
+        Ok(Self { process })
+    }
+
+    /// Whether FFmpeg has stopped on its own, e.g. because the screen went away
+    pub fn has_exited(&self) -> bool {
+        self.process.has_exited()
+    }
+
+    /// Finish the segment being written and wait for FFmpeg to exit
+    pub fn stop(self) -> Result<(), Error> {
+        self.process.finish().map(|_| ())
+    }
+}
+
+impl Recorder for FfmpegRecorder {
+    fn start_recording(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<(), Error> {
+        if self.output.is_some() {
+            return Err(Error::RecordingFailed("Already recording".into()));
+        }
+
+        self.ensure_output_dir(output_path)?;
+        log::info!(
+            "🎬 Starting FFmpeg capture with {} ({})",
+            encoder_name(profile, self.hardware)?,
+            profile.summary()
+        );
+
+        self.frames = Some(Arc::new(FrameLog::start()));
+        self.process = match self.spawn_capture(output_path, profile) {
+            Ok(process) => Some(process),
+            Err(err) => {
+                self.frames = None;
+                return Err(err);
+            }
+        };
+        self.output = Some(PartedOutput::new(output_path));
+        self.profile = Some(profile.clone());
+        self.clock = Some(RecordingClock::start());
+        self.finished_parts = FfmpegProgress::default();
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<String, Error> {
+        let Some(output) = self.output.take() else {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        };
+
+        self.profile = None;
+        self.clock = None;
+        let frames = self.frames.take();
+        let finished = match self.process.take() {
+            Some(process) => process.finish().map(|_| ()),
+            None => Ok(()),
+        };
+        // Join the parts even when the last one didn't end cleanly; any that
+        // can't be joined are left for recovery
+        let joined = output.finish();
+        finished?;
+        let output = joined?;
+        if let Some(frames) = frames {
+            if let Err(err) = frames.write(&output) {
+                log::warn!("Failed to write frame timestamps: {err:?}");
+            }
+        }
+
+        log::info!("Recording saved to {output}");
+        Ok(output)
+    }
+
+    fn is_recording(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// FFmpeg can't pause a capture, so the current part is finished instead
+    fn pause(&mut self) -> Result<(), Error> {
+        let Some(process) = self.process.take() else {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        };
+
+        let progress = process.finish()?;
+        self.finished_parts.add(&progress);
+        if let Some(clock) = self.clock.as_mut() {
+            clock.pause();
+        }
+        if let Some(frames) = &self.frames {
+            frames.pause();
+        }
+        log::info!("⏸️ Paused FFmpeg capture");
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        if !self.is_paused() {
+            return Err(Error::RecordingFailed("Not paused".into()));
+        }
+        let (Some(output), Some(profile)) = (self.output.as_mut(), self.profile.clone()) else {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        };
+        let part = output.next_part();
+
+        self.process = Some(self.spawn_capture(&part, &profile)?);
+        if let Some(clock) = self.clock.as_mut() {
+            clock.resume();
+        }
+        if let Some(frames) = &self.frames {
+            frames.resume();
+        }
+        log::info!("▶️ Resumed FFmpeg capture into {part}");
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.output.is_some() && self.process.is_none()
+    }
+
+    fn stats(&self) -> RecorderStats {
+        let Some(clock) = &self.clock else {
+            return RecorderStats::default();
+        };
+
+        let mut totals = self.finished_parts.clone();
+        if let Some(process) = &self.process {
+            totals.add(&process.progress());
+        }
+        RecorderStats {
+            elapsed_seconds: clock.elapsed().as_secs_f64(),
+            frames_captured: totals.frames,
+            frames_dropped: totals.dropped,
+            encoder_queue_depth: self.process.as_ref().map_or(0, |p| p.frames_behind()),
+            bytes_written: totals.bytes,
+            audio_underruns: totals.audio_underruns,
+            paused: clock.is_paused(),
+        }
+    }
+
+    fn target_lost(&self) -> bool {
+        let Some(process) = &self.process else {
+            return false;
+        };
+        #[cfg(target_os = "linux")]
+        if self
+            .window_pid
+            .is_some_and(|pid| !super::linux::has_window(pid))
+        {
+            return true;
+        }
+        process.has_exited()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        let codecs = [VideoCodec::H264, VideoCodec::Hevc, VideoCodec::Av1];
+        BackendCapabilities {
+            name: "FFmpeg".to_string(),
+            software_codecs: codecs.to_vec(),
+            hardware_codecs: codecs
+                .into_iter()
+                .filter(|&codec| self.hardware.encoder_name(codec).is_some())
+                .collect(),
+            containers: vec![Container::Mp4, Container::Mkv, Container::FragmentedMp4],
+            rate_controls: vec![RateControl::Cbr, RateControl::Vbr, RateControl::Cqp],
+            max_fps: 240,
+            scaling: true,
+        }
+    }
+}
+
+impl Drop for FfmpegRecorder {
+    // Don't leave FFmpeg recording in the background
+    fn drop(&mut self) {
+        if self.output.is_some() {
+            let _ = self.stop_recording();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::RecordingQuality;
+
+    fn value_after<'a>(args: &'a [String], flag: &str) -> &'a str {
+        let index = args.iter().position(|a| a == flag).unwrap();
+        &args[index + 1]
+    }
+
+    #[test]
+    fn test_output_args_follow_profile() {
+        let profile = RecordingProfile::from(RecordingQuality::Low);
+        let args = output_args(&profile, HardwareEncoder::Nvenc, false, "out.mp4").unwrap();
+
+        // Auto picks the software encoder, which is always there
+        assert_eq!(value_after(&args, "-c:v"), "libx264");
+        assert_eq!(value_after(&args, "-b:v"), "5000000");
+        assert_eq!(value_after(&args, "-r"), "60");
+        assert!(!args.iter().any(|a| a == "-c:a"));
+        assert_eq!(args.last().unwrap(), "out.mp4");
+
+        let hardware = RecordingProfile {
+            codec: VideoCodec::Hevc,
+            container: Container::Mkv,
+            rate_control: RateControl::Cqp,
+            qp: 20,
+            output_height: Some(720),
+            encoder: EncoderKind::Hardware,
+            ..profile.clone()
+        };
+        let args = output_args(&hardware, HardwareEncoder::Nvenc, true, "out.mkv").unwrap();
+        assert_eq!(value_after(&args, "-c:v"), "hevc_nvenc");
+        assert_eq!(value_after(&args, "-qp"), "20");
+        assert_eq!(
+            value_after(&args, "-vf"),
+            "showinfo=checksum=0,scale=-2:720"
+        );
+        assert_eq!(value_after(&args, "-f"), "matroska");
+        assert!(args.iter().any(|a| a == "aac"));
+    }
+
+    #[test]
+    fn test_segment_output_args_roll_within_one_process() {
+        let profile = RecordingProfile::from(RecordingQuality::Low);
+        let args = segment_output_args(
+            &profile,
+            HardwareEncoder::Nvenc,
+            false,
+            5,
+            "buffer_%05d.mp4",
+            "buffer.csv",
+        )
+        .unwrap();
+
+        assert_eq!(value_after(&args, "-c:v"), "libx264");
+        assert_eq!(value_after(&args, "-f"), "segment");
+        assert_eq!(value_after(&args, "-segment_time"), "5");
+        assert_eq!(
+            value_after(&args, "-force_key_frames"),
+            "expr:gte(t,n_forced*5)"
+        );
+        assert_eq!(value_after(&args, "-segment_list"), "buffer.csv");
+        assert!(value_after(&args, "-segment_format_options").contains("empty_moov"));
+        assert_eq!(args.last().unwrap(), "buffer_%05d.mp4");
+    }
+
+    #[test]
+    fn test_reads_progress_output() {
+        let mut progress = FfmpegProgress::default();
+        for line in "frame=120\nfps=60.00\ndrop_frames=3\ntotal_size=N/A\ntotal_size=524288\nprogress=continue\n".lines() {
+            progress.apply(line);
+        }
+        assert_eq!(progress.frames, 120);
+        assert_eq!(progress.dropped, 3);
+        assert_eq!(progress.bytes, 524288);
+        assert!(is_audio_underrun("[alsa @ 0x5581] ALSA buffer xrun."));
+    }
+
+    #[test]
+    fn test_showinfo_places_frames_after_a_drop() {
+        let log = "\
+[Parsed_showinfo_0 @ 0x5581] [info] n:   0 pts:      0 pts_time:0        duration:16667
+[Parsed_showinfo_0 @ 0x5581] [info] n:   1 pts:  16667 pts_time:0.016667 duration:16667
+[Parsed_showinfo_0 @ 0x5581] [info]  color_range:pc color_space:unknown
+[Parsed_showinfo_0 @ 0x5581] [info] n:   2 pts:  50000 pts_time:0.05     duration:16667
+[x11grab @ 0x5570] [error] Cannot get the image data";
+
+        let frames = FrameLog::start();
+        for line in log.lines() {
+            if let Some(pts) = showinfo_pts(line) {
+                frames.record(10.0 + pts);
+            }
+        }
+
+        // The frame at 0.033s never came, so the next is three frames in, not two
+        let pts: Vec<f64> = frames.timestamps().frames.iter().map(|f| f.0).collect();
+        assert_eq!(pts.len(), 3);
+        assert!((pts[1] - 10.016667).abs() < 1e-9);
+        assert!((pts[2] - 10.05).abs() < 1e-9);
+
+        assert!(log.lines().filter(|line| is_log_error(line)).count() == 1);
+    }
+
+    #[test]
+    fn test_hardware_av1_needs_an_encoder_that_has_it() {
+        let profile = RecordingProfile {
+            codec: VideoCodec::Av1,
+            encoder: EncoderKind::Hardware,
+            ..RecordingProfile::default()
+        };
+
+        let recorder = FfmpegRecorder::new(HardwareEncoder::VideoToolbox);
+        assert!(profile.validate(&recorder.capabilities()).is_err());
+        assert!(output_args(&profile, HardwareEncoder::VideoToolbox, false, "out.mp4").is_err());
+
+        let recorder = FfmpegRecorder::new(HardwareEncoder::Qsv);
+        assert!(profile.validate(&recorder.capabilities()).is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+// When every frame of a recording was captured
+//
+// Recorders log each frame's position in the video against the wall clock and
+// write the log next to the video as `<video>.frames.json`. Knowing when a
+// replay frame was played, `slippi::sync` can then find it in the video,
+// whatever the capture's start latency or the frames it dropped.
+
+use super::stats::RecordingClock;
+use super::Error;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Frame timestamps of one video file, as written next to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameTimestamps {
+    /// Wall-clock time the recording started, RFC 3339 with milliseconds
+    pub started_at: String,
+    /// `(pts, wall)` of every frame: seconds into the video, and seconds after
+    /// `started_at` it was captured
+    pub frames: Vec<(f64, f64)>,
+}
+
+impl FrameTimestamps {
+    pub fn path_for(video_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.frames.json", video_path))
+    }
+
+    /// The timestamps written next to `video_path`, if there are any
+    pub fn load(video_path: &str) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path_for(video_path)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Seconds into the video of whatever was on screen `wall` seconds after the
+    /// recording started. Between frames the video runs with the clock, except
+    /// across a pause, where it holds on the next frame. Outside the logged
+    /// frames it's extrapolated, so it can be negative.
+    pub fn pts_at(&self, wall: f64) -> Option<f64> {
+        let first = self.frames.first()?;
+        let next = self.frames.partition_point(|&(_, w)| w < wall);
+        if next == 0 {
+            return Some(first.0 - (first.1 - wall));
+        }
+
+        let (pts, captured) = self.frames[next - 1];
+        let held = pts + (wall - captured);
+        Some(match self.frames.get(next) {
+            Some(&(next_pts, _)) => held.min(next_pts),
+            None => held,
+        })
+    }
+
+    /// Seconds after the recording started that the frame at `pts` was
+    /// captured; the inverse of `pts_at`
+    pub fn wall_at(&self, pts: f64) -> Option<f64> {
+        let first = self.frames.first()?;
+        let next = self.frames.partition_point(|&(p, _)| p < pts);
+        if next == 0 {
+            return Some(first.1 - (first.0 - pts));
+        }
+
+        let (previous, captured) = self.frames[next - 1];
+        let wall = captured + (pts - previous);
+        Some(match self.frames.get(next) {
+            Some(&(_, next_wall)) => wall.min(next_wall),
+            None => wall,
+        })
+    }
+}
+
+/// Logs frames as they're captured; shared with the capture threads
+pub struct FrameLog {
+    started_at: DateTime<Utc>,
+    started: Instant,
+    /// Recorded time, for backends that don't know their frames' timestamps
+    clock: Mutex<RecordingClock>,
+    /// For `record_stamped`: the capture's timestamp of the current part's
+    /// first frame, and that frame's `(pts, wall)`
+    part_start: Mutex<Option<(f64, f64, f64)>>,
+    frames: Mutex<Vec<(f64, f64)>>,
+}
+
+impl FrameLog {
+    pub fn start() -> Self {
+        Self {
+            started_at: Utc::now(),
+            started: Instant::now(),
+            clock: Mutex::new(RecordingClock::start()),
+            part_start: Mutex::new(None),
+            frames: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A frame captured just now, `pts` seconds into the video
+    pub fn record(&self, pts: f64) {
+        self.record_captured(pts, self.elapsed());
+    }
+
+    /// A frame `pts` seconds into the video, captured `wall` seconds after the
+    /// log started
+    pub fn record_captured(&self, pts: f64, wall: f64) {
+        if let Ok(mut frames) = self.frames.lock() {
+            frames.push((pts, wall));
+        }
+    }
+
+    /// A frame the capture stamped `timestamp` seconds, for encoders that place
+    /// frames by those stamps. Each part is timed from when its first frame
+    /// arrived and follows on from what was recorded before it.
+    pub fn record_stamped(&self, timestamp: f64) {
+        let Ok(mut part_start) = self.part_start.lock() else {
+            return;
+        };
+        let &mut (first, pts, wall) =
+            part_start.get_or_insert_with(|| (timestamp, self.recorded_seconds(), self.elapsed()));
+        let since_first = timestamp - first;
+        self.record_captured(pts + since_first, wall + since_first);
+    }
+
+    /// Seconds since the log started, paused or not
+    pub fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// Time recorded so far, leaving out pauses
+    pub fn recorded_seconds(&self) -> f64 {
+        self.clock
+            .lock()
+            .map(|clock| clock.elapsed().as_secs_f64())
+            .unwrap_or(0.0)
+    }
+
+    pub fn pause(&self) {
+        if let Ok(mut clock) = self.clock.lock() {
+            clock.pause();
+        }
+        // Capture restarts after a pause, stamping frames afresh
+        if let Ok(mut part_start) = self.part_start.lock() {
+            *part_start = None;
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut clock) = self.clock.lock() {
+            clock.resume();
+        }
+    }
+
+    pub fn timestamps(&self) -> FrameTimestamps {
+        FrameTimestamps {
+            started_at: self.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            frames: self
+                .frames
+                .lock()
+                .map(|frames| frames.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Write the log next to the finished video
+    pub fn write(&self, video_path: &str) -> Result<(), Error> {
+        let timestamps = self.timestamps();
+        let json = serde_json::to_string(&timestamps)
+            .map_err(|e| Error::RecordingFailed(format!("Failed to serialize frames: {}", e)))?;
+        std::fs::write(FrameTimestamps::path_for(video_path), json)?;
+        log::info!(
+            "🕐 Wrote timestamps of {} frames for {}",
+            timestamps.frames.len(),
+            video_path
+        );
+        Ok(())
+    }
+}
+
+impl Default for FrameLog {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pts_follows_dropped_frames_and_pauses() {
+        let timestamps = FrameTimestamps {
+            started_at: "2026-01-01T00:00:00.000Z".to_string(),
+            // The first frame arrives late, the third is dropped, and the
+            // recording is paused for two seconds before the last
+            frames: vec![(0.0, 0.2), (0.1, 0.3), (0.3, 0.5), (0.4, 2.6)],
+        };
+
+        assert!((timestamps.pts_at(0.1).unwrap() - -0.1).abs() < 1e-9);
+        assert!((timestamps.pts_at(0.25).unwrap() - 0.05).abs() < 1e-9);
+        assert!((timestamps.pts_at(0.45).unwrap() - 0.25).abs() < 1e-9);
+        // Paused: nothing after the last frame before the pause until the next
+        assert!((timestamps.pts_at(1.5).unwrap() - 0.4).abs() < 1e-9);
+        assert!((timestamps.pts_at(3.0).unwrap() - 0.8).abs() < 1e-9);
+        assert!((timestamps.wall_at(0.25).unwrap() - 0.45).abs() < 1e-9);
+        assert!((timestamps.wall_at(0.8).unwrap() - 3.0).abs() < 1e-9);
+        assert!(timestamps.started_at().is_some());
+    }
+}
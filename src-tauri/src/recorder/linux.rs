@@ -0,0 +1,263 @@
+// X11 window and PulseAudio lookups for the FFmpeg recorder's x11grab input
+//
+// Works on X11 and, through XWayland, on most Wayland desktops. PipeWire's
+// pulse server is picked up the same way as PulseAudio.
+
+use super::target::{self, TargetSelection, GAME_WINDOW_SCORE};
+use std::process::Command;
+
+/// Capture whatever is playing rather than a microphone
+const DEFAULT_AUDIO_SOURCE: &str = "@DEFAULT_MONITOR@";
+
+/// The default output's monitor, when a pulse server (PulseAudio or
+/// pipewire-pulse) is running
+pub(super) fn default_audio_source() -> Option<String> {
+    let server_running = Command::new("pactl")
+        .arg("info")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    server_running.then(|| DEFAULT_AUDIO_SOURCE.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct X11Window {
+    pub(super) title: String,
+    pub(super) pid: u32,
+    pub(super) x: i32,
+    pub(super) y: i32,
+    pub(super) width: u32,
+    pub(super) height: u32,
+}
+
+/// The X display to capture: `$DISPLAY`, or the first one
+pub(super) fn default_display() -> String {
+    std::env::var("DISPLAY").unwrap_or_else(|_| ":0".into())
+}
+
+/// Every top-level window on `display`, or `None` if wmctrl isn't installed
+fn list_windows(display: &str) -> Option<Vec<X11Window>> {
+    match Command::new("wmctrl")
+        .arg("-lpG")
+        .env("DISPLAY", display)
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            Some(parse_wmctrl(&String::from_utf8_lossy(&output.stdout)))
+        }
+        Ok(_) | Err(_) => {
+            log::warn!("wmctrl is unavailable, can't look up the game window");
+            None
+        }
+    }
+}
+
+/// Pick the window to capture, or `None` to capture the whole screen
+pub(super) fn find_best_window(display: &str, selection: &TargetSelection) -> Option<X11Window> {
+    let windows = list_windows(display)?;
+    let hint = selection.title.as_deref();
+    windows
+        .into_iter()
+        .filter(|w| selection.pid.is_none_or(|pid| w.pid == pid))
+        .filter(|w| score_window(w, hint) >= 0)
+        .max_by_key(|w| score_window(w, hint.or(Some("slippi"))))
+        .map(|mut w| {
+            w.width = even_dimension(w.width);
+            w.height = even_dimension(w.height);
+            w
+        })
+}
+
+/// A window that's recognisably the game, never just the biggest one
+pub(super) fn find_game_window(hint: Option<&str>) -> Option<X11Window> {
+    list_windows(&default_display())?
+        .into_iter()
+        .filter(|w| score_window(w, hint) >= GAME_WINDOW_SCORE)
+        .max_by_key(|w| score_window(w, hint))
+}
+
+/// Whether `pid` still has a window open; `true` if we can't tell
+pub(super) fn has_window(pid: u32) -> bool {
+    list_windows(&default_display()).is_none_or(|windows| windows.iter().any(|w| w.pid == pid))
+}
+
+/// Parse `wmctrl -lpG` output: id, desktop, pid, x, y, width, height, host, title
+fn parse_wmctrl(output: &str) -> Vec<X11Window> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _id = fields.next()?;
+            let _desktop = fields.next()?;
+            let pid = fields.next()?.parse().ok()?;
+            let x = fields.next()?.parse().ok()?;
+            let y = fields.next()?.parse().ok()?;
+            let width = fields.next()?.parse().ok()?;
+            let height = fields.next()?.parse().ok()?;
+            let _host = fields.next()?;
+            let title = fields.collect::<Vec<_>>().join(" ");
+
+            Some(X11Window {
+                title,
+                pid,
+                x,
+                y,
+                width,
+                height,
+            })
+        })
+        .collect()
+}
+
+fn score_window(window: &X11Window, hint: Option<&str>) -> i64 {
+    target::score_window(&window.title, window.width, window.height, hint)
+}
+
+#[inline]
+fn even_dimension(value: u32) -> u32 {
+    if value % 2 == 0 {
+        value
+    } else {
+        value - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Stdio};
+    use std::time::Duration;
+
+    /// Window managers wmctrl can ask for the window list, any of which will do
+    const WINDOW_MANAGERS: [&str; 5] = ["openbox", "fluxbox", "icewm", "xfwm4", "metacity"];
+    const TITLE: &str = "Slippi Dolphin | Melee x11grab test";
+
+    /// Processes killed when the test ends, however it ends
+    struct Spawned(Vec<Child>);
+
+    impl Drop for Spawned {
+        fn drop(&mut self) {
+            for child in self.0.iter_mut().rev() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    fn spawn(program: &str, args: &[&str], display: &str) -> Option<Child> {
+        Command::new(program)
+            .args(args)
+            .env("DISPLAY", display)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+    }
+
+    /// Poll for up to five seconds
+    fn wait_for<T>(mut check: impl FnMut() -> Option<T>) -> Option<T> {
+        (0..50).find_map(|_| {
+            std::thread::sleep(Duration::from_millis(100));
+            check()
+        })
+    }
+
+    /// Where X itself says the window is: absolute x, y, width and height
+    fn xwininfo(display: &str, title: &str) -> Option<(i32, i32, u32, u32)> {
+        let output = Command::new("xwininfo")
+            .args(["-display", display, "-name", title])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        let field = |name: &str| {
+            output
+                .lines()
+                .find_map(|line| line.trim().strip_prefix(name))
+                .and_then(|value| value.trim().parse::<i64>().ok())
+        };
+        Some((
+            field("Absolute upper-left X:")? as i32,
+            field("Absolute upper-left Y:")? as i32,
+            field("Width:")? as u32,
+            field("Height:")? as u32,
+        ))
+    }
+
+    fn value_after<'a>(args: &'a [String], flag: &str) -> &'a str {
+        let index = args.iter().position(|a| a == flag).unwrap();
+        &args[index + 1]
+    }
+
+    #[test]
+    fn test_parses_wmctrl_output() {
+        let output = "\
+0x01e00003  0 4242   0    27   1920 1053 desktop Slippi Dolphin 2.3.0 | Melee\n\
+0x02a00007 -1 1337   0    0    1920 27   desktop Top Panel\n\
+garbage line\n";
+
+        let windows = parse_wmctrl(output);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(
+            windows[0],
+            X11Window {
+                title: "Slippi Dolphin 2.3.0 | Melee".into(),
+                pid: 4242,
+                x: 0,
+                y: 27,
+                width: 1920,
+                height: 1053,
+            }
+        );
+
+        // Panels and other small windows are never picked
+        assert!(score_window(&windows[1], None) < 0);
+        assert!(score_window(&windows[0], Some("melee")) > score_window(&windows[0], None));
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb, a window manager, wmctrl, xwininfo and xterm"]
+    fn test_x11grab_region_matches_the_window() {
+        let display = ":97";
+        let xvfb = spawn("Xvfb", &[display, "-screen", "0", "1280x1024x24"], display)
+            .expect("Xvfb is installed");
+        let mut spawned = Spawned(vec![xvfb]);
+        wait_for(|| {
+            Command::new("xwininfo")
+                .args(["-display", display, "-root"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+        })
+        .expect("Xvfb starts and xwininfo can reach it");
+        let wm = WINDOW_MANAGERS
+            .iter()
+            .find_map(|wm| spawn(wm, &[], display))
+            .unwrap_or_else(|| panic!("one of {:?} is installed", WINDOW_MANAGERS));
+        spawned.0.push(wm);
+        let xterm = spawn("xterm", &["-T", TITLE, "-geometry", "81x31+64+48"], display)
+            .expect("xterm is installed");
+        spawned.0.push(xterm);
+
+        wait_for(|| {
+            list_windows(display).filter(|windows| windows.iter().any(|w| w.title == TITLE))
+        })
+        .expect("wmctrl lists the xterm");
+        let (x, y, width, height) = xwininfo(display, TITLE).unwrap();
+
+        let selection = TargetSelection {
+            title: Some(TITLE.to_string()),
+            pid: None,
+        };
+        let input = super::super::ffmpeg::x11_capture_input(display, &selection, 60).unwrap();
+        assert_eq!(
+            value_after(&input.args, "-video_size"),
+            format!("{}x{}", even_dimension(width), even_dimension(height))
+        );
+        assert_eq!(
+            value_after(&input.args, "-i"),
+            format!("{}+{},{}", display, x, y)
+        );
+        assert!(input.window_pid.is_some());
+    }
+}
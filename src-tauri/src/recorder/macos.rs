@@ -4,7 +4,15 @@
 )]
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
-use super::{Error, Recorder};
+use super::frames::FrameLog;
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+use super::profile::{Container, RateControl, VideoCodec};
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+use super::stats::RecordingClock;
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+use super::target::{self, TargetMatch, TargetSelection, GAME_WINDOW_SCORE};
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
 use core_foundation::{
@@ -52,6 +60,11 @@ const FALLBACK_HEIGHT: i32 = 720;
 const MIN_DIMENSION: i32 = 320;
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
 const PIXEL_FORMAT_BGRA: i32 = 0x4247_5241; // 'BGRA'
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+const MAX_FPS: u32 = 60;
+/// Seconds between fragments of a fragmented MP4; at most this much is lost in a crash
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+const FRAGMENT_SECONDS: i64 = 2;
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
 type RawStreamOutput = *mut Object;
@@ -60,6 +73,9 @@ type RawStreamOutput = *mut Object;
 #[link(name = "CoreMedia", kind = "framework")]
 extern "C" {
     fn CMSampleBufferGetPresentationTimeStamp(buffer: CMSampleBufferRef) -> CMTime;
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CMTimeMake(value: i64, timescale: i32) -> CMTime;
+    fn CMTimeSubtract(lhs: CMTime, rhs: CMTime) -> CMTime;
 }
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
@@ -69,6 +85,9 @@ pub struct MacOSRecorder {
     output_handle: Option<RawStreamOutput>,
     writer: Option<Arc<Mutex<VideoWriter>>>,
     output_path: Option<String>,
+    clock: Option<RecordingClock>,
+    /// ScreenCaptureKit's id of the window being captured
+    window_id: Option<u32>,
 }
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
@@ -83,27 +102,11 @@ impl MacOSRecorder {
             output_handle: None,
             writer: None,
             output_path: None,
+            clock: None,
+            window_id: None,
         }
     }
 
-    fn find_dolphin_window(&self) -> Result<SCWindow, Error> {
-        let content = SCShareableContent::get().map_err(|e| {
-            Error::InitializationError(format!("Failed to enumerate windows: {}", e))
-        })?;
-
-        let dolphin_window = content.windows().into_iter().find(|window| {
-            let title = window.title();
-            (title.contains("Slippi Dolphin")
-                || title.contains("Melee")
-                || (title.contains("Dolphin") && title.contains("FPS")))
-                && !title.contains("Configuration")
-                && !title.contains("Settings")
-                && !title.contains("Graphics")
-        });
-
-        dolphin_window.ok_or(Error::WindowNotFound)
-    }
-
     fn desired_dimensions(window: &SCWindow) -> (i32, i32) {
         let frame = window.get_frame();
         let width = frame.size.width.round() as i32;
@@ -119,17 +122,29 @@ impl MacOSRecorder {
         }
     }
 
+    /// Scale the window's size down to the profile's output height, if it has one
+    fn output_dimensions(width: i32, height: i32, profile: &RecordingProfile) -> (i32, i32) {
+        match profile.output_height {
+            Some(target) if (target as i32) < height => {
+                let scaled_width = (width as i64 * target as i64 / height as i64) as i32;
+                (scaled_width & !1, target as i32)
+            }
+            _ => (width, height),
+        }
+    }
+
     fn initialize_stream(
         &mut self,
         output_path: &str,
-        _quality: super::RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<(), Error> {
-        // Note: macOS implementation uses VideoWriter which doesn't currently support
-        // configurable bitrate, but we accept the parameter for API consistency
-        let window = self.find_dolphin_window()?;
+        let window = find_dolphin_window(TargetSelection::from_env().title.as_deref())?;
         log::info!("🎮 Found game window: {}", window.title());
+        self.window_id = Some(window.window_id());
 
         let (width, height) = Self::desired_dimensions(&window);
+        // ScreenCaptureKit scales for us when asked for a smaller frame
+        let (width, height) = Self::output_dimensions(width, height, profile);
         log::info!("🖥️  Capturing window at {}x{}", width, height);
 
         let filter = SCContentFilter::new().with_desktop_independent_window(&window);
@@ -143,7 +158,7 @@ impl MacOSRecorder {
             .set_captures_audio(false)
             .map_err(|e| Error::InitializationError(format!("Failed to disable audio: {e}")))?;
 
-        let writer = VideoWriter::new(output_path, width, height)?;
+        let writer = VideoWriter::new(output_path, width, height, profile)?;
         let writer_arc = Arc::new(Mutex::new(writer));
 
         let mut stream = SCStream::new(&filter, &config);
@@ -168,19 +183,18 @@ impl Recorder for MacOSRecorder {
     fn start_recording(
         &mut self,
         output_path: &str,
-        quality: super::RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<(), Error> {
         if self.is_recording {
             return Err(Error::RecordingFailed("Already recording".into()));
         }
 
         log::info!(
-            "🎥 [macOS] Starting recording to {} with {:?} quality (bitrate: {} Mbps)",
+            "🎥 [macOS] Starting recording to {} ({})",
             output_path,
-            quality,
-            quality.bitrate() / 1_000_000
+            profile.summary()
         );
-        self.initialize_stream(output_path, quality)?;
+        self.initialize_stream(output_path, profile)?;
 
         if let Some(stream_arc) = &self.stream {
             let stream_guard = stream_arc
@@ -196,6 +210,7 @@ impl Recorder for MacOSRecorder {
         }
 
         self.is_recording = true;
+        self.clock = Some(RecordingClock::start());
         Ok(())
     }
 
@@ -225,6 +240,11 @@ impl Recorder for MacOSRecorder {
                     .lock()
                     .map_err(|e| Error::RecordingFailed(format!("Writer lock poisoned: {e}")))?;
                 writer.finish()?;
+                if let Some(output_path) = &self.output_path {
+                    if let Err(err) = writer.frames.write(output_path) {
+                        log::warn!("Failed to write frame timestamps: {err:?}");
+                    }
+                }
             }
 
             Ok(())
@@ -239,6 +259,8 @@ impl Recorder for MacOSRecorder {
         self.writer = None;
         self.output_path = None;
         self.output_handle = None;
+        self.clock = None;
+        self.window_id = None;
         self.is_recording = false;
 
         stop_result?;
@@ -250,6 +272,144 @@ impl Recorder for MacOSRecorder {
     fn is_recording(&self) -> bool {
         self.is_recording
     }
+
+    /// The stream keeps running; the writer drops frames until resumed
+    fn pause(&mut self) -> Result<(), Error> {
+        if !self.is_recording {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        }
+        let writer = self
+            .writer
+            .as_ref()
+            .ok_or_else(|| Error::RecordingFailed("Not recording".into()))?;
+        writer
+            .lock()
+            .map_err(|e| Error::RecordingFailed(format!("Writer lock poisoned: {e}")))?
+            .pause()?;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.pause();
+        }
+
+        log::info!("⏸️  [macOS] Paused recording");
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        let writer = self
+            .writer
+            .as_ref()
+            .ok_or_else(|| Error::RecordingFailed("Not recording".into()))?;
+        writer
+            .lock()
+            .map_err(|e| Error::RecordingFailed(format!("Writer lock poisoned: {e}")))?
+            .resume()?;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.resume();
+        }
+
+        log::info!("▶️  [macOS] Resumed recording");
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.writer
+            .as_ref()
+            .and_then(|writer| writer.lock().ok().map(|writer| writer.paused))
+            .unwrap_or(false)
+    }
+
+    /// AVAssetWriter doesn't expose its queue or capture audio, so those stay at zero
+    fn stats(&self) -> RecorderStats {
+        let (Some(clock), Some(writer)) = (&self.clock, &self.writer) else {
+            return RecorderStats::default();
+        };
+        let Ok(writer) = writer.lock() else {
+            return RecorderStats::default();
+        };
+
+        RecorderStats {
+            elapsed_seconds: clock.elapsed().as_secs_f64(),
+            frames_captured: writer.frames_captured,
+            frames_dropped: writer.dropped_frames,
+            encoder_queue_depth: 0,
+            bytes_written: self
+                .output_path
+                .as_ref()
+                .and_then(|path| std::fs::metadata(path).ok())
+                .map_or(0, |metadata| metadata.len()),
+            audio_underruns: 0,
+            paused: writer.paused,
+        }
+    }
+
+    /// The stream just stops delivering frames, so check the window is still there
+    fn target_lost(&self) -> bool {
+        let Some(window_id) = self.window_id else {
+            return false;
+        };
+        match SCShareableContent::get() {
+            Ok(content) => !content
+                .windows()
+                .iter()
+                .any(|window| window.window_id() == window_id),
+            Err(_) => false,
+        }
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "macOS".to_string(),
+            software_codecs: vec![],
+            hardware_codecs: vec![VideoCodec::H264, VideoCodec::Hevc],
+            containers: vec![Container::Mp4, Container::FragmentedMp4],
+            rate_controls: vec![RateControl::Vbr],
+            max_fps: MAX_FPS,
+            scaling: true,
+        }
+    }
+}
+
+/// The best scoring game window, preferring the one picked in settings
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+fn find_dolphin_window(hint: Option<&str>) -> Result<SCWindow, Error> {
+    let content = SCShareableContent::get()
+        .map_err(|e| Error::InitializationError(format!("Failed to enumerate windows: {}", e)))?;
+
+    content
+        .windows()
+        .into_iter()
+        .filter(|window| {
+            let title = window.title();
+            !title.contains("Configuration")
+                && !title.contains("Settings")
+                && !title.contains("Graphics")
+        })
+        .map(|window| (score_window(&window, hint), window))
+        .filter(|(score, _)| *score >= GAME_WINDOW_SCORE)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, window)| window)
+        .ok_or(Error::WindowNotFound)
+}
+
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+fn score_window(window: &SCWindow, hint: Option<&str>) -> i64 {
+    let frame = window.get_frame();
+    target::score_window(
+        &window.title(),
+        frame.size.width.max(0.0) as u32,
+        frame.size.height.max(0.0) as u32,
+        hint,
+    )
+}
+
+/// The game window, for picking it up again after it closed
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+pub(super) fn find_game_window(hint: Option<&str>) -> Option<TargetMatch> {
+    let window = find_dolphin_window(hint).ok()?;
+    Some(TargetMatch {
+        title: window.title(),
+        pid: window.owning_application().process_id() as u32,
+    })
 }
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
@@ -292,7 +452,19 @@ struct VideoWriter {
     input: StrongPtr,
     adaptor: StrongPtr,
     started: bool,
-    dropped_frames: usize,
+    frames_captured: u64,
+    dropped_frames: u64,
+    /// Length of one frame at the profile's frame rate
+    frame_duration: CMTime,
+    paused: bool,
+    /// The next frame is the first after a pause
+    resuming: bool,
+    /// Paused time so far, taken off every source timestamp
+    offset: CMTime,
+    last_timestamp: Option<CMTime>,
+    /// Source time of the first frame, where the video starts
+    session_start: Option<CMTime>,
+    frames: FrameLog,
 }
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
@@ -300,7 +472,12 @@ unsafe impl Send for VideoWriter {}
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
 impl VideoWriter {
-    fn new(output_path: &str, width: i32, height: i32) -> Result<Self, Error> {
+    fn new(
+        output_path: &str,
+        width: i32,
+        height: i32,
+        profile: &RecordingProfile,
+    ) -> Result<Self, Error> {
         let path = Path::new(output_path);
         if let Some(parent) = path.parent() {
             if !parent.as_os_str().is_empty() {
@@ -318,7 +495,7 @@ impl VideoWriter {
         let file_url = CFURL::from_path(path, false)
             .ok_or_else(|| Error::RecordingFailed("Invalid output path".into()))?;
 
-        let video_settings = video_output_settings(width as u32, height as u32)?;
+        let video_settings = video_output_settings(width as u32, height as u32, profile)?;
         let pixel_attrs = pixel_buffer_attributes(width as u32, height as u32)?;
 
         unsafe {
@@ -379,17 +556,51 @@ impl VideoWriter {
             }
             let _: () = msg_send![*writer, addInput: *input];
 
+            if profile.container == Container::FragmentedMp4 {
+                let interval = CMTimeMake(FRAGMENT_SECONDS, 1);
+                let _: () = msg_send![*writer, setMovieFragmentInterval: interval];
+            }
+
             Ok(Self {
                 writer,
                 input,
                 adaptor,
                 started: false,
+                frames_captured: 0,
                 dropped_frames: 0,
+                frame_duration: CMTimeMake(1, profile.fps as i32),
+                paused: false,
+                resuming: false,
+                offset: CMTimeMake(0, profile.fps as i32),
+                last_timestamp: None,
+                session_start: None,
+                frames: FrameLog::start(),
             })
         }
     }
 
+    fn pause(&mut self) -> Result<(), Error> {
+        if self.paused {
+            return Err(Error::RecordingFailed("Already paused".into()));
+        }
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        if !self.paused {
+            return Err(Error::RecordingFailed("Not paused".into()));
+        }
+        self.paused = false;
+        self.resuming = true;
+        Ok(())
+    }
+
     fn append_sample_buffer(&mut self, sample_buffer: CMSampleBuffer) -> Result<(), Error> {
+        if self.paused {
+            return Ok(());
+        }
+
         sample_buffer
             .make_data_ready()
             .map_err(|e| Error::RecordingFailed(format!("Buffer not ready: {e:?}")))?;
@@ -400,8 +611,19 @@ impl VideoWriter {
             .get_pixel_buffer()
             .map_err(|e| Error::RecordingFailed(format!("Failed to get pixel buffer: {e:?}")))?;
 
+        // Close the gap so the first frame after a pause follows the last one
+        if std::mem::take(&mut self.resuming) {
+            if let Some(last) = self.last_timestamp {
+                self.offset =
+                    unsafe { CMTimeSubtract(CMTimeSubtract(timestamp, last), self.frame_duration) };
+            }
+        }
+        let timestamp = unsafe { CMTimeSubtract(timestamp, self.offset) };
+
         self.ensure_started(timestamp)?;
-        self.append_pixel_buffer(pixel_buffer, timestamp)
+        self.append_pixel_buffer(pixel_buffer, timestamp)?;
+        self.last_timestamp = Some(timestamp);
+        Ok(())
     }
 
     fn ensure_started(&mut self, timestamp: CMTime) -> Result<(), Error> {
@@ -421,6 +643,7 @@ impl VideoWriter {
         }
 
         self.started = true;
+        self.session_start = Some(timestamp);
         Ok(())
     }
 
@@ -452,6 +675,11 @@ impl VideoWriter {
                 )));
             }
         }
+        self.frames_captured += 1;
+        if let Some(start) = self.session_start {
+            self.frames
+                .record(unsafe { CMTimeGetSeconds(CMTimeSubtract(timestamp, start)) });
+        }
 
         Ok(())
     }
@@ -490,18 +718,39 @@ impl VideoWriter {
 }
 
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
-fn video_output_settings(width: u32, height: u32) -> Result<CFDictionary<CFString, CFType>, Error> {
+fn video_output_settings(
+    width: u32,
+    height: u32,
+    profile: &RecordingProfile,
+) -> Result<CFDictionary<CFString, CFType>, Error> {
     let codec_key = CFString::new("AVVideoCodecKey");
-    let codec_value = CFString::new("avc1").as_CFType(); // H.264
+    let codec_value = match profile.codec {
+        VideoCodec::Hevc => CFString::new("hvc1").as_CFType(),
+        _ => CFString::new("avc1").as_CFType(), // H.264
+    };
     let width_key = CFString::new("AVVideoWidthKey");
     let height_key = CFString::new("AVVideoHeightKey");
     let width_value = CFNumber::from(width as i64).as_CFType();
     let height_value = CFNumber::from(height as i64).as_CFType();
 
+    // AVVideoAverageBitRateKey and AVVideoExpectedSourceFrameRateKey
+    let compression = CFDictionary::<CFString, CFType>::from_CFType_pairs(&[
+        (
+            CFString::new("AverageBitRate"),
+            CFNumber::from(profile.bitrate as i64).as_CFType(),
+        ),
+        (
+            CFString::new("ExpectedFrameRate"),
+            CFNumber::from(profile.fps as i64).as_CFType(),
+        ),
+    ]);
+    let compression_key = CFString::new("AVVideoCompressionPropertiesKey");
+
     Ok(CFDictionary::<CFString, CFType>::from_CFType_pairs(&[
         (codec_key, codec_value),
         (width_key, width_value),
         (height_key, height_value),
+        (compression_key, compression.as_CFType()),
     ]))
 }
 
@@ -1,41 +1,171 @@
-use super::{Error, Recorder};
-use std::time::Instant;
+// Stand-in recorder for development without a capture backend
+//
+// Stopping writes a real video the length of the recording: FFmpeg's test
+// pattern with the time burned in, and a tone for audio. Everything after
+// recording (the library, thumbnails, clips, uploads) then works the same as
+// with a real recorder, on any machine.
+
+use super::ffmpeg::{output_args, HardwareEncoder};
+use super::frames::FrameLog;
+use super::profile::{Container, EncoderKind, RateControl, VideoCodec};
+use super::stats::RecordingClock;
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
+use ffmpeg_sidecar::command::FfmpegCommand;
+use std::path::Path;
+
+/// Size of the test pattern when the profile doesn't scale
+const DEFAULT_HEIGHT: u32 = 480;
 
 pub struct MockRecorder {
+    /// Whether stopping writes the video, or only pretends to
+    writes_video: bool,
     is_recording: bool,
-    start_time: Option<Instant>,
+    clock: Option<RecordingClock>,
+    /// Every frame of the test pattern, logged as it would have been captured
+    frames: Option<FrameLog>,
+    frames_logged: u64,
+    /// Frame rate and bitrate the made-up stats follow
+    profile: Option<RecordingProfile>,
     output_path: Option<String>,
 }
 
 impl MockRecorder {
     pub fn new() -> Self {
         Self {
+            writes_video: true,
             is_recording: false,
-            start_time: None,
+            clock: None,
+            frames: None,
+            frames_logged: 0,
+            profile: None,
             output_path: None,
         }
     }
+
+    /// A recorder that never touches the disk, for tests of what wraps it
+    pub fn without_video() -> Self {
+        Self {
+            writes_video: false,
+            ..Self::new()
+        }
+    }
+
+    fn log_recorded_frames(&mut self) {
+        if let Some(recorded) = self.frames.as_ref().map(|frames| frames.recorded_seconds()) {
+            self.log_frames(recorded);
+        }
+    }
+
+    /// Log the test pattern's frames up to `until` seconds into the video, each
+    /// captured as long before now as it is before the recorded time
+    fn log_frames(&mut self, until: f64) {
+        let (Some(frames), Some(profile)) = (&self.frames, &self.profile) else {
+            return;
+        };
+        let fps = profile.fps.max(1) as f64;
+        let recorded = frames.recorded_seconds();
+        let now = frames.elapsed();
+        while self.frames_logged as f64 / fps <= until {
+            let pts = self.frames_logged as f64 / fps;
+            frames.record_captured(pts, now - (recorded - pts));
+            self.frames_logged += 1;
+        }
+    }
+}
+
+/// Write `duration` seconds of test pattern, timecode and tone, encoded the way
+/// `profile` asks but always in software
+fn write_test_video(
+    output_path: &str,
+    profile: &RecordingProfile,
+    duration: f64,
+) -> Result<(), Error> {
+    crate::clip_processor::ensure_ffmpeg()?;
+    if let Some(parent) = Path::new(output_path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let height = profile.output_height.unwrap_or(DEFAULT_HEIGHT);
+    // 4:3 like the game, rounded to an even width
+    let width = (height * 4 / 3 + 1) & !1;
+    let software = RecordingProfile {
+        encoder: EncoderKind::Software,
+        output_height: None,
+        ..profile.clone()
+    };
+    let output = output_args(&software, HardwareEncoder::default(), true, output_path)?;
+
+    let run = |with_timecode: bool| -> Result<(), Error> {
+        let mut command = FfmpegCommand::new();
+        command
+            .args([
+                "-f",
+                "lavfi",
+                "-i",
+                &format!(
+                    "testsrc2=size={}x{}:rate={}:duration={:.3}",
+                    width, height, profile.fps, duration
+                ),
+                "-f",
+                "lavfi",
+                "-i",
+                &format!(
+                    "sine=frequency=440:sample_rate=48000:duration={:.3}",
+                    duration
+                ),
+            ])
+            .args(["-map", "0:v", "-map", "1:a"]);
+        if with_timecode {
+            command.args([
+                "-vf",
+                "drawtext=text='%{pts\\:hms}':x=16:y=16:fontsize=32:fontcolor=white:box=1:boxcolor=black@0.6",
+            ]);
+        }
+        let status = command
+            .args(&output)
+            .spawn()
+            .map_err(|e| Error::RecordingFailed(format!("Failed to spawn FFmpeg: {}", e)))?
+            .wait()
+            .map_err(|e| Error::RecordingFailed(format!("FFmpeg process error: {}", e)))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::RecordingFailed(format!(
+                "FFmpeg failed with status: {:?}",
+                status
+            )))
+        }
+    };
+
+    // drawtext needs an FFmpeg built with fonts; the pattern alone will do
+    run(true).or_else(|e| {
+        log::warn!("⚠️ [MOCK] Writing without timecode: {:?}", e);
+        run(false)
+    })
 }
 
 impl Recorder for MockRecorder {
     fn start_recording(
         &mut self,
         output_path: &str,
-        quality: super::RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<(), Error> {
         if self.is_recording {
             return Err(Error::RecordingFailed("Already recording".to_string()));
         }
 
         println!(
-            "🎥 [MOCK] Starting recording to: {} with {:?} quality (bitrate: {} Mbps)",
+            "🎥 [MOCK] Starting recording to: {} ({})",
             output_path,
-            quality,
-            quality.bitrate() / 1_000_000
+            profile.summary()
         );
         self.is_recording = true;
-        self.start_time = Some(Instant::now());
+        self.clock = Some(RecordingClock::start());
+        self.frames = Some(FrameLog::start());
+        self.frames_logged = 0;
+        self.profile = Some(profile.clone());
         self.output_path = Some(output_path.to_string());
+        self.log_frames(0.0);
 
         Ok(())
     }
@@ -48,9 +178,10 @@ impl Recorder for MockRecorder {
         }
 
         let duration = self
-            .start_time
-            .map(|start| start.elapsed().as_secs())
-            .unwrap_or(0);
+            .clock
+            .as_ref()
+            .map(|clock| clock.elapsed().as_secs_f64())
+            .unwrap_or(0.0);
 
         let output_path = self
             .output_path
@@ -58,12 +189,25 @@ impl Recorder for MockRecorder {
             .unwrap_or_else(|| "unknown.mp4".to_string());
 
         println!(
-            "⏹️  [MOCK] Stopped recording. Duration: {}s. Saved to: {}",
+            "⏹️  [MOCK] Stopped recording. Duration: {:.1}s. Saved to: {}",
             duration, output_path
         );
 
+        // A video needs at least a frame
+        let video_duration = duration.max(1.0);
+        self.log_frames(video_duration);
+
         self.is_recording = false;
-        self.start_time = None;
+        self.clock = None;
+        let profile = self.profile.take();
+        let frames = self.frames.take();
+
+        if let (true, Some(profile)) = (self.writes_video, profile) {
+            write_test_video(&output_path, &profile, video_duration)?;
+            if let Some(frames) = frames {
+                frames.write(&output_path)?;
+            }
+        }
 
         Ok(output_path)
     }
@@ -71,6 +215,74 @@ impl Recorder for MockRecorder {
     fn is_recording(&self) -> bool {
         self.is_recording
     }
+
+    fn pause(&mut self) -> Result<(), Error> {
+        let Some(clock) = self.clock.as_mut().filter(|clock| !clock.is_paused()) else {
+            return Err(Error::RecordingFailed(
+                "Not currently recording".to_string(),
+            ));
+        };
+
+        println!("⏸️  [MOCK] Paused recording");
+        clock.pause();
+        self.log_recorded_frames();
+        if let Some(frames) = &self.frames {
+            frames.pause();
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        let Some(clock) = self.clock.as_mut().filter(|clock| clock.is_paused()) else {
+            return Err(Error::RecordingFailed("Not paused".to_string()));
+        };
+
+        println!("▶️  [MOCK] Resumed recording");
+        clock.resume();
+        if let Some(frames) = &self.frames {
+            frames.resume();
+        }
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.clock.as_ref().is_some_and(|clock| clock.is_paused())
+    }
+
+    /// A perfect recording at the profile's frame rate and bitrate
+    fn stats(&self) -> RecorderStats {
+        let (Some(clock), Some(profile)) = (&self.clock, &self.profile) else {
+            return RecorderStats::default();
+        };
+
+        let elapsed = clock.elapsed().as_secs_f64();
+        RecorderStats {
+            elapsed_seconds: elapsed,
+            frames_captured: (elapsed * profile.fps as f64) as u64,
+            bytes_written: (elapsed * profile.bitrate as f64 / 8.0) as u64,
+            paused: clock.is_paused(),
+            ..RecorderStats::default()
+        }
+    }
+
+    fn tick(&mut self) -> Result<(), Error> {
+        self.log_recorded_frames();
+        Ok(())
+    }
+
+    /// Pretends to support everything so any profile can be tested
+    fn capabilities(&self) -> BackendCapabilities {
+        let codecs = vec![VideoCodec::H264, VideoCodec::Hevc, VideoCodec::Av1];
+        BackendCapabilities {
+            name: "mock".to_string(),
+            software_codecs: codecs.clone(),
+            hardware_codecs: codecs,
+            containers: vec![Container::Mp4, Container::Mkv, Container::FragmentedMp4],
+            rate_controls: vec![RateControl::Cbr, RateControl::Vbr, RateControl::Cqp],
+            max_fps: 240,
+            scaling: true,
+        }
+    }
 }
 
 impl Default for MockRecorder {
@@ -78,3 +290,36 @@ impl Default for MockRecorder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::frames::FrameTimestamps;
+    use crate::recorder::RecordingQuality;
+
+    #[test]
+    fn test_writes_a_video_of_the_recording_length() {
+        if !ffmpeg_sidecar::command::ffmpeg_is_installed() {
+            return;
+        }
+
+        let dir = std::env::temp_dir().join("buckwheat_mock_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let output_path = dir.join("Game_1.mkv").to_string_lossy().to_string();
+
+        let mut profile: RecordingProfile = RecordingQuality::Low.into();
+        profile.container = Container::Mkv;
+        profile.fps = 30;
+
+        let mut recorder = MockRecorder::new();
+        recorder.start_recording(&output_path, &profile).unwrap();
+        assert_eq!(recorder.stop_recording().unwrap(), output_path);
+
+        let size = std::fs::metadata(&output_path).unwrap().len();
+        assert!(size > 0);
+        let timestamps = FrameTimestamps::load(&output_path).unwrap();
+        assert!(timestamps.frames.len() >= 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,16 @@
+pub mod ffmpeg;
+pub mod frames;
 pub mod mock;
+pub mod parts;
+pub mod profile;
+pub mod recovery;
+pub mod replay_buffer;
+pub mod segmented;
+pub mod segments;
+pub mod set;
+pub mod stats;
+pub mod target;
+pub mod watchdog;
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 pub mod windows;
@@ -6,8 +18,13 @@ pub mod windows;
 #[cfg(all(target_os = "macos", feature = "real-recording"))]
 pub mod macos;
 
+#[cfg(target_os = "linux")]
+pub mod linux;
+
 use crate::commands::errors::Error;
+pub use profile::{BackendCapabilities, RecordingProfile};
 use serde::{Deserialize, Serialize};
+pub use stats::RecorderStats;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -40,30 +57,289 @@ pub trait Recorder {
     fn start_recording(
         &mut self,
         output_path: &str,
-        quality: RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<(), Error>;
     fn stop_recording(&mut self) -> Result<String, Error>;
     fn is_recording(&self) -> bool;
+    /// Stop capturing without ending the file; the paused time is left out of
+    /// the output rather than recorded as a frozen frame
+    fn pause(&mut self) -> Result<(), Error>;
+    /// Continue a paused recording in the same file
+    fn resume(&mut self) -> Result<(), Error>;
+    fn is_paused(&self) -> bool;
+    /// Snapshot of how the recording is going; all zeros when not recording
+    fn stats(&self) -> RecorderStats;
+    /// Called every few seconds while recording, for work the capture itself
+    /// doesn't drive, like starting the next segment
+    fn tick(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Whether the window being captured has gone away, so nothing more can be
+    /// recorded until the game is back
+    fn target_lost(&self) -> bool {
+        false
+    }
+    /// What this backend can encode, for validating profiles
+    fn capabilities(&self) -> BackendCapabilities;
 }
 
-pub fn get_recorder() -> Box<dyn Recorder + Send> {
+/// The platform's native capture, if this build has one. Linux has none of its
+/// own; x11grab is the FFmpeg recorder.
+fn native_recorder() -> Option<Box<dyn Recorder + Send>> {
     #[cfg(all(target_os = "macos", feature = "real-recording"))]
     {
         log::info!(
             "🍎 Initializing MacOS recorder with screencapturekit-rs (real-recording enabled)"
         );
-        Box::new(macos::MacOSRecorder::new())
+        Some(Box::new(macos::MacOSRecorder::new()))
     }
 
     #[cfg(all(target_os = "windows", feature = "real-recording"))]
     {
         log::info!("🪟 Initializing Windows recorder with windows-record (real-recording enabled)");
-        Box::new(windows::WindowsRecorder::new())
+        Some(Box::new(windows::WindowsRecorder::new()))
     }
 
-    #[cfg(not(feature = "real-recording"))]
+    #[cfg(not(all(
+        any(target_os = "macos", target_os = "windows"),
+        feature = "real-recording"
+    )))]
     {
+        None
+    }
+}
+
+/// Which implementation captures the game, chosen in settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecorderBackend {
+    /// The platform's native capture API, falling back to FFmpeg if it fails to start
+    #[default]
+    Native,
+    /// FFmpeg's capture devices only
+    Ffmpeg,
+}
+
+/// Dev builds without native capture record the test pattern instead, unless
+/// FFmpeg is picked outright
+fn records_test_pattern(backend: RecorderBackend) -> bool {
+    cfg!(all(debug_assertions, not(feature = "real-recording")))
+        && backend == RecorderBackend::Native
+}
+
+/// Whether `create_recorder` captures with FFmpeg for `backend`, so FFmpeg's
+/// own tools, like its segment muxer, can capture in its place
+pub fn records_with_ffmpeg(backend: RecorderBackend) -> bool {
+    let has_native = cfg!(all(
+        any(target_os = "macos", target_os = "windows"),
+        feature = "real-recording"
+    ));
+    match backend {
+        RecorderBackend::Native => !records_test_pattern(backend) && !has_native,
+        RecorderBackend::Ffmpeg => true,
+    }
+}
+
+/// Create a recorder for `backend`; `hardware` is the GPU encoder FFmpeg uses
+/// for profiles that ask for hardware encoding
+pub fn create_recorder(
+    backend: RecorderBackend,
+    hardware: ffmpeg::HardwareEncoder,
+) -> Box<dyn Recorder + Send> {
+    if records_test_pattern(backend) {
         log::info!("🧪 Initializing mock recorder (dev mode - real-recording disabled)");
-        Box::new(mock::MockRecorder::new())
+        return Box::new(mock::MockRecorder::new());
+    }
+
+    match backend {
+        RecorderBackend::Native => match native_recorder() {
+            Some(native) => Box::new(FallbackRecorder::new(
+                native,
+                Box::new(ffmpeg::FfmpegRecorder::new(hardware)),
+            )),
+            None => {
+                log::info!(
+                    "🎬 No native capture in this build, initializing FFmpeg recorder ({:?})",
+                    hardware
+                );
+                Box::new(ffmpeg::FfmpegRecorder::new(hardware))
+            }
+        },
+        RecorderBackend::Ffmpeg => {
+            log::info!("🎬 Initializing FFmpeg recorder ({:?})", hardware);
+            Box::new(ffmpeg::FfmpegRecorder::new(hardware))
+        }
+    }
+}
+
+/// Records with `primary`, switching to `fallback` if `primary` can't record the
+/// profile or fails to start
+pub struct FallbackRecorder {
+    primary: Box<dyn Recorder + Send>,
+    fallback: Box<dyn Recorder + Send>,
+    using_fallback: bool,
+}
+
+impl FallbackRecorder {
+    pub fn new(primary: Box<dyn Recorder + Send>, fallback: Box<dyn Recorder + Send>) -> Self {
+        Self {
+            primary,
+            fallback,
+            using_fallback: false,
+        }
+    }
+
+    fn active(&mut self) -> &mut Box<dyn Recorder + Send> {
+        if self.using_fallback {
+            &mut self.fallback
+        } else {
+            &mut self.primary
+        }
+    }
+}
+
+impl Recorder for FallbackRecorder {
+    fn start_recording(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<(), Error> {
+        if self.is_recording() {
+            return Err(Error::RecordingFailed("Already recording".to_string()));
+        }
+
+        let started = profile
+            .validate(&self.primary.capabilities())
+            .and_then(|()| self.primary.start_recording(output_path, profile));
+        match started {
+            Ok(()) => {
+                self.using_fallback = false;
+                Ok(())
+            }
+            Err(e) => {
+                log::warn!(
+                    "⚠️ Native recorder failed to start ({}), falling back to FFmpeg",
+                    e
+                );
+                self.using_fallback = true;
+                self.fallback.start_recording(output_path, profile)
+            }
+        }
+    }
+
+    fn stop_recording(&mut self) -> Result<String, Error> {
+        self.active().stop_recording()
+    }
+
+    fn is_recording(&self) -> bool {
+        if self.using_fallback {
+            self.fallback.is_recording()
+        } else {
+            self.primary.is_recording()
+        }
+    }
+
+    fn pause(&mut self) -> Result<(), Error> {
+        self.active().pause()
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        self.active().resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        if self.using_fallback {
+            self.fallback.is_paused()
+        } else {
+            self.primary.is_paused()
+        }
+    }
+
+    fn stats(&self) -> RecorderStats {
+        if self.using_fallback {
+            self.fallback.stats()
+        } else {
+            self.primary.stats()
+        }
+    }
+
+    fn tick(&mut self) -> Result<(), Error> {
+        self.active().tick()
+    }
+
+    fn target_lost(&self) -> bool {
+        if self.using_fallback {
+            self.fallback.target_lost()
+        } else {
+            self.primary.target_lost()
+        }
+    }
+
+    /// Anything the fallback can record will be recorded by one of the two
+    fn capabilities(&self) -> BackendCapabilities {
+        self.fallback.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backend whose capture API is unavailable
+    struct FailingRecorder;
+
+    impl Recorder for FailingRecorder {
+        fn start_recording(&mut self, _: &str, _: &RecordingProfile) -> Result<(), Error> {
+            Err(Error::RecordingFailed("No capture API".to_string()))
+        }
+
+        fn stop_recording(&mut self) -> Result<String, Error> {
+            Err(Error::RecordingFailed("Not recording".to_string()))
+        }
+
+        fn is_recording(&self) -> bool {
+            false
+        }
+
+        fn pause(&mut self) -> Result<(), Error> {
+            Err(Error::RecordingFailed("Not recording".to_string()))
+        }
+
+        fn resume(&mut self) -> Result<(), Error> {
+            Err(Error::RecordingFailed("Not recording".to_string()))
+        }
+
+        fn is_paused(&self) -> bool {
+            false
+        }
+
+        fn stats(&self) -> RecorderStats {
+            RecorderStats::default()
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            mock::MockRecorder::new().capabilities()
+        }
+    }
+
+    #[test]
+    fn test_falls_back_when_primary_fails_to_start() {
+        let mut recorder = FallbackRecorder::new(
+            Box::new(FailingRecorder),
+            Box::new(mock::MockRecorder::without_video()),
+        );
+        recorder
+            .start_recording("game.mp4", &RecordingQuality::Low.into())
+            .unwrap();
+        assert!(recorder.is_recording());
+
+        // Pausing reaches the recorder that's actually running
+        recorder.pause().unwrap();
+        assert!(recorder.is_paused());
+        recorder.resume().unwrap();
+        assert!(!recorder.is_paused());
+
+        assert_eq!(recorder.stop_recording().unwrap(), "game.mp4");
+        assert!(!recorder.is_recording());
     }
 }
@@ -0,0 +1,161 @@
+// Pausing for backends that can only write a file from start to finish
+//
+// Each stretch between a resume and the next pause is recorded to its own
+// part file. Stopping joins the parts into the requested output, so the result
+// is one continuous file whose timestamps skip the paused time.
+
+use crate::commands::errors::Error;
+use std::path::{Path, PathBuf};
+
+pub struct PartedOutput {
+    output_path: String,
+    /// Files recorded so far, the first being `output_path` itself
+    parts: Vec<PathBuf>,
+}
+
+impl PartedOutput {
+    /// Start with the first part written straight to `output_path`, so a
+    /// recording that's never paused needs no joining
+    pub fn new(output_path: &str) -> Self {
+        Self {
+            output_path: output_path.to_string(),
+            parts: vec![PathBuf::from(output_path)],
+        }
+    }
+
+    /// Pick up the parts an interrupted recording left on disk, in order
+    pub fn existing(output_path: &str) -> Self {
+        let mut output = Self::new(output_path);
+        loop {
+            let next = output.part_path(output.parts.len());
+            if !next.exists() {
+                break;
+            }
+            output.parts.push(next);
+        }
+        output
+    }
+
+    pub fn output_path(&self) -> &str {
+        &self.output_path
+    }
+
+    pub fn parts(&self) -> &[PathBuf] {
+        &self.parts
+    }
+
+    /// `<stem>.part<index>.<ext>` next to the output
+    fn part_path(&self, index: usize) -> PathBuf {
+        let output = Path::new(&self.output_path);
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("recording");
+        let extension = output.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+        output.with_file_name(format!("{}.part{}.{}", stem, index, extension))
+    }
+
+    /// Path to record the next part to after resuming
+    pub fn next_part(&mut self) -> String {
+        let path = self.part_path(self.parts.len());
+        self.parts.push(path.clone());
+        path.to_string_lossy().to_string()
+    }
+
+    /// Size of everything recorded so far
+    pub fn bytes_written(&self) -> u64 {
+        self.parts
+            .iter()
+            .filter_map(|part| std::fs::metadata(part).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Join the parts into the output path, removing them afterwards
+    pub fn finish(self) -> Result<String, Error> {
+        if self.parts.len() == 1 {
+            return Ok(self.output_path);
+        }
+
+        // The first part is in the way of the joined file
+        let first = self.part_path(0);
+        if self.parts[0].exists() {
+            std::fs::rename(&self.parts[0], &first)?;
+        }
+        let mut parts = self.parts;
+        parts[0] = first;
+
+        let inputs: Vec<String> = parts
+            .iter()
+            .filter(|p| p.exists())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        log::info!("⏯️ Joining {} recorded parts", inputs.len());
+        if let Err(e) = crate::clip_processor::concat_videos(&inputs, &self.output_path) {
+            // Put the first part back where the recording belongs, leaving the
+            // rest for `existing` to pick up again
+            let _ = std::fs::remove_file(&self.output_path);
+            if parts[0].exists() {
+                if let Err(rename_err) = std::fs::rename(&parts[0], &self.output_path) {
+                    log::error!(
+                        "Failed to restore first recording part {:?}: {}",
+                        parts[0],
+                        rename_err
+                    );
+                }
+            }
+            return Err(e);
+        }
+
+        for part in &parts {
+            if let Err(e) = std::fs::remove_file(part) {
+                log::warn!("Failed to remove recording part {:?}: {}", part, e);
+            }
+        }
+        Ok(self.output_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_sit_next_to_the_output() {
+        let mut output = PartedOutput::new("/videos/Game_1.mkv");
+        assert_eq!(output.next_part(), "/videos/Game_1.part1.mkv");
+        assert_eq!(output.next_part(), "/videos/Game_1.part2.mkv");
+        assert_eq!(
+            output.part_path(0),
+            PathBuf::from("/videos/Game_1.part0.mkv")
+        );
+        assert_eq!(output.output_path(), "/videos/Game_1.mkv");
+    }
+
+    #[test]
+    fn test_unpaused_recording_is_left_alone() {
+        let output = PartedOutput::new("/videos/Game_1.mp4");
+        assert_eq!(output.finish().unwrap(), "/videos/Game_1.mp4");
+    }
+
+    #[test]
+    fn test_failed_join_leaves_the_parts_where_they_were() {
+        let dir = std::env::temp_dir().join("buckwheat_parts_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let output_path = dir.join("Game_1.mp4").to_string_lossy().to_string();
+        std::fs::write(&output_path, b"first part").unwrap();
+
+        // Neither part is a video, so joining them fails
+        let mut output = PartedOutput::new(&output_path);
+        std::fs::write(output.next_part(), b"second part").unwrap();
+        assert!(output.finish().is_err());
+
+        assert_eq!(std::fs::read(&output_path).unwrap(), b"first part");
+        let output = PartedOutput::existing(&output_path);
+        assert_eq!(output.parts().len(), 2);
+        assert!(!output.part_path(0).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,263 @@
+// Everything about how a recording is encoded
+//
+// The four `RecordingQuality` levels are presets of a profile. Each backend
+// describes what it can encode with `BackendCapabilities`, and a profile is
+// checked against those before recording starts.
+
+use super::RecordingQuality;
+use crate::commands::errors::Error;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+    Av1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Container {
+    Mp4,
+    Mkv,
+    /// MP4 written as self-contained fragments, playable even if the app crashes
+    FragmentedMp4,
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 | Container::FragmentedMp4 => "mp4",
+            Container::Mkv => "mkv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateControl {
+    /// Constant bitrate
+    Cbr,
+    /// Variable bitrate averaging the target
+    Vbr,
+    /// Constant quantizer, ignores the bitrate
+    Cqp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderKind {
+    /// Whatever the backend encodes with by default
+    #[default]
+    Auto,
+    Software,
+    Hardware,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingProfile {
+    pub codec: VideoCodec,
+    pub container: Container,
+    pub rate_control: RateControl,
+    /// Target bitrate in bits per second, for CBR and VBR
+    pub bitrate: u32,
+    /// Quantizer for CQP, 0-51 (lower is better)
+    pub qp: u8,
+    pub fps: u32,
+    /// Scale the output to this height keeping the aspect ratio, or record at
+    /// the window's own size
+    pub output_height: Option<u32>,
+    pub encoder: EncoderKind,
+}
+
+impl RecordingProfile {
+    /// The profile behind one of the quality levels. These are plain MP4,
+    /// the only container Windows' native capture writes, so a crash mid-game
+    /// loses the recording (see `recovery`).
+    pub fn preset(quality: RecordingQuality) -> Self {
+        Self {
+            codec: VideoCodec::H264,
+            container: Container::Mp4,
+            rate_control: RateControl::Vbr,
+            bitrate: quality.bitrate(),
+            qp: 23,
+            fps: 60,
+            output_height: None,
+            encoder: EncoderKind::Auto,
+        }
+    }
+
+    /// Check the profile makes sense and that a backend can record it
+    pub fn validate(&self, capabilities: &BackendCapabilities) -> Result<(), Error> {
+        let unsupported = |what: String| {
+            Err(Error::UnsupportedRecordingProfile(format!(
+                "{} ({} recorder)",
+                what, capabilities.name
+            )))
+        };
+
+        if self.fps == 0 || self.fps > capabilities.max_fps {
+            return unsupported(format!(
+                "{} fps is outside 1-{}",
+                self.fps, capabilities.max_fps
+            ));
+        }
+
+        match self.rate_control {
+            RateControl::Cbr | RateControl::Vbr if self.bitrate == 0 => {
+                return unsupported("a bitrate is required".to_string());
+            }
+            RateControl::Cqp if self.qp > 51 => {
+                return unsupported(format!("QP {} is outside 0-51", self.qp));
+            }
+            _ => {}
+        }
+        if !capabilities.rate_controls.contains(&self.rate_control) {
+            return unsupported(format!(
+                "{:?} rate control isn't supported",
+                self.rate_control
+            ));
+        }
+
+        if let Some(height) = self.output_height {
+            if !capabilities.scaling {
+                return unsupported("scaling isn't supported".to_string());
+            }
+            if height < 144 || height % 2 != 0 {
+                return unsupported(format!(
+                    "output height {} must be even and at least 144",
+                    height
+                ));
+            }
+        }
+
+        if !capabilities.containers.contains(&self.container) {
+            return unsupported(format!("{:?} container isn't supported", self.container));
+        }
+
+        let codecs: Vec<VideoCodec> = match self.encoder {
+            EncoderKind::Software => capabilities.software_codecs.clone(),
+            EncoderKind::Hardware => capabilities.hardware_codecs.clone(),
+            EncoderKind::Auto => capabilities
+                .software_codecs
+                .iter()
+                .chain(&capabilities.hardware_codecs)
+                .copied()
+                .collect(),
+        };
+        if !codecs.contains(&self.codec) {
+            return unsupported(format!(
+                "{:?} can't be encoded with a {:?} encoder",
+                self.codec, self.encoder
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// One-line description for logs
+    pub fn summary(&self) -> String {
+        let rate = match self.rate_control {
+            RateControl::Cqp => format!("CQP {}", self.qp),
+            rc => format!("{:?} {} Mbps", rc, self.bitrate / 1_000_000),
+        };
+        let size = self
+            .output_height
+            .map(|h| format!("{}p", h))
+            .unwrap_or_else(|| "source size".to_string());
+        format!(
+            "{:?}/{:?}, {}, {} fps, {}, {:?} encoder",
+            self.codec, self.container, rate, self.fps, size, self.encoder
+        )
+    }
+}
+
+impl Default for RecordingProfile {
+    fn default() -> Self {
+        Self::preset(RecordingQuality::default())
+    }
+}
+
+impl From<RecordingQuality> for RecordingProfile {
+    fn from(quality: RecordingQuality) -> Self {
+        Self::preset(quality)
+    }
+}
+
+/// What a recorder backend is able to encode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendCapabilities {
+    pub name: String,
+    /// Codecs available from a software encoder; empty if there is none
+    pub software_codecs: Vec<VideoCodec>,
+    /// Codecs available from a hardware encoder; empty if there is none
+    pub hardware_codecs: Vec<VideoCodec>,
+    pub containers: Vec<Container>,
+    pub rate_controls: Vec<RateControl>,
+    pub max_fps: u32,
+    /// Whether the output can be scaled to a different height
+    pub scaling: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn native_like() -> BackendCapabilities {
+        BackendCapabilities {
+            name: "test".to_string(),
+            software_codecs: vec![],
+            hardware_codecs: vec![VideoCodec::H264, VideoCodec::Hevc],
+            containers: vec![Container::Mp4],
+            rate_controls: vec![RateControl::Vbr],
+            max_fps: 60,
+            scaling: false,
+        }
+    }
+
+    #[test]
+    fn test_presets_keep_quality_bitrates() {
+        for quality in [
+            RecordingQuality::Low,
+            RecordingQuality::Medium,
+            RecordingQuality::High,
+            RecordingQuality::Ultra,
+        ] {
+            let profile = RecordingProfile::from(quality);
+            assert_eq!(profile.bitrate, quality.bitrate());
+            assert!(profile.validate(&native_like()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rejects_what_the_backend_cant_do() {
+        let caps = native_like();
+        let base = RecordingProfile::default();
+
+        let software = RecordingProfile {
+            encoder: EncoderKind::Software,
+            ..base.clone()
+        };
+        let av1 = RecordingProfile {
+            codec: VideoCodec::Av1,
+            ..base.clone()
+        };
+        let scaled = RecordingProfile {
+            output_height: Some(720),
+            ..base.clone()
+        };
+        let mkv = RecordingProfile {
+            container: Container::Mkv,
+            ..base.clone()
+        };
+        let fast = RecordingProfile {
+            fps: 120,
+            ..base.clone()
+        };
+
+        for profile in [software, av1, scaled, mkv, fast] {
+            assert!(profile.validate(&caps).is_err(), "{}", profile.summary());
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// Repairing recordings cut off by a crash
+//
+// While a video is being recorded an `<video>.inprogress` marker sits next to
+// it, and it's removed once the recorder has finished the file. A marker still
+// there at startup means the app or the recorder died mid-recording. The video
+// and any parts left from pausing are remuxed so they get a proper index, then
+// joined and named after their `.slp` so they're listed with their game.
+//
+// Fragmented MP4 and MKV survive this; a plain MP4 has no index until it's
+// finished, so FFmpeg can't read it and it's set aside as `.damaged`.
+
+use super::parts::PartedOutput;
+use super::segmented::SegmentManifest;
+use crate::clip_markers::ClipMarkerFile;
+use crate::commands::errors::Error;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MARKER_EXTENSION: &str = "inprogress";
+
+/// Contents of the `.inprogress` marker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InProgressMarker {
+    pub video_path: String,
+    /// The game being recorded, for auto-recordings
+    pub slp_path: Option<String>,
+    pub started_at: String,
+}
+
+/// `<video>.inprogress`
+pub fn marker_path(video_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", video_path, MARKER_EXTENSION))
+}
+
+/// Note that `video_path` is being recorded
+pub fn write_marker(video_path: &str, slp_path: Option<&str>) -> Result<(), Error> {
+    let marker = InProgressMarker {
+        video_path: video_path.to_string(),
+        slp_path: slp_path.map(str::to_string),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let json = serde_json::to_string_pretty(&marker)
+        .map_err(|e| Error::RecordingFailed(format!("Failed to serialize marker: {}", e)))?;
+    std::fs::write(marker_path(video_path), json)?;
+    Ok(())
+}
+
+/// The recorder finished `video_path`, so there's nothing to recover
+pub fn clear_marker(video_path: &str) {
+    let path = marker_path(video_path);
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            log::warn!("Failed to remove recording marker {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Markers left in `dir` by recordings that never finished
+pub fn find_orphans(dir: &Path) -> Vec<InProgressMarker> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut markers: Vec<InProgressMarker> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some(MARKER_EXTENSION))
+        .filter_map(|path| match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(marker) => Some(marker),
+                Err(e) => {
+                    log::warn!("Ignoring unreadable recording marker {:?}: {}", path, e);
+                    None
+                }
+            },
+            Err(_) => None,
+        })
+        .collect();
+    markers.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    markers
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    /// The video plays again
+    Repaired,
+    /// Nothing could be read from the video; it was renamed to `.damaged`
+    Unrecoverable,
+    /// The video was never written
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveredRecording {
+    pub video_path: String,
+    pub slp_path: Option<String>,
+    pub outcome: RecoveryOutcome,
+}
+
+/// Repair the recording behind an orphaned marker, and remove the marker
+pub fn recover(marker: &InProgressMarker) -> RecoveredRecording {
+    log::info!("🩹 Recovering interrupted recording {}", marker.video_path);
+
+    let outcome = match SegmentManifest::load(&marker.video_path) {
+        Some(manifest) => repair_segments(&manifest),
+        None => repair_parts(&marker.video_path),
+    };
+    let video_path = match outcome {
+        RecoveryOutcome::Repaired => pair_with_slp(marker),
+        _ => marker.video_path.clone(),
+    };
+    clear_marker(&marker.video_path);
+
+    RecoveredRecording {
+        video_path,
+        slp_path: marker.slp_path.clone(),
+        outcome,
+    }
+}
+
+/// Repair each file of a segmented recording
+fn repair_segments(manifest: &SegmentManifest) -> RecoveryOutcome {
+    let outcomes: Vec<RecoveryOutcome> = manifest
+        .segments
+        .iter()
+        .map(|segment| repair_parts(&segment.path))
+        .collect();
+    if outcomes.contains(&RecoveryOutcome::Repaired) {
+        RecoveryOutcome::Repaired
+    } else if outcomes.contains(&RecoveryOutcome::Unrecoverable) {
+        RecoveryOutcome::Unrecoverable
+    } else {
+        RecoveryOutcome::Missing
+    }
+}
+
+/// Remux every part that can be read and join them into the video
+fn repair_parts(video_path: &str) -> RecoveryOutcome {
+    let output = PartedOutput::existing(video_path);
+    let existing: Vec<&PathBuf> = output.parts().iter().filter(|p| p.exists()).collect();
+    if existing.is_empty() {
+        return RecoveryOutcome::Missing;
+    }
+
+    let mut repaired_any = false;
+    for part in existing {
+        match remux_in_place(part) {
+            Ok(()) => repaired_any = true,
+            Err(e) => {
+                log::warn!("⚠️ Could not repair {:?}: {:?}", part, e);
+                set_aside(part);
+            }
+        }
+    }
+    if !repaired_any {
+        return RecoveryOutcome::Unrecoverable;
+    }
+
+    // Parts set aside are skipped by the join
+    match output.finish() {
+        Ok(_) => RecoveryOutcome::Repaired,
+        Err(e) => {
+            log::error!("Failed to join recovered parts of {}: {:?}", video_path, e);
+            RecoveryOutcome::Unrecoverable
+        }
+    }
+}
+
+fn remux_in_place(path: &Path) -> Result<(), Error> {
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+    let repaired = path.with_extension(format!("repaired.{}", extension));
+    let input = path.to_string_lossy().to_string();
+    let output = repaired.to_string_lossy().to_string();
+
+    if let Err(e) = crate::clip_processor::remux_video(&input, &output) {
+        let _ = std::fs::remove_file(&repaired);
+        return Err(e);
+    }
+    std::fs::rename(&repaired, path)?;
+    Ok(())
+}
+
+/// Keep an unreadable file, but out of the recordings list
+fn set_aside(path: &Path) {
+    let damaged = PathBuf::from(format!("{}.damaged", path.to_string_lossy()));
+    if let Err(e) = std::fs::rename(path, &damaged) {
+        log::warn!("Failed to set aside {:?}: {}", path, e);
+    }
+}
+
+/// Recordings are matched to replays by name, so make sure the video is named
+/// after its `.slp`
+fn pair_with_slp(marker: &InProgressMarker) -> String {
+    let Some(slp_path) = marker.slp_path.as_deref() else {
+        return marker.video_path.clone();
+    };
+    let video = Path::new(&marker.video_path);
+    let Some(paired) = paired_video_path(video, Path::new(slp_path)) else {
+        return marker.video_path.clone();
+    };
+    if paired == video || paired.exists() {
+        return marker.video_path.clone();
+    }
+
+    match std::fs::rename(video, &paired) {
+        Ok(()) => {
+            let paired = paired.to_string_lossy().to_string();
+            // Clip markers made before the crash follow the video
+            let markers = ClipMarkerFile::path_for(&marker.video_path);
+            if markers.exists() {
+                if let Err(e) = std::fs::rename(&markers, ClipMarkerFile::path_for(&paired)) {
+                    log::warn!("Failed to move clip markers of {:?}: {}", video, e);
+                }
+            }
+            paired
+        }
+        Err(e) => {
+            log::warn!("Failed to rename {:?} after its replay: {}", video, e);
+            marker.video_path.clone()
+        }
+    }
+}
+
+/// `<video dir>/<slp stem>.<video extension>`
+fn paired_video_path(video: &Path, slp: &Path) -> Option<PathBuf> {
+    let stem = slp.file_stem()?.to_str()?;
+    let extension = video.extension()?.to_str()?;
+    Some(video.with_file_name(format!("{}.{}", stem, extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_markers_of_unfinished_recordings() {
+        let dir = std::env::temp_dir().join("buckwheat_recovery_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let finished = dir.join("Game_1.mp4").to_string_lossy().to_string();
+        let crashed = dir.join("Game_2.mkv").to_string_lossy().to_string();
+        write_marker(&finished, Some("/slp/Game_1.slp")).unwrap();
+        write_marker(&crashed, Some("/slp/Game_2.slp")).unwrap();
+        clear_marker(&finished);
+
+        let orphans = find_orphans(&dir);
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].video_path, crashed);
+        assert_eq!(orphans[0].slp_path.as_deref(), Some("/slp/Game_2.slp"));
+
+        // Nothing was written before the crash
+        let recovered = recover(&orphans[0]);
+        assert_eq!(recovered.outcome, RecoveryOutcome::Missing);
+        assert!(find_orphans(&dir).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_video_is_named_after_its_replay() {
+        assert_eq!(
+            paired_video_path(
+                Path::new("/videos/recording_20240101.mkv"),
+                Path::new("/slp/Game_20240101T120000.slp")
+            ),
+            Some(PathBuf::from("/videos/Game_20240101T120000.mkv"))
+        );
+    }
+}
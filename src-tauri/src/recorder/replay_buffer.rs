@@ -0,0 +1,411 @@
+// Instant replay: keep the last N seconds of gameplay in a ring of short segments
+//
+// The buffer records continuously into a temp directory, starting a new
+// segment every `segment_seconds` and deleting segments that fall out of the
+// window. Saving stitches the buffered segments into a single video.
+//
+// Any recorder can buffer: it's stopped and restarted for each segment, which
+// loses a few frames at every boundary. When the recorder would capture with
+// FFmpeg anyway, a single FFmpeg whose segment muxer starts each file can take
+// its place, and nothing is lost between segments.
+
+use super::ffmpeg::{HardwareEncoder, SegmentedCapture};
+use super::segments::{self, Segment, SegmentRotator};
+use super::{Recorder, RecordingProfile};
+use crate::commands::errors::Error;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default length of footage kept in the buffer
+pub const DEFAULT_BUFFER_SECONDS: u64 = 30;
+/// Segment length; the buffer holds up to this much more than asked for
+pub const DEFAULT_SEGMENT_SECONDS: u64 = 5;
+
+/// Name segment files start with
+const SEGMENT_PREFIX: &str = "buffer";
+
+#[derive(Debug, Clone)]
+pub struct ReplayBufferConfig {
+    pub buffer_seconds: u64,
+    pub segment_seconds: u64,
+    /// Where segments are written while buffering
+    pub dir: PathBuf,
+    pub profile: RecordingProfile,
+}
+
+/// How the segments are being recorded
+enum Capture {
+    /// The recorder, restarted for each segment
+    Rotator(Box<SegmentRotator>),
+    /// FFmpeg's segment muxer
+    Muxer(SegmentedCapture),
+}
+
+pub struct ReplayBuffer {
+    config: ReplayBufferConfig,
+    /// The recorder while it's not buffering; the rotator holds it while it is
+    idle: Option<Box<dyn Recorder + Send>>,
+    /// Encoder for FFmpeg's segment muxer, when it records instead
+    muxer: Option<HardwareEncoder>,
+    capture: Option<Capture>,
+    /// Finished segments still in the buffer, oldest first
+    finished: VecDeque<Segment>,
+    /// How many segments FFmpeg's muxer has finished, including those pruned since
+    listed: usize,
+    /// Snapshots taken, to name their directories
+    snapshots: u32,
+}
+
+impl ReplayBuffer {
+    pub fn new(recorder: Box<dyn Recorder + Send>, config: ReplayBufferConfig) -> Self {
+        Self {
+            config,
+            idle: Some(recorder),
+            muxer: None,
+            capture: None,
+            finished: VecDeque::new(),
+            listed: 0,
+            snapshots: 0,
+        }
+    }
+
+    /// Record with FFmpeg's segment muxer rather than the recorder, falling
+    /// back to the recorder if FFmpeg can't start
+    pub fn with_segment_muxer(mut self, hardware: HardwareEncoder) -> Self {
+        self.muxer = Some(hardware);
+        self
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Path segment `index` is written to, e.g. `<dir>/buffer_00003.mp4`
+    fn segment_path(&self, index: usize) -> PathBuf {
+        self.config.dir.join(format!(
+            "{}_{:05}.{}",
+            SEGMENT_PREFIX,
+            index,
+            self.config.profile.container.extension()
+        ))
+    }
+
+    /// FFmpeg's list of finished segments
+    fn list_path(&self) -> PathBuf {
+        self.config.dir.join(format!("{}.csv", SEGMENT_PREFIX))
+    }
+
+    pub fn start(&mut self) -> Result<(), Error> {
+        if self.capture.is_some() {
+            return Err(Error::RecordingFailed("Already buffering".to_string()));
+        }
+
+        log::info!(
+            "⏺️  Starting replay buffer ({}s in {}s segments) in {:?}",
+            self.config.buffer_seconds,
+            self.config.segment_seconds,
+            self.config.dir
+        );
+        std::fs::create_dir_all(&self.config.dir).map_err(|e| {
+            Error::RecordingFailed(format!("Failed to create replay buffer directory: {}", e))
+        })?;
+
+        if let Some(hardware) = self.muxer {
+            match self.start_muxer(hardware) {
+                Ok(capture) => {
+                    self.capture = Some(Capture::Muxer(capture));
+                    return Ok(());
+                }
+                Err(e) => log::warn!(
+                    "⚠️ FFmpeg couldn't segment the capture ({}), using the recorder",
+                    e
+                ),
+            }
+        }
+
+        let recorder = self
+            .idle
+            .take()
+            .ok_or_else(|| Error::RecordingFailed("Already buffering".to_string()))?;
+        let mut rotator = SegmentRotator::new(
+            recorder,
+            &self.config.dir,
+            SEGMENT_PREFIX,
+            self.config.profile.clone(),
+        );
+        if let Err(e) = rotator.start() {
+            self.idle = Some(rotator.into_recorder());
+            return Err(e);
+        }
+        self.capture = Some(Capture::Rotator(Box::new(rotator)));
+        Ok(())
+    }
+
+    fn start_muxer(&self, hardware: HardwareEncoder) -> Result<SegmentedCapture, Error> {
+        let pattern = self.config.dir.join(format!(
+            "{}_%05d.{}",
+            SEGMENT_PREFIX,
+            self.config.profile.container.extension()
+        ));
+        SegmentedCapture::start(
+            &self.config.profile,
+            hardware,
+            self.config.segment_seconds,
+            &pattern.to_string_lossy(),
+            &self.list_path().to_string_lossy(),
+        )
+    }
+
+    /// Pick up the segments finished since the last call, starting the next
+    /// one if the recorder is doing the segmenting, and forget those that have
+    /// fallen out of the buffer. Call this regularly.
+    pub fn tick(&mut self) -> Result<(), Error> {
+        match &mut self.capture {
+            Some(Capture::Rotator(rotator)) => {
+                let segment_length = Duration::from_secs(self.config.segment_seconds);
+                if rotator
+                    .current_elapsed()
+                    .is_some_and(|elapsed| elapsed >= segment_length)
+                {
+                    rotator.rotate()?;
+                }
+                self.finished.extend(rotator.drain());
+            }
+            Some(Capture::Muxer(capture)) => {
+                if capture.has_exited() {
+                    return Err(Error::RecordingFailed(
+                        "FFmpeg stopped buffering".to_string(),
+                    ));
+                }
+                let listed = segments::read_segment_list(&self.list_path(), &self.config.dir);
+                if listed.len() > self.listed {
+                    let count = listed.len();
+                    self.finished.extend(listed.into_iter().skip(self.listed));
+                    self.listed = count;
+                }
+            }
+            None => {}
+        }
+
+        let keep = Duration::from_secs(self.config.buffer_seconds);
+        remove_segment_files(segments::prune_segments(&mut self.finished, keep));
+        Ok(())
+    }
+
+    /// The buffered segments oldest first, up to now. They're linked into a
+    /// directory of their own, so pruning can't delete them while they're
+    /// being saved; `save_segments` cleans it up.
+    pub fn snapshot(&mut self) -> Result<Vec<Segment>, Error> {
+        // The recorder's segment isn't readable until it's finished, while
+        // the muxer's can be read as it's written
+        let mut current = None;
+        match &mut self.capture {
+            Some(Capture::Rotator(rotator)) => {
+                rotator.rotate()?;
+            }
+            Some(Capture::Muxer(_)) => {
+                let path = self.segment_path(self.listed);
+                current = path.exists().then_some(Segment {
+                    index: self.listed as u32,
+                    path,
+                    // Unknown until FFmpeg finishes it
+                    duration: Duration::ZERO,
+                });
+            }
+            None => {}
+        }
+        self.tick()?;
+
+        let mut buffered: Vec<Segment> = self.finished.iter().cloned().chain(current).collect();
+        let dir = self.config.dir.join(format!("snapshot_{}", self.snapshots));
+        self.snapshots += 1;
+        std::fs::create_dir_all(&dir)?;
+        for segment in &mut buffered {
+            let name = segment.path.file_name().unwrap_or_default();
+            let linked = dir.join(name);
+            // Copy where links aren't supported, e.g. FAT32 temp drives
+            if std::fs::hard_link(&segment.path, &linked).is_err() {
+                std::fs::copy(&segment.path, &linked)?;
+            }
+            segment.path = linked;
+        }
+        Ok(buffered)
+    }
+
+    /// Stop buffering and delete every segment
+    pub fn stop(&mut self) -> Result<(), Error> {
+        let stopped = match self.capture.take() {
+            Some(Capture::Rotator(mut rotator)) => {
+                let stopped = rotator.stop().map(|_| ());
+                self.idle = Some(rotator.into_recorder());
+                stopped
+            }
+            Some(Capture::Muxer(capture)) => capture.stop(),
+            None => Ok(()),
+        };
+        self.finished.clear();
+        self.listed = 0;
+        clear_buffer_dir(&self.config.dir);
+        log::info!("⏹️  Replay buffer stopped");
+        stopped
+    }
+}
+
+/// Write a snapshot's segments to `output_path`, then remove them. This runs
+/// FFmpeg, so keep it off the async runtime and away from the buffer's lock.
+pub fn save_segments(segments: &[Segment], output_path: &str) -> Result<String, Error> {
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return Err(Error::RecordingFailed("Replay buffer is empty".to_string()));
+    };
+
+    let inputs: Vec<String> = segments
+        .iter()
+        .map(|s| s.path.to_string_lossy().to_string())
+        .collect();
+    let saved = crate::clip_processor::concat_videos(&inputs, output_path);
+
+    if let Some(dir) = first.path.parent() {
+        clear_buffer_dir(dir);
+    }
+    saved?;
+
+    log::info!(
+        "💾 Saved buffered segments {}..={} to {}",
+        first.index,
+        last.index,
+        output_path
+    );
+    Ok(output_path.to_string())
+}
+
+fn remove_segment_files(segments: Vec<Segment>) {
+    for segment in segments {
+        if let Err(e) = std::fs::remove_file(&segment.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to delete buffer segment {:?}: {}", segment.path, e);
+            }
+        }
+    }
+}
+
+/// Temp directory segments are buffered in
+pub fn default_buffer_dir() -> PathBuf {
+    std::env::temp_dir().join("buckwheat_replay_buffer")
+}
+
+/// Remove leftovers from a previous run (e.g. after a crash)
+pub fn clear_buffer_dir(dir: &Path) {
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            log::warn!("Failed to clear replay buffer directory {:?}: {}", dir, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::mock::MockRecorder;
+    use crate::recorder::{BackendCapabilities, RecorderStats, RecordingQuality};
+
+    /// The mock, writing a stand-in file for every segment it records
+    struct SegmentWriter(MockRecorder);
+
+    impl Recorder for SegmentWriter {
+        fn start_recording(&mut self, path: &str, profile: &RecordingProfile) -> Result<(), Error> {
+            self.0.start_recording(path, profile)
+        }
+
+        fn stop_recording(&mut self) -> Result<String, Error> {
+            let path = self.0.stop_recording()?;
+            std::fs::write(&path, b"segment")?;
+            Ok(path)
+        }
+
+        fn is_recording(&self) -> bool {
+            self.0.is_recording()
+        }
+
+        fn pause(&mut self) -> Result<(), Error> {
+            self.0.pause()
+        }
+
+        fn resume(&mut self) -> Result<(), Error> {
+            self.0.resume()
+        }
+
+        fn is_paused(&self) -> bool {
+            self.0.is_paused()
+        }
+
+        fn stats(&self) -> RecorderStats {
+            self.0.stats()
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            self.0.capabilities()
+        }
+    }
+
+    fn buffer(name: &str, buffer_seconds: u64, segment_seconds: u64) -> ReplayBuffer {
+        let dir = std::env::temp_dir().join(name);
+        clear_buffer_dir(&dir);
+        ReplayBuffer::new(
+            Box::new(SegmentWriter(MockRecorder::without_video())),
+            ReplayBufferConfig {
+                buffer_seconds,
+                segment_seconds,
+                dir,
+                profile: RecordingQuality::Low.into(),
+            },
+        )
+    }
+
+    #[test]
+    fn test_old_segments_fall_out_of_the_buffer() {
+        // A zero second buffer keeps only the newest finished segment
+        let mut buffer = buffer("buckwheat_replay_buffer_prune_test", 0, 0);
+        buffer.start().unwrap();
+        for _ in 0..5 {
+            buffer.tick().unwrap();
+        }
+
+        let kept: Vec<u32> = buffer.finished.iter().map(|s| s.index).collect();
+        assert_eq!(kept, vec![4]);
+        assert!(!buffer.segment_path(0).exists());
+        assert!(buffer.segment_path(4).exists());
+
+        buffer.stop().unwrap();
+        assert!(!buffer.is_running());
+        assert!(!buffer.config.dir.exists());
+    }
+
+    #[test]
+    fn test_snapshot_keeps_buffering() {
+        let mut buffer = buffer("buckwheat_replay_buffer_snapshot_test", 30, 5);
+        buffer.start().unwrap();
+
+        // The segment being recorded is finished so it can be saved
+        let first = buffer.snapshot().unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(buffer.is_running());
+
+        let second = buffer.snapshot().unwrap();
+        let indices: Vec<u32> = second.iter().map(|s| s.index).collect();
+        assert_eq!(indices, vec![0, 1]);
+
+        // The snapshot outlives the buffer pruning its segments
+        buffer.config.buffer_seconds = 0;
+        buffer.config.segment_seconds = 0;
+        buffer.tick().unwrap();
+        buffer.tick().unwrap();
+        assert!(!buffer.segment_path(0).exists());
+        assert!(second.iter().all(|s| s.path.exists()));
+
+        // The recorder is back to record again
+        buffer.stop().unwrap();
+        buffer.start().unwrap();
+        buffer.stop().unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+// Long recordings split into files of a bounded length or size
+//
+// A segmented recording rolls over to a new file once the current one has run
+// for the configured time or grown to the configured size. The segments are
+// named `<stem>_00000.<ext>`, `<stem>_00001.<ext>`, ... after the recording's
+// path, and listed in `<video>.segments.json` so the library can show them as
+// one recording and clips can span a boundary.
+
+use super::segments::SegmentRotator;
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Suffix of the manifest written next to a segmented recording's path
+pub const MANIFEST_SUFFIX: &str = ".segments.json";
+
+/// When to start the next segment; whichever is reached first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SegmentLimits {
+    pub max_duration: Option<Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+impl SegmentLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_duration.is_none() && self.max_bytes.is_none()
+    }
+
+    fn reached(&self, elapsed: Duration, bytes: u64) -> bool {
+        self.max_duration.is_some_and(|max| elapsed >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max)
+    }
+}
+
+/// One file of a segmented recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestSegment {
+    pub path: String,
+    /// Seconds into the whole recording the segment starts
+    pub start_seconds: f64,
+    /// Unknown while the segment is being recorded, or if it was cut off by a crash
+    pub duration_seconds: Option<f64>,
+}
+
+/// Contents of the `.segments.json` manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    /// The recording as a whole. No file exists at this path, unless the
+    /// recording was split by losing the game window: then it's the first segment
+    pub video_path: String,
+    pub segments: Vec<ManifestSegment>,
+}
+
+/// Part of one segment, as cut from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentRange {
+    pub path: String,
+    /// Seconds into the segment
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+impl SegmentManifest {
+    /// `<video>.segments.json`
+    pub fn path_for(video_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}{}", video_path, MANIFEST_SUFFIX))
+    }
+
+    /// The manifest of `video_path`, if it's a segmented recording
+    pub fn load(video_path: &str) -> Option<Self> {
+        Self::read(&Self::path_for(video_path))
+    }
+
+    pub fn read(manifest_path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(manifest_path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                log::warn!("Ignoring unreadable manifest {:?}: {}", manifest_path, e);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self) -> Result<PathBuf, Error> {
+        let path = Self::path_for(&self.video_path);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::RecordingFailed(format!("Failed to serialize manifest: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// Length of the whole recording, if every segment's is known
+    pub fn duration_seconds(&self) -> Option<f64> {
+        let last = self.segments.last()?;
+        Some(last.start_seconds + last.duration_seconds?)
+    }
+
+    /// The pieces of each segment covering `start..start + duration` of the
+    /// whole recording, in order
+    pub fn locate(&self, start: f64, duration: f64) -> Vec<SegmentRange> {
+        let end = start + duration;
+        self.segments
+            .iter()
+            .filter_map(|segment| {
+                let segment_end = segment
+                    .duration_seconds
+                    .map_or(f64::INFINITY, |d| segment.start_seconds + d);
+                let from = start.max(segment.start_seconds);
+                let to = end.min(segment_end);
+                // Skip slivers from rounding at the boundary
+                (to - from > 0.01).then(|| SegmentRange {
+                    path: segment.path.clone(),
+                    start_seconds: from - segment.start_seconds,
+                    duration_seconds: to - from,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Records into segments with the recorder it wraps
+pub struct SegmentedRecorder {
+    limits: SegmentLimits,
+    /// The wrapped recorder between recordings; the rotator holds it while recording
+    idle: Option<Box<dyn Recorder + Send>>,
+    rotator: Option<SegmentRotator>,
+    manifest: Option<SegmentManifest>,
+    /// Stats of the segments finished so far
+    finished_stats: RecorderStats,
+}
+
+impl SegmentedRecorder {
+    pub fn new(recorder: Box<dyn Recorder + Send>, limits: SegmentLimits) -> Self {
+        Self {
+            limits,
+            idle: Some(recorder),
+            rotator: None,
+            manifest: None,
+            finished_stats: RecorderStats::default(),
+        }
+    }
+
+    /// Bring the manifest up to date with the rotator and write it out
+    fn write_manifest(&mut self) {
+        let (Some(rotator), Some(manifest)) = (&self.rotator, self.manifest.as_mut()) else {
+            return;
+        };
+
+        let mut start_seconds = 0.0;
+        manifest.segments.clear();
+        for segment in rotator.segments() {
+            let duration = segment.duration.as_secs_f64();
+            manifest.segments.push(ManifestSegment {
+                path: segment.path.to_string_lossy().to_string(),
+                start_seconds,
+                duration_seconds: Some(duration),
+            });
+            start_seconds += duration;
+        }
+        if let Some(path) = rotator.current_path() {
+            manifest.segments.push(ManifestSegment {
+                path: path.to_string_lossy().to_string(),
+                start_seconds,
+                duration_seconds: None,
+            });
+        }
+
+        if let Err(e) = manifest.write() {
+            log::error!("Failed to write segment manifest: {:?}", e);
+        }
+    }
+}
+
+impl Recorder for SegmentedRecorder {
+    fn start_recording(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<(), Error> {
+        let Some(recorder) = self.idle.take() else {
+            return Err(Error::RecordingFailed("Already recording".to_string()));
+        };
+
+        let path = Path::new(output_path);
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let prefix = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("recording");
+        let mut rotator = SegmentRotator::new(recorder, dir, prefix, profile.clone());
+        if let Err(e) = rotator.start() {
+            self.idle = Some(rotator.into_recorder());
+            return Err(e);
+        }
+
+        log::info!(
+            "🎞️ Recording {} in segments ({:?})",
+            output_path,
+            self.limits
+        );
+        self.rotator = Some(rotator);
+        self.manifest = Some(SegmentManifest {
+            video_path: output_path.to_string(),
+            segments: Vec::new(),
+        });
+        self.finished_stats = RecorderStats::default();
+        self.write_manifest();
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<String, Error> {
+        let Some(rotator) = self.rotator.as_mut() else {
+            return Err(Error::RecordingFailed("Not recording".to_string()));
+        };
+
+        let stopped = rotator.stop();
+        self.write_manifest();
+        if let Some(rotator) = self.rotator.take() {
+            self.idle = Some(rotator.into_recorder());
+        }
+        stopped?;
+
+        let manifest = self
+            .manifest
+            .take()
+            .ok_or_else(|| Error::RecordingFailed("Not recording".to_string()))?;
+        log::info!(
+            "🎞️ Recorded {} in {} segment(s)",
+            manifest.video_path,
+            manifest.segments.len()
+        );
+        Ok(manifest.video_path)
+    }
+
+    fn is_recording(&self) -> bool {
+        self.rotator.as_ref().is_some_and(|r| r.is_recording())
+    }
+
+    fn pause(&mut self) -> Result<(), Error> {
+        match self.rotator.as_mut() {
+            Some(rotator) => rotator.pause(),
+            None => Err(Error::RecordingFailed("Not recording".to_string())),
+        }
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        match self.rotator.as_mut() {
+            Some(rotator) => rotator.resume(),
+            None => Err(Error::RecordingFailed("Not recording".to_string())),
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.rotator
+            .as_ref()
+            .is_some_and(|r| r.recorder().is_paused())
+    }
+
+    /// Totals over every segment so far
+    fn stats(&self) -> RecorderStats {
+        let Some(rotator) = &self.rotator else {
+            return RecorderStats::default();
+        };
+
+        self.finished_stats
+            .with_current(&rotator.recorder().stats())
+    }
+
+    /// Start the next segment once the current one has reached a limit
+    fn tick(&mut self) -> Result<(), Error> {
+        let Some(rotator) = self.rotator.as_mut() else {
+            return Ok(());
+        };
+        let current = rotator.recorder().stats();
+        let Some(elapsed) = rotator.current_elapsed() else {
+            return Ok(());
+        };
+        if current.paused || !self.limits.reached(elapsed, current.bytes_written) {
+            return Ok(());
+        }
+
+        let finished = rotator.rotate()?;
+        self.finished_stats.add(&current);
+        if let Some(segment) = finished {
+            log::info!(
+                "🎞️ Segment {} finished after {:.0}s",
+                segment.index,
+                segment.duration.as_secs_f64()
+            );
+        }
+        self.write_manifest();
+        Ok(())
+    }
+
+    fn target_lost(&self) -> bool {
+        self.rotator
+            .as_ref()
+            .is_some_and(|r| r.recorder().target_lost())
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        match (&self.idle, &self.rotator) {
+            (Some(recorder), _) => recorder.capabilities(),
+            (None, Some(rotator)) => rotator.recorder().capabilities(),
+            (None, None) => unreachable!("the recorder is always idle or rotating"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::mock::MockRecorder;
+    use crate::recorder::RecordingQuality;
+
+    fn manifest(durations: &[Option<f64>]) -> SegmentManifest {
+        let mut start_seconds = 0.0;
+        let segments = durations
+            .iter()
+            .enumerate()
+            .map(|(i, duration)| {
+                let segment = ManifestSegment {
+                    path: format!("/videos/Manual_{:05}.mp4", i),
+                    start_seconds,
+                    duration_seconds: *duration,
+                };
+                start_seconds += duration.unwrap_or(0.0);
+                segment
+            })
+            .collect();
+        SegmentManifest {
+            video_path: "/videos/Manual.mp4".to_string(),
+            segments,
+        }
+    }
+
+    #[test]
+    fn test_clip_across_a_boundary_takes_from_both_segments() {
+        let manifest = manifest(&[Some(600.0), Some(600.0), None]);
+
+        let ranges = manifest.locate(590.0, 30.0);
+        assert_eq!(
+            ranges,
+            vec![
+                SegmentRange {
+                    path: "/videos/Manual_00000.mp4".to_string(),
+                    start_seconds: 590.0,
+                    duration_seconds: 10.0,
+                },
+                SegmentRange {
+                    path: "/videos/Manual_00001.mp4".to_string(),
+                    start_seconds: 0.0,
+                    duration_seconds: 20.0,
+                },
+            ]
+        );
+
+        // The last segment runs to the end, however long that is
+        let tail = manifest.locate(1300.0, 30.0);
+        assert_eq!(tail.len(), 1);
+        assert_eq!(tail[0].start_seconds, 100.0);
+        assert_eq!(manifest.duration_seconds(), None);
+    }
+
+    #[test]
+    fn test_rolls_over_at_the_time_limit() {
+        let dir = std::env::temp_dir().join("buckwheat_segmented_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video_path = dir.join("Manual.mp4").to_string_lossy().to_string();
+
+        let mut recorder = SegmentedRecorder::new(
+            Box::new(MockRecorder::without_video()),
+            SegmentLimits {
+                max_duration: Some(Duration::from_millis(50)),
+                max_bytes: None,
+            },
+        );
+        recorder
+            .start_recording(&video_path, &RecordingQuality::Low.into())
+            .unwrap();
+        recorder.tick().unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+        recorder.tick().unwrap();
+        assert_eq!(recorder.stop_recording().unwrap(), video_path);
+
+        let manifest = SegmentManifest::load(&video_path).unwrap();
+        assert_eq!(manifest.segments.len(), 2);
+        assert!(manifest.segments[1].path.ends_with("Manual_00001.mp4"));
+        assert!(manifest.segments[1].start_seconds >= 0.05);
+        assert!(manifest.duration_seconds().is_some());
+
+        // The wrapped recorder can record again
+        assert!(!recorder.is_recording());
+        recorder
+            .start_recording(&video_path, &RecordingQuality::Low.into())
+            .unwrap();
+        recorder.stop_recording().unwrap();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
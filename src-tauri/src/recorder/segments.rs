@@ -0,0 +1,235 @@
+// Splits one continuous capture into a sequence of short files
+//
+// The backends only know how to record a single file, so segmenting is done by
+// stopping and immediately restarting the same recorder with the next path.
+
+use super::stats::RecordingClock;
+use super::{Recorder, RecordingProfile};
+use crate::commands::errors::Error;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A finished segment file
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub index: u32,
+    pub path: PathBuf,
+    pub duration: Duration,
+}
+
+pub struct SegmentRotator {
+    recorder: Box<dyn Recorder + Send>,
+    dir: PathBuf,
+    prefix: String,
+    profile: RecordingProfile,
+    next_index: u32,
+    /// Index, path and recorded time of the segment being recorded
+    current: Option<(u32, PathBuf, RecordingClock)>,
+    finished: VecDeque<Segment>,
+}
+
+impl SegmentRotator {
+    pub fn new(
+        recorder: Box<dyn Recorder + Send>,
+        dir: &Path,
+        prefix: &str,
+        profile: RecordingProfile,
+    ) -> Self {
+        Self {
+            recorder,
+            dir: dir.to_path_buf(),
+            prefix: prefix.to_string(),
+            profile,
+            next_index: 0,
+            current: None,
+            finished: VecDeque::new(),
+        }
+    }
+
+    /// Path segment `index` is written to, e.g. `<dir>/<prefix>_00003.mp4`
+    pub fn segment_path(&self, index: u32) -> PathBuf {
+        self.dir.join(format!(
+            "{}_{:05}.{}",
+            self.prefix,
+            index,
+            self.profile.container.extension()
+        ))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// How long the current segment has been recording, leaving out pauses
+    pub fn current_elapsed(&self) -> Option<Duration> {
+        self.current.as_ref().map(|(_, _, clock)| clock.elapsed())
+    }
+
+    /// Path of the segment being recorded
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|(_, path, _)| path.as_path())
+    }
+
+    pub fn recorder(&self) -> &(dyn Recorder + Send) {
+        self.recorder.as_ref()
+    }
+
+    /// Give back the recorder once done with segmenting
+    pub fn into_recorder(self) -> Box<dyn Recorder + Send> {
+        self.recorder
+    }
+
+    /// Finished segments, oldest first
+    pub fn segments(&self) -> &VecDeque<Segment> {
+        &self.finished
+    }
+
+    /// Start recording the next segment
+    pub fn start(&mut self) -> Result<(), Error> {
+        if self.current.is_some() {
+            return Err(Error::RecordingFailed("Already recording".to_string()));
+        }
+
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            Error::RecordingFailed(format!("Failed to create segment directory: {}", e))
+        })?;
+
+        let index = self.next_index;
+        let path = self.segment_path(index);
+        self.recorder
+            .start_recording(&path.to_string_lossy(), &self.profile)?;
+        self.next_index += 1;
+        self.current = Some((index, path, RecordingClock::start()));
+        Ok(())
+    }
+
+    /// Pause the current segment; the paused time doesn't count towards its length
+    pub fn pause(&mut self) -> Result<(), Error> {
+        let Some((_, _, clock)) = self.current.as_mut() else {
+            return Err(Error::RecordingFailed("Not recording".to_string()));
+        };
+        self.recorder.pause()?;
+        clock.pause();
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> Result<(), Error> {
+        let Some((_, _, clock)) = self.current.as_mut() else {
+            return Err(Error::RecordingFailed("Not recording".to_string()));
+        };
+        self.recorder.resume()?;
+        clock.resume();
+        Ok(())
+    }
+
+    /// Finish the current segment, returning it
+    pub fn stop(&mut self) -> Result<Option<Segment>, Error> {
+        let Some((index, path, clock)) = self.current.take() else {
+            return Ok(None);
+        };
+
+        self.recorder.stop_recording()?;
+        let segment = Segment {
+            index,
+            path,
+            duration: clock.elapsed(),
+        };
+        self.finished.push_back(segment.clone());
+        Ok(Some(segment))
+    }
+
+    /// Finish the current segment and immediately start the next one
+    pub fn rotate(&mut self) -> Result<Option<Segment>, Error> {
+        let finished = self.stop()?;
+        self.start()?;
+        Ok(finished)
+    }
+
+    /// Drop the oldest finished segments that aren't needed to cover the last
+    /// `keep` of footage, returning them so the caller can delete their files
+    pub fn prune_to(&mut self, keep: Duration) -> Vec<Segment> {
+        prune_segments(&mut self.finished, keep)
+    }
+
+    /// Take all finished segments, leaving none behind
+    pub fn drain(&mut self) -> Vec<Segment> {
+        self.finished.drain(..).collect()
+    }
+}
+
+/// Drop the oldest of `segments` that aren't needed to cover the last `keep`
+/// of footage, returning them
+pub fn prune_segments(segments: &mut VecDeque<Segment>, keep: Duration) -> Vec<Segment> {
+    let mut total: Duration = segments.iter().map(|s| s.duration).sum();
+    let mut dropped = Vec::new();
+
+    // The newest segment is always kept, however short the window
+    while segments.len() > 1 {
+        let oldest = segments[0].duration;
+        if total.saturating_sub(oldest) < keep {
+            break;
+        }
+        total -= oldest;
+        dropped.extend(segments.pop_front());
+    }
+
+    dropped
+}
+
+/// Segments FFmpeg's segment muxer has finished, from its CSV list of
+/// `name,start,end` lines in `dir`; empty until the first one is done
+pub fn read_segment_list(list_path: &Path, dir: &Path) -> Vec<Segment> {
+    let Ok(list) = std::fs::read_to_string(list_path) else {
+        return Vec::new();
+    };
+
+    list.split_inclusive('\n')
+        // The line being written may be cut short
+        .filter_map(|line| line.strip_suffix('\n'))
+        .filter_map(|line| {
+            let mut fields = line.trim_end_matches('\r').rsplitn(3, ',');
+            let end: f64 = fields.next()?.parse().ok()?;
+            let start: f64 = fields.next()?.parse().ok()?;
+            Some((dir.join(fields.next()?), (end - start).max(0.0)))
+        })
+        .enumerate()
+        .map(|(index, (path, seconds))| Segment {
+            index: index as u32,
+            path,
+            duration: Duration::from_secs_f64(seconds),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_the_segments_ffmpeg_has_finished() {
+        let dir = std::env::temp_dir().join("buckwheat_segment_list_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let list_path = dir.join("buffer.csv");
+        assert!(read_segment_list(&list_path, &dir).is_empty());
+
+        // Half a line from FFmpeg is ignored
+        std::fs::write(
+            &list_path,
+            concat!(
+                "buffer_00000.mp4,0.000000,5.000000\n",
+                "buffer_00001.mp4,5.000000,9.500000\n",
+                "buffer_00002.mp4,9.5",
+            ),
+        )
+        .unwrap();
+        let segments = read_segment_list(&list_path, &dir);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].index, 1);
+        assert_eq!(segments[1].path, dir.join("buffer_00001.mp4"));
+        assert_eq!(segments[1].duration, Duration::from_secs_f64(4.5));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,195 @@
+// A single recording that spans several back-to-back games
+//
+// With post-roll and "continue into next game" enabled, the recorder keeps
+// running across games. The boundaries of each game within the video are kept
+// here and written next to it as `<video>.set.json`.
+
+use super::stats::RecordingClock;
+use crate::commands::errors::Error;
+use crate::slippi::GameEndReason;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Suffix of the sidecar written next to a set's video
+pub const SIDECAR_SUFFIX: &str = ".set.json";
+
+/// Where one game sits within the set's video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetGame {
+    pub slp_path: String,
+    /// Seconds into the video the game's replay appeared
+    pub start_seconds: f64,
+    /// Seconds into the video the game ended, if it has
+    pub end_seconds: Option<f64>,
+    pub end_reason: Option<GameEndReason>,
+}
+
+/// Contents of the `.set.json` sidecar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetManifest {
+    pub video_path: String,
+    pub games: Vec<SetGame>,
+}
+
+impl SetManifest {
+    /// `<video>.set.json`
+    pub fn path_for(video_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}{}", video_path, SIDECAR_SUFFIX))
+    }
+
+    /// The games recorded in `video_path`, if it holds more than one
+    pub fn load(video_path: &str) -> Option<Self> {
+        Self::read(&Self::path_for(video_path))
+    }
+
+    pub fn read(sidecar_path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(sidecar_path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                log::warn!("Ignoring unreadable set {:?}: {}", sidecar_path, e);
+                None
+            }
+        }
+    }
+
+    /// Position of `slp_path` among the set's games, and where it is in the video
+    pub fn game(&self, slp_path: &str) -> Option<(usize, &SetGame)> {
+        self.games
+            .iter()
+            .enumerate()
+            .find(|(_, game)| Path::new(&game.slp_path) == Path::new(slp_path))
+    }
+}
+
+pub struct RecordingSet {
+    /// Paused time between games isn't in the video, so it doesn't count
+    clock: RecordingClock,
+    manifest: SetManifest,
+}
+
+impl RecordingSet {
+    /// Start tracking a recording that begins with `slp_path`
+    pub fn new(video_path: &str, slp_path: &str) -> Self {
+        let mut set = Self {
+            clock: RecordingClock::start(),
+            manifest: SetManifest {
+                video_path: video_path.to_string(),
+                games: Vec::new(),
+            },
+        };
+        set.start_game(slp_path);
+        set
+    }
+
+    pub fn games(&self) -> &[SetGame] {
+        &self.manifest.games
+    }
+
+    fn elapsed_seconds(&self) -> f64 {
+        self.clock.elapsed().as_secs_f64()
+    }
+
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    /// The recording continued into another game
+    pub fn start_game(&mut self, slp_path: &str) {
+        let start_seconds = self.elapsed_seconds();
+        self.manifest.games.push(SetGame {
+            slp_path: slp_path.to_string(),
+            start_seconds,
+            end_seconds: None,
+            end_reason: None,
+        });
+    }
+
+    pub fn end_game(&mut self, slp_path: &str, reason: GameEndReason) {
+        let end_seconds = self.elapsed_seconds();
+        if let Some(game) = self
+            .manifest
+            .games
+            .iter_mut()
+            .rev()
+            .find(|g| g.slp_path == slp_path)
+        {
+            game.end_seconds = Some(end_seconds);
+            game.end_reason = Some(reason);
+        }
+    }
+
+    /// `<video>.set.json`
+    pub fn sidecar_path(&self) -> PathBuf {
+        SetManifest::path_for(&self.manifest.video_path)
+    }
+
+    /// Write the game boundaries next to the video
+    pub fn write_sidecar(&self) -> Result<PathBuf, Error> {
+        let path = self.sidecar_path();
+        let json = serde_json::to_string_pretty(&self.manifest)
+            .map_err(|e| Error::RecordingFailed(format!("Failed to serialize set: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_game_boundaries() {
+        let mut set = RecordingSet::new("/videos/Game_1.mp4", "Game_1.slp");
+        set.end_game("Game_1.slp", GameEndReason::Game);
+        set.start_game("Game_2.slp");
+        set.end_game("Game_2.slp", GameEndReason::Time);
+
+        let games = set.games();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].end_reason, Some(GameEndReason::Game));
+        assert!(games[1].start_seconds >= games[0].end_seconds.unwrap());
+        assert_eq!(games[1].end_reason, Some(GameEndReason::Time));
+        assert_eq!(
+            set.sidecar_path(),
+            PathBuf::from("/videos/Game_1.mp4.set.json")
+        );
+    }
+
+    #[test]
+    fn test_sidecar_finds_each_game() {
+        let dir = std::env::temp_dir().join("buckwheat_set_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Game_1.mp4").to_string_lossy().to_string();
+
+        let mut set = RecordingSet::new(&video, "Game_1.slp");
+        set.end_game("Game_1.slp", GameEndReason::Game);
+        set.start_game("Game_2.slp");
+        set.write_sidecar().unwrap();
+
+        let manifest = SetManifest::load(&video).unwrap();
+        let (index, game) = manifest.game("Game_2.slp").unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(game.start_seconds, set.games()[1].start_seconds);
+        assert!(manifest.game("Game_3.slp").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_paused_time_is_left_out() {
+        let mut set = RecordingSet::new("/videos/Game_1.mp4", "Game_1.slp");
+        set.end_game("Game_1.slp", GameEndReason::Game);
+        set.pause();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        set.resume();
+        set.start_game("Game_2.slp");
+
+        let games = set.games();
+        assert!(games[1].start_seconds - games[0].end_seconds.unwrap() < 0.05);
+    }
+}
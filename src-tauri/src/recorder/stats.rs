@@ -0,0 +1,173 @@
+// Live numbers about a running recording
+//
+// Every backend reports a `RecorderStats` snapshot. The app polls it while
+// recording, forwards it to the frontend and watches the drop rate so a bad
+// recording is noticed during the set rather than after it.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Drop rate over one polling interval that counts as a degraded recording
+pub const DEGRADED_DROP_RATE: f64 = 0.05;
+/// Fewer frames than this in an interval is too little to judge the drop rate
+const MIN_FRAMES_TO_JUDGE: u64 = 30;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecorderStats {
+    /// Recorded time, leaving out pauses
+    pub elapsed_seconds: f64,
+    pub frames_captured: u64,
+    /// Frames the capture or the encoder couldn't keep up with
+    pub frames_dropped: u64,
+    /// Frames waiting to be encoded
+    pub encoder_queue_depth: u64,
+    pub bytes_written: u64,
+    /// Times the audio capture had nothing to give when it was due
+    pub audio_underruns: u64,
+    pub paused: bool,
+}
+
+impl RecorderStats {
+    /// Dropped frames as a share of all frames, over the whole recording
+    pub fn drop_rate(&self) -> f64 {
+        let total = self.frames_captured + self.frames_dropped;
+        if total == 0 {
+            0.0
+        } else {
+            self.frames_dropped as f64 / total as f64
+        }
+    }
+
+    /// Count a finished file into totals kept over several files
+    pub fn add(&mut self, finished: &RecorderStats) {
+        self.elapsed_seconds += finished.elapsed_seconds;
+        self.frames_captured += finished.frames_captured;
+        self.frames_dropped += finished.frames_dropped;
+        self.bytes_written += finished.bytes_written;
+        self.audio_underruns += finished.audio_underruns;
+    }
+
+    /// These totals plus the file being recorded now
+    pub fn with_current(&self, current: &RecorderStats) -> RecorderStats {
+        let mut totals = self.clone();
+        totals.add(current);
+        totals.encoder_queue_depth = current.encoder_queue_depth;
+        totals.paused = current.paused;
+        totals
+    }
+}
+
+/// Wall-clock time of a recording that can be paused
+#[derive(Debug, Clone)]
+pub struct RecordingClock {
+    started: Instant,
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl RecordingClock {
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            paused_at: None,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    /// Time recorded so far, leaving out pauses
+    pub fn elapsed(&self) -> Duration {
+        let current_pause = self.paused_at.map(|at| at.elapsed()).unwrap_or_default();
+        self.started
+            .elapsed()
+            .saturating_sub(self.paused_total + current_pause)
+    }
+
+    pub fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += paused_at.elapsed();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
+
+/// Spots when the drop rate between two snapshots crosses the threshold
+pub struct DropRateMonitor {
+    threshold: f64,
+    /// Captured and dropped counts at the previous snapshot
+    last: (u64, u64),
+    degraded: bool,
+}
+
+impl DropRateMonitor {
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            last: (0, 0),
+            degraded: false,
+        }
+    }
+
+    /// Feed the next snapshot. Returns the interval's drop rate when the
+    /// recording has just become degraded; it has to recover to half the
+    /// threshold before it's reported again.
+    pub fn update(&mut self, stats: &RecorderStats) -> Option<f64> {
+        let captured = stats.frames_captured.saturating_sub(self.last.0);
+        let dropped = stats.frames_dropped.saturating_sub(self.last.1);
+        self.last = (stats.frames_captured, stats.frames_dropped);
+
+        if captured + dropped < MIN_FRAMES_TO_JUDGE {
+            return None;
+        }
+
+        let rate = dropped as f64 / (captured + dropped) as f64;
+        if rate >= self.threshold && !self.degraded {
+            self.degraded = true;
+            return Some(rate);
+        }
+        if rate < self.threshold / 2.0 {
+            self.degraded = false;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(frames_captured: u64, frames_dropped: u64) -> RecorderStats {
+        RecorderStats {
+            frames_captured,
+            frames_dropped,
+            ..RecorderStats::default()
+        }
+    }
+
+    #[test]
+    fn test_reports_degradation_once_until_recovered() {
+        let mut monitor = DropRateMonitor::new(DEGRADED_DROP_RATE);
+
+        assert_eq!(monitor.update(&snapshot(120, 0)), None);
+        // 12 of 120 frames dropped in this interval
+        let rate = monitor.update(&snapshot(228, 12)).unwrap();
+        assert!((rate - 0.1).abs() < 1e-9);
+        // Still dropping, but already reported
+        assert_eq!(monitor.update(&snapshot(336, 24)), None);
+        // Recovers, then degrades again
+        assert_eq!(monitor.update(&snapshot(456, 24)), None);
+        assert!(monitor.update(&snapshot(564, 36)).is_some());
+    }
+
+    #[test]
+    fn test_small_intervals_are_not_judged() {
+        let mut monitor = DropRateMonitor::new(DEGRADED_DROP_RATE);
+        assert_eq!(monitor.update(&snapshot(5, 5)), None);
+    }
+}
@@ -0,0 +1,143 @@
+// Which window to capture, and finding it again
+//
+// Every backend looks for the game the same way: the window picked in settings
+// if there is one, otherwise the biggest window that looks like Dolphin.
+
+/// Any window scoring this much is named like the game or the picked window,
+/// rather than just being big
+pub(super) const GAME_WINDOW_SCORE: i64 = 500_000_000;
+
+/// Window picked in settings, passed through the same environment variables
+/// the native recorders read
+///
+/// Only x11grab can look windows up by process, and avfoundation can't capture
+/// single windows at all, so not every platform reads every field.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+pub(super) struct TargetSelection {
+    pub(super) title: Option<String>,
+    pub(super) pid: Option<u32>,
+}
+
+impl TargetSelection {
+    pub(super) fn from_env() -> Self {
+        let mut title = std::env::var("PEPPI_TARGET_WINDOW")
+            .ok()
+            .map(|s| s.trim().to_string());
+        let mut pid = std::env::var("PEPPI_TARGET_PID")
+            .ok()
+            .and_then(|raw| raw.parse::<u32>().ok());
+
+        if let Some(t) = &title {
+            if let Some(idx) = t.rfind("(PID:") {
+                if pid.is_none() {
+                    let digits: String = t[idx + 5..]
+                        .chars()
+                        .filter(|ch| ch.is_ascii_digit())
+                        .collect();
+                    pid = digits.parse::<u32>().ok();
+                }
+                title = Some(t[..idx].trim().to_string());
+            }
+        }
+
+        Self {
+            title: title.filter(|s| !s.is_empty()),
+            pid,
+        }
+    }
+}
+
+/// A game window found by `find_game_window`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetMatch {
+    pub title: String,
+    pub pid: u32,
+}
+
+impl TargetMatch {
+    /// Point the recorders at this window; the process the settings named may
+    /// be gone, e.g. after Dolphin restarted
+    pub fn select(&self) {
+        std::env::set_var("PEPPI_TARGET_WINDOW", &self.title);
+        std::env::set_var("PEPPI_TARGET_PID", self.pid.to_string());
+    }
+}
+
+/// How much a window looks like the one to capture. Matching `hint` or looking
+/// like Dolphin outweighs any size; windows too small to be the game score -1.
+pub(super) fn score_window(title: &str, width: u32, height: u32, hint: Option<&str>) -> i64 {
+    if width < 200 || height < 200 {
+        return -1;
+    }
+
+    let mut score = width as i64 * height as i64;
+    let lc_title = title.to_lowercase();
+
+    if let Some(h) = hint {
+        if lc_title.contains(&h.to_lowercase()) {
+            score += 1_000_000_000;
+        }
+    }
+
+    if lc_title.contains("slippi") || lc_title.contains("melee") || lc_title.contains("dolphin") {
+        score += GAME_WINDOW_SCORE;
+    }
+
+    score
+}
+
+/// Look for the game by the picked window's title or by looking like Dolphin,
+/// whatever process it belongs to now
+pub fn find_game_window() -> Option<TargetMatch> {
+    let selection = TargetSelection::from_env();
+    find_platform_window(selection.title.as_deref())
+}
+
+#[cfg(target_os = "linux")]
+fn find_platform_window(hint: Option<&str>) -> Option<TargetMatch> {
+    super::linux::find_game_window(hint).map(|window| TargetMatch {
+        title: window.title,
+        pid: window.pid,
+    })
+}
+
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+fn find_platform_window(hint: Option<&str>) -> Option<TargetMatch> {
+    super::windows::find_game_window(hint)
+}
+
+#[cfg(all(target_os = "macos", feature = "real-recording"))]
+fn find_platform_window(hint: Option<&str>) -> Option<TargetMatch> {
+    super::macos::find_game_window(hint)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    all(target_os = "windows", feature = "real-recording"),
+    all(target_os = "macos", feature = "real-recording")
+)))]
+fn find_platform_window(_hint: Option<&str>) -> Option<TargetMatch> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_windows_outscore_bigger_ones() {
+        let game = score_window("Slippi Dolphin 2.3.0 | Melee", 1280, 1056, None);
+        let browser = score_window("Firefox", 3840, 2160, None);
+        assert!(game >= GAME_WINDOW_SCORE);
+        assert!(browser < GAME_WINDOW_SCORE);
+        assert!(game > browser);
+
+        // The picked window counts for more than looking like Dolphin
+        assert!(
+            score_window("Firefox", 1280, 720, Some("firefox"))
+                > score_window("Dolphin", 1280, 720, Some("firefox"))
+        );
+        assert!(score_window("Slippi Dolphin", 150, 100, None) < 0);
+    }
+}
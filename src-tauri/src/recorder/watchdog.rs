@@ -0,0 +1,442 @@
+// Keeps a recording going when the game window goes away
+//
+// Dolphin closes its window when it restarts, e.g. after a desync, and the
+// capture ends with it. The watchdog finishes the file at that point, looks for
+// the game again on every tick, and records what follows into
+// `<stem>_00001.<ext>`, `<stem>_00002.<ext>`, ... next to the original. The
+// files are listed in the recording's `.segments.json` manifest, so the library
+// and clips treat them as one recording, like a segmented recording.
+
+use super::segmented::{ManifestSegment, SegmentManifest};
+use super::stats::RecordingClock;
+use super::target::{self, TargetMatch};
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
+use std::path::Path;
+
+/// Looks for the game window; `None` while it's still gone
+pub type WindowFinder = Box<dyn FnMut() -> Option<TargetMatch> + Send>;
+
+pub struct Watchdog {
+    inner: Box<dyn Recorder + Send>,
+    find_window: WindowFinder,
+    output_path: Option<String>,
+    profile: Option<RecordingProfile>,
+    /// The file being recorded and its recorded time; `None` while the game is gone
+    current: Option<(String, RecordingClock)>,
+    /// Files finished when the game went away
+    finished: Vec<ManifestSegment>,
+    finished_stats: RecorderStats,
+    next_index: u32,
+    /// Kept here as well, so a pause while the game is gone carries over
+    paused: bool,
+}
+
+impl Watchdog {
+    pub fn new(inner: Box<dyn Recorder + Send>) -> Self {
+        Self::with_finder(inner, Box::new(target::find_game_window))
+    }
+
+    pub fn with_finder(inner: Box<dyn Recorder + Send>, find_window: WindowFinder) -> Self {
+        Self {
+            inner,
+            find_window,
+            output_path: None,
+            profile: None,
+            current: None,
+            finished: Vec::new(),
+            finished_stats: RecorderStats::default(),
+            next_index: 0,
+            paused: false,
+        }
+    }
+
+    /// The recording's own path first, then `<stem>_<index>.<ext>` next to it
+    fn file_path(output_path: &str, index: u32) -> String {
+        if index == 0 {
+            return output_path.to_string();
+        }
+        let path = Path::new(output_path);
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("recording");
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("mp4");
+        path.with_file_name(format!("{}_{:05}.{}", stem, index, extension))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Start recording the next file
+    fn start_file(&mut self) -> Result<(), Error> {
+        let (Some(output_path), Some(profile)) = (&self.output_path, &self.profile) else {
+            return Err(Error::RecordingFailed("Not recording".to_string()));
+        };
+
+        let path = Self::file_path(output_path, self.next_index);
+        self.inner.start_recording(&path, profile)?;
+        let mut clock = RecordingClock::start();
+        if self.paused {
+            if let Err(e) = self.inner.pause() {
+                log::warn!("Failed to pause the resumed recording: {:?}", e);
+            }
+            clock.pause();
+        }
+        self.current = Some((path, clock));
+        self.next_index += 1;
+        self.write_manifest();
+        Ok(())
+    }
+
+    /// Finish the current file, listing whatever was recorded even if the
+    /// recorder fails to stop; the failure is passed on
+    fn finish_file(&mut self) -> Result<(), Error> {
+        let Some((path, clock)) = self.current.take() else {
+            return Ok(());
+        };
+
+        self.finished_stats.add(&self.inner.stats());
+        let (path, stopped) = match self.inner.stop_recording() {
+            Ok(path) => (path, Ok(())),
+            Err(e) => (path, Err(e)),
+        };
+
+        let start_seconds = self
+            .finished
+            .last()
+            .map_or(0.0, |s| s.start_seconds + s.duration_seconds.unwrap_or(0.0));
+        match SegmentManifest::load(&path) {
+            // The recorder split the file itself; list its segments instead
+            Some(manifest) => {
+                if Some(&path) != self.output_path.as_ref() {
+                    let _ = std::fs::remove_file(SegmentManifest::path_for(&path));
+                }
+                self.finished
+                    .extend(manifest.segments.into_iter().map(|s| ManifestSegment {
+                        start_seconds: start_seconds + s.start_seconds,
+                        ..s
+                    }));
+            }
+            None => self.finished.push(ManifestSegment {
+                path,
+                start_seconds,
+                duration_seconds: Some(clock.elapsed().as_secs_f64()),
+            }),
+        }
+        stopped
+    }
+
+    /// List the files recorded so far, once there's more than the one
+    fn write_manifest(&self) {
+        let Some(output_path) = &self.output_path else {
+            return;
+        };
+        if self.finished.is_empty() {
+            return;
+        }
+
+        let mut segments = self.finished.clone();
+        if let Some((path, _)) = &self.current {
+            let last = &segments[segments.len() - 1];
+            segments.push(ManifestSegment {
+                path: path.clone(),
+                start_seconds: last.start_seconds + last.duration_seconds.unwrap_or(0.0),
+                duration_seconds: None,
+            });
+        }
+        let manifest = SegmentManifest {
+            video_path: output_path.clone(),
+            segments,
+        };
+        if let Err(e) = manifest.write() {
+            log::error!("Failed to write segment manifest: {:?}", e);
+        }
+    }
+}
+
+impl Recorder for Watchdog {
+    fn start_recording(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<(), Error> {
+        if self.output_path.is_some() {
+            return Err(Error::RecordingFailed("Already recording".to_string()));
+        }
+
+        self.output_path = Some(output_path.to_string());
+        self.profile = Some(profile.clone());
+        self.finished.clear();
+        self.finished_stats = RecorderStats::default();
+        self.next_index = 0;
+        self.paused = false;
+        if let Err(e) = self.start_file() {
+            self.output_path = None;
+            self.profile = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<String, Error> {
+        if self.output_path.is_none() {
+            return Err(Error::RecordingFailed("Not recording".to_string()));
+        }
+
+        let stopped = self.finish_file();
+        self.write_manifest();
+        if self.finished.len() > 1 {
+            log::info!(
+                "🎞️ Recording continued across {} files after losing the game window",
+                self.finished.len()
+            );
+        }
+        self.profile = None;
+        let output_path = self.output_path.take();
+        stopped?;
+        output_path.ok_or_else(|| Error::RecordingFailed("Not recording".to_string()))
+    }
+
+    fn is_recording(&self) -> bool {
+        self.output_path.is_some()
+    }
+
+    fn pause(&mut self) -> Result<(), Error> {
+        if self.output_path.is_none() || self.paused {
+            return Err(Error::RecordingFailed(
+                "Not currently recording".to_string(),
+            ));
+        }
+        if let Some((_, clock)) = self.current.as_mut() {
+            self.inner.pause()?;
+            clock.pause();
+        }
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        if !self.paused {
+            return Err(Error::RecordingFailed("Not paused".to_string()));
+        }
+        if let Some((_, clock)) = self.current.as_mut() {
+            self.inner.resume()?;
+            clock.resume();
+        }
+        self.paused = false;
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Totals over every file; while the game is gone nothing is being recorded
+    fn stats(&self) -> RecorderStats {
+        if self.output_path.is_none() {
+            return RecorderStats::default();
+        }
+        let current = match &self.current {
+            Some(_) => self.inner.stats(),
+            None => RecorderStats {
+                paused: self.paused,
+                ..RecorderStats::default()
+            },
+        };
+        self.finished_stats.with_current(&current)
+    }
+
+    /// Notice the game going away, and start the next file once it's back
+    fn tick(&mut self) -> Result<(), Error> {
+        if self.output_path.is_none() {
+            return Ok(());
+        }
+
+        if self.current.is_some() {
+            self.inner.tick()?;
+            if self.inner.target_lost() {
+                log::warn!("🪟 Lost the game window, waiting for it to come back");
+                // The capture ended under the recorder, so it may well
+                // complain; what it recorded is kept all the same
+                if let Err(e) = self.finish_file() {
+                    log::warn!("⚠️ Recording ended uncleanly: {:?}", e);
+                }
+                self.write_manifest();
+            }
+            return Ok(());
+        }
+
+        let Some(window) = (self.find_window)() else {
+            return Ok(());
+        };
+        log::info!(
+            "🪟 Found the game window again: {} (pid {})",
+            window.title,
+            window.pid
+        );
+        window.select();
+        self.start_file()
+    }
+
+    fn target_lost(&self) -> bool {
+        self.output_path.is_some() && self.current.is_none()
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::mock::MockRecorder;
+    use crate::recorder::RecordingQuality;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// A recorder whose window can be closed from the test
+    struct ClosableRecorder {
+        inner: MockRecorder,
+        closed: Arc<AtomicBool>,
+        /// Whether stopping fails, like a file that couldn't be finished
+        fails_to_stop: Arc<AtomicBool>,
+    }
+
+    impl Recorder for ClosableRecorder {
+        fn start_recording(&mut self, path: &str, profile: &RecordingProfile) -> Result<(), Error> {
+            self.inner.start_recording(path, profile)
+        }
+
+        fn stop_recording(&mut self) -> Result<String, Error> {
+            let path = self.inner.stop_recording()?;
+            if self.fails_to_stop.load(Ordering::Relaxed) {
+                return Err(Error::RecordingFailed("Couldn't finish the file".into()));
+            }
+            Ok(path)
+        }
+
+        fn is_recording(&self) -> bool {
+            self.inner.is_recording()
+        }
+
+        fn pause(&mut self) -> Result<(), Error> {
+            self.inner.pause()
+        }
+
+        fn resume(&mut self) -> Result<(), Error> {
+            self.inner.resume()
+        }
+
+        fn is_paused(&self) -> bool {
+            self.inner.is_paused()
+        }
+
+        fn stats(&self) -> RecorderStats {
+            self.inner.stats()
+        }
+
+        fn target_lost(&self) -> bool {
+            self.closed.load(Ordering::Relaxed)
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            self.inner.capabilities()
+        }
+    }
+
+    #[test]
+    fn test_continues_in_a_new_file_when_the_window_returns() {
+        let dir = std::env::temp_dir().join("buckwheat_watchdog_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video_path = dir.join("Game_1.mp4").to_string_lossy().to_string();
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let recorder = ClosableRecorder {
+            inner: MockRecorder::without_video(),
+            closed: closed.clone(),
+            fails_to_stop: Arc::new(AtomicBool::new(false)),
+        };
+        let window_closed = closed.clone();
+        let mut watchdog = Watchdog::with_finder(
+            Box::new(recorder),
+            Box::new(move || {
+                (!window_closed.load(Ordering::Relaxed)).then(|| TargetMatch {
+                    title: "Slippi Dolphin".to_string(),
+                    pid: 4242,
+                })
+            }),
+        );
+
+        watchdog
+            .start_recording(&video_path, &RecordingQuality::Low.into())
+            .unwrap();
+        watchdog.tick().unwrap();
+        assert!(!watchdog.target_lost());
+        assert!(SegmentManifest::load(&video_path).is_none());
+
+        // Dolphin restarts: the file ends and the recording waits
+        closed.store(true, Ordering::Relaxed);
+        watchdog.tick().unwrap();
+        watchdog.tick().unwrap();
+        assert!(watchdog.target_lost());
+        assert!(watchdog.is_recording());
+
+        closed.store(false, Ordering::Relaxed);
+        watchdog.tick().unwrap();
+        assert!(!watchdog.target_lost());
+        assert_eq!(watchdog.stop_recording().unwrap(), video_path);
+
+        let manifest = SegmentManifest::load(&video_path).unwrap();
+        assert_eq!(manifest.segments.len(), 2);
+        assert_eq!(manifest.segments[0].path, video_path);
+        assert!(manifest.segments[1].path.ends_with("Game_1_00001.mp4"));
+        assert!(manifest.duration_seconds().is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_passes_on_a_failed_stop() {
+        let dir = std::env::temp_dir().join("buckwheat_watchdog_stop_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video_path = dir.join("Game_1.mp4").to_string_lossy().to_string();
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let fails_to_stop = Arc::new(AtomicBool::new(true));
+        let recorder = ClosableRecorder {
+            inner: MockRecorder::without_video(),
+            closed: closed.clone(),
+            fails_to_stop: fails_to_stop.clone(),
+        };
+        let window_closed = closed.clone();
+        let mut watchdog = Watchdog::with_finder(
+            Box::new(recorder),
+            Box::new(move || {
+                (!window_closed.load(Ordering::Relaxed)).then(|| TargetMatch {
+                    title: "Slippi Dolphin".to_string(),
+                    pid: 4242,
+                })
+            }),
+        );
+        watchdog
+            .start_recording(&video_path, &RecordingQuality::Low.into())
+            .unwrap();
+
+        // Losing the window is expected to end the file badly
+        closed.store(true, Ordering::Relaxed);
+        watchdog.tick().unwrap();
+        assert!(watchdog.target_lost());
+        closed.store(false, Ordering::Relaxed);
+        watchdog.tick().unwrap();
+
+        // Stopping isn't, so the caller hears about it
+        assert!(watchdog.stop_recording().is_err());
+        assert!(!watchdog.is_recording());
+        let manifest = SegmentManifest::load(&video_path).unwrap();
+        assert_eq!(manifest.segments.len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
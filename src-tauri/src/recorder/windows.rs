@@ -4,12 +4,22 @@
 )]
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
-use super::{Error, Recorder};
+use super::frames::FrameLog;
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+use super::parts::PartedOutput;
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+use super::profile::{Container, RateControl, VideoCodec};
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+use super::stats::RecordingClock;
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+use super::target::{self, TargetMatch, TargetSelection, GAME_WINDOW_SCORE};
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+use super::{BackendCapabilities, Error, Recorder, RecorderStats, RecordingProfile};
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 use std::collections::VecDeque;
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 use std::sync::{mpsc, Arc, Mutex};
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
@@ -47,7 +57,7 @@ use windows_capture::settings::{
 use windows_capture::window::Window as CaptureWindow;
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
-const TARGET_FPS: u32 = 60;
+const MAX_FPS: u32 = 60;
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 const AUDIO_SAMPLE_RATE: u32 = 48_000;
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
@@ -61,8 +71,13 @@ pub struct WindowsRecorder {
     capture_control: Option<CaptureControl<WindowCaptureHandler, HandlerError>>,
     audio_thread: Option<JoinHandle<()>>,
     shared_state: Option<Arc<SharedRecorderState>>,
-    output_path: Option<String>,
+    output: Option<PartedOutput>,
+    profile: Option<RecordingProfile>,
+    is_paused: bool,
     target_process_id: Option<u32>,
+    clock: Option<RecordingClock>,
+    /// Shared by every part, so the counts cover the whole recording
+    counters: Arc<CaptureCounters>,
 }
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
@@ -73,8 +88,12 @@ impl WindowsRecorder {
             capture_control: None,
             audio_thread: None,
             shared_state: None,
-            output_path: None,
+            output: None,
+            profile: None,
+            is_paused: false,
             target_process_id: None,
+            clock: None,
+            counters: Arc::new(CaptureCounters::default()),
         }
     }
 
@@ -99,19 +118,20 @@ impl WindowsRecorder {
         width: u32,
         height: u32,
         output_path: &str,
-        quality: super::RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<VideoEncoder, Error> {
-        let bitrate = quality.bitrate();
-        log::info!(
-            "🎬 Building encoder with {:?} quality (bitrate: {} Mbps)",
-            quality,
-            bitrate / 1_000_000
-        );
-
+        log::info!("🎬 Building encoder ({})", profile.summary());
+
+        // Media Foundation picks the (hardware) encoder; validation has already
+        // ruled out anything it can't do
+        let sub_type = match profile.codec {
+            VideoCodec::Hevc => VideoSettingsSubType::HEVC,
+            _ => VideoSettingsSubType::H264,
+        };
         let video_settings = VideoSettingsBuilder::new(width, height)
-            .sub_type(VideoSettingsSubType::H264)
-            .frame_rate(TARGET_FPS)
-            .bitrate(bitrate);
+            .sub_type(sub_type)
+            .frame_rate(profile.fps)
+            .bitrate(profile.bitrate);
 
         let audio_settings = AudioSettingsBuilder::new().disabled(false);
         let container_settings = ContainerSettingsBuilder::new();
@@ -127,25 +147,25 @@ impl WindowsRecorder {
 }
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
-impl Recorder for WindowsRecorder {
-    fn start_recording(
+impl WindowsRecorder {
+    /// Start capturing the game window into `output_path`
+    fn start_capture(
         &mut self,
         output_path: &str,
-        quality: super::RecordingQuality,
+        profile: &RecordingProfile,
     ) -> Result<(), Error> {
-        if self.is_recording {
-            return Err(Error::RecordingFailed("Already recording".into()));
-        }
-
-        self.ensure_output_dir(output_path)?;
         let target = self.resolve_target_window()?;
         info!(
-            "Starting Windows capture for '{}' (pid {}, {}x{}) with {:?} quality",
-            target.title, target.pid, target.width, target.height, quality
+            "Starting Windows capture for '{}' (pid {}, {}x{}) with {}",
+            target.title,
+            target.pid,
+            target.width,
+            target.height,
+            profile.summary()
         );
 
-        let encoder = self.build_encoder(target.width, target.height, output_path, quality)?;
-        let shared = Arc::new(SharedRecorderState::new(encoder));
+        let encoder = self.build_encoder(target.width, target.height, output_path, profile)?;
+        let shared = Arc::new(SharedRecorderState::new(encoder, self.counters.clone()));
         let capture_settings = Settings::new(
             target.window,
             CursorCaptureSettings::WithoutCursor,
@@ -167,18 +187,12 @@ impl Recorder for WindowsRecorder {
         self.capture_control = Some(capture_control);
         self.audio_thread = Some(audio_thread);
         self.shared_state = Some(shared);
-        self.output_path = Some(output_path.to_string());
         self.target_process_id = Some(target.pid);
-        self.is_recording = true;
-
         Ok(())
     }
 
-    fn stop_recording(&mut self) -> Result<String, Error> {
-        if !self.is_recording {
-            return Err(Error::RecordingFailed("Not recording".into()));
-        }
-
+    /// Stop capturing and finalize the file being written
+    fn finish_capture(&mut self) -> Result<(), Error> {
         if let Some(shared) = &self.shared_state {
             shared.request_stop();
         }
@@ -193,11 +207,6 @@ impl Recorder for WindowsRecorder {
             let _ = handle.join();
         }
 
-        let output = self
-            .output_path
-            .clone()
-            .unwrap_or_else(|| "recording.mp4".into());
-
         if let Some(shared) = self.shared_state.take() {
             if let Some(encoder) = shared.take_encoder() {
                 encoder.finish().map_err(|err| {
@@ -206,13 +215,63 @@ impl Recorder for WindowsRecorder {
             }
 
             if let Some(err) = shared.take_error() {
-                self.is_recording = false;
                 return Err(Error::RecordingFailed(err));
             }
         }
 
-        self.output_path = None;
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+impl Recorder for WindowsRecorder {
+    fn start_recording(
+        &mut self,
+        output_path: &str,
+        profile: &RecordingProfile,
+    ) -> Result<(), Error> {
+        if self.is_recording {
+            return Err(Error::RecordingFailed("Already recording".into()));
+        }
+
+        self.ensure_output_dir(output_path)?;
+        self.counters = Arc::new(CaptureCounters::default());
+        self.start_capture(output_path, profile)?;
+
+        self.output = Some(PartedOutput::new(output_path));
+        self.profile = Some(profile.clone());
+        self.clock = Some(RecordingClock::start());
+        self.is_paused = false;
+        self.is_recording = true;
+
+        Ok(())
+    }
+
+    fn stop_recording(&mut self) -> Result<String, Error> {
+        if !self.is_recording {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        }
+
+        let finished = if self.is_paused {
+            Ok(())
+        } else {
+            self.finish_capture()
+        };
         self.is_recording = false;
+        self.is_paused = false;
+        self.profile = None;
+        self.clock = None;
+        // Join the parts even when the last one didn't end cleanly; any that
+        // can't be joined are left for recovery
+        let joined = match self.output.take() {
+            Some(output) => output.finish(),
+            None => Ok("recording.mp4".into()),
+        };
+        finished?;
+        let output = joined?;
+        if let Err(err) = self.counters.frames.write(&output) {
+            warn!("Failed to write frame timestamps: {err:?}");
+        }
         info!("Recording saved to {output}");
         Ok(output)
     }
@@ -220,25 +279,131 @@ impl Recorder for WindowsRecorder {
     fn is_recording(&self) -> bool {
         self.is_recording
     }
+
+    /// Media Foundation writes one file per encoder, so each stretch between
+    /// pauses gets its own part
+    fn pause(&mut self) -> Result<(), Error> {
+        if !self.is_recording || self.is_paused {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        }
+
+        self.finish_capture()?;
+        self.is_paused = true;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.pause();
+        }
+        self.counters.frames.pause();
+        info!("⏸️ Paused Windows capture");
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), Error> {
+        if !self.is_paused {
+            return Err(Error::RecordingFailed("Not paused".into()));
+        }
+        let (Some(output), Some(profile)) = (self.output.as_mut(), self.profile.clone()) else {
+            return Err(Error::RecordingFailed("Not recording".into()));
+        };
+        let part = output.next_part();
+
+        self.start_capture(&part, &profile)?;
+        self.is_paused = false;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.resume();
+        }
+        self.counters.frames.resume();
+        info!("▶️ Resumed Windows capture into {part}");
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Media Foundation encodes as frames are sent, so there's no queue to
+    /// report. Nor are there drops: the capture only delivers a frame when the
+    /// window changes, so a gap between frames can't tell a drop from a still
+    /// screen.
+    fn stats(&self) -> RecorderStats {
+        let Some(clock) = &self.clock else {
+            return RecorderStats::default();
+        };
+
+        RecorderStats {
+            elapsed_seconds: clock.elapsed().as_secs_f64(),
+            frames_captured: self.counters.frames_captured.load(Ordering::Relaxed),
+            frames_dropped: 0,
+            encoder_queue_depth: 0,
+            bytes_written: self
+                .output
+                .as_ref()
+                .map_or(0, |output| output.bytes_written()),
+            audio_underruns: self.counters.audio_underruns.load(Ordering::Relaxed),
+            paused: self.is_paused,
+        }
+    }
+
+    fn target_lost(&self) -> bool {
+        self.shared_state
+            .as_ref()
+            .is_some_and(|shared| shared.window_closed.load(Ordering::Relaxed))
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            name: "Windows".to_string(),
+            software_codecs: vec![],
+            hardware_codecs: vec![VideoCodec::H264, VideoCodec::Hevc],
+            // Media Foundation can't write crash-safe containers; those
+            // profiles are recorded by the FFmpeg fallback
+            containers: vec![Container::Mp4],
+            rate_controls: vec![RateControl::Vbr],
+            max_fps: MAX_FPS,
+            scaling: false,
+        }
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+#[derive(Default)]
+struct CaptureCounters {
+    frames_captured: AtomicU64,
+    audio_underruns: AtomicU64,
+    /// Frames are placed by their capture timestamps, as that's how the encoder
+    /// stamps them
+    frames: FrameLog,
 }
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 struct SharedRecorderState {
     encoder: Mutex<Option<VideoEncoder>>,
     stop_flag: AtomicBool,
+    /// The captured window closed, which ends the capture
+    window_closed: AtomicBool,
     last_error: Mutex<Option<String>>,
+    counters: Arc<CaptureCounters>,
 }
 
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 impl SharedRecorderState {
-    fn new(encoder: VideoEncoder) -> Self {
+    fn new(encoder: VideoEncoder, counters: Arc<CaptureCounters>) -> Self {
         Self {
             encoder: Mutex::new(Some(encoder)),
             stop_flag: AtomicBool::new(false),
+            window_closed: AtomicBool::new(false),
             last_error: Mutex::new(None),
+            counters,
         }
     }
 
+    /// Count a captured frame. `timestamp` is its SystemRelativeTime in seconds.
+    fn record_frame(&self, timestamp: f64) {
+        self.counters
+            .frames_captured
+            .fetch_add(1, Ordering::Relaxed);
+        self.counters.frames.record_stamped(timestamp);
+    }
+
     fn request_stop(&self) {
         self.stop_flag.store(true, Ordering::Relaxed);
     }
@@ -292,17 +457,21 @@ impl GraphicsCaptureApiHandler for WindowCaptureHandler {
             return Ok(());
         }
 
+        // In 100ns ticks
+        let timestamp = frame.timestamp().Duration as f64 / 10_000_000.0;
         let mut guard = self.shared.encoder.lock().unwrap();
         if let Some(encoder) = guard.as_mut() {
             encoder
                 .send_frame(frame)
                 .map_err(|err| HandlerError::Encoder(format!("Failed to encode frame: {err:?}")))?;
+            self.shared.record_frame(timestamp);
         }
 
         Ok(())
     }
 
     fn on_closed(&mut self) -> Result<(), Self::Error> {
+        self.shared.window_closed.store(true, Ordering::Relaxed);
         self.shared.request_stop();
         Ok(())
     }
@@ -410,9 +579,17 @@ fn capture_audio_loop(
             }
         }
 
+        let queued = sample_queue.len();
         capture_client
             .read_from_device_to_deque(&mut sample_queue)
             .map_err(|err| format!("Failed to capture audio: {err:?}"))?;
+        if sample_queue.len() == queued {
+            // Woken up for samples that weren't there
+            shared
+                .counters
+                .audio_underruns
+                .fetch_add(1, Ordering::Relaxed);
+        }
 
         if event_handle.wait_for_event(100_000).is_err() {
             break;
@@ -441,43 +618,6 @@ fn create_loopback_client(
     Ok(device.get_iaudioclient()?)
 }
 
-#[cfg(all(target_os = "windows", feature = "real-recording"))]
-#[derive(Clone)]
-struct TargetSelection {
-    title: Option<String>,
-    pid: Option<u32>,
-}
-
-#[cfg(all(target_os = "windows", feature = "real-recording"))]
-impl TargetSelection {
-    fn from_env() -> Self {
-        let mut title = std::env::var("PEPPI_TARGET_WINDOW")
-            .ok()
-            .map(|s| s.trim().to_string());
-        let mut pid = std::env::var("PEPPI_TARGET_PID")
-            .ok()
-            .and_then(|raw| raw.parse::<u32>().ok());
-
-        if let Some(t) = &title {
-            if let Some(idx) = t.rfind("(PID:") {
-                if pid.is_none() {
-                    let digits: String = t[idx + 5..]
-                        .chars()
-                        .filter(|ch| ch.is_ascii_digit())
-                        .collect();
-                    pid = digits.parse::<u32>().ok();
-                }
-                title = Some(t[..idx].trim().to_string());
-            }
-        }
-
-        Self {
-            title: title.filter(|s| !s.is_empty()),
-            pid,
-        }
-    }
-}
-
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
 struct TargetWindow {
     window: CaptureWindow,
@@ -558,29 +698,32 @@ fn pick_default_window() -> Result<CaptureWindow, Error> {
         .ok_or_else(|| Error::WindowNotFound)
 }
 
+/// A window that's recognisably the game, for picking it up again after it closed
 #[cfg(all(target_os = "windows", feature = "real-recording"))]
-fn score_window(window: &CaptureWindow, hint: Option<&str>) -> i64 {
-    let width = window.width().unwrap_or(0) as i64;
-    let height = window.height().unwrap_or(0) as i64;
-    if width < 200 || height < 200 {
-        return -1;
-    }
-
-    let mut score = width * height;
-    let lc_title = window.title().unwrap_or_default().to_lowercase();
-    let class_name = window_class_name(window);
-
-    if let Some(h) = hint {
-        if lc_title.contains(&h.to_lowercase()) {
-            score += 1_000_000_000;
-        }
-    }
+pub(super) fn find_game_window(hint: Option<&str>) -> Option<TargetMatch> {
+    let windows = CaptureWindow::enumerate().ok()?;
+    let window = windows
+        .into_iter()
+        .filter(|window| score_window(window, hint) >= GAME_WINDOW_SCORE)
+        .max_by_key(|window| score_window(window, hint))?;
+    Some(TargetMatch {
+        title: window.title().unwrap_or_default(),
+        pid: window.process_id().ok()?,
+    })
+}
 
-    if lc_title.contains("slippi") || lc_title.contains("melee") || lc_title.contains("dolphin") {
-        score += 500_000_000;
+/// The shared score, plus Dolphin's own window classes
+#[cfg(all(target_os = "windows", feature = "real-recording"))]
+fn score_window(window: &CaptureWindow, hint: Option<&str>) -> i64 {
+    let title = window.title().unwrap_or_default();
+    let width = window.width().unwrap_or(0).max(0) as u32;
+    let height = window.height().unwrap_or(0).max(0) as u32;
+    let mut score = target::score_window(&title, width, height, hint);
+    if score < 0 {
+        return score;
     }
 
-    if let Some(name) = class_name {
+    if let Some(name) = window_class_name(window) {
         let ln = name.to_lowercase();
         if ln.contains("d3dproxy") {
             score += 5_000_000_000;
@@ -1,24 +1,21 @@
 // Event extraction from .slp game data
 
-use super::types::{DeathEvent, GameEvent};
+use super::moves::move_name;
+use super::states;
+use super::types::{BlastZone, ComboEvent, ComboMove, DeathEvent, GameEvent};
 use crate::commands::errors::Error;
+use peppi::frame::immutable::PortData;
 use peppi::frame::Rollbacks;
 use ssbm_data::action_state::Common;
 
-/// Extract death events from a parsed game
-///
-/// This function iterates through all frames and detects when a player enters
-/// a death animation state, recording the frame number and player information.
-pub fn extract_death_events(game: &peppi::game::immutable::Game) -> Result<Vec<GameEvent>, Error> {
-    log::info!("🔍 Extracting death events from game data");
-
-    let mut events = Vec::new();
-    let mut is_dead = vec![false; game.frames.ports.len()];
-    let rollbacks = game.frames.rollbacks(Rollbacks::ExceptLast);
+/// Frames the victim must escape for before a hit string is considered dropped
+const HIT_STRING_TIMEOUT: u32 = 45;
+/// Hit strings with fewer distinct moves are not reported as combos
+const MIN_COMBO_MOVES: usize = 2;
 
-    // Get player tags from metadata for event data
-    let player_tags: Vec<String> = game
-        .start
+/// Resolve a display tag (connect code, netplay name or "P<n>") for every player
+pub fn player_tags(game: &peppi::game::immutable::Game) -> Vec<String> {
+    game.start
         .players
         .iter()
         .map(|p| {
@@ -34,68 +31,538 @@ pub fn extract_death_events(game: &peppi::game::immutable::Game) -> Result<Vec<G
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| format!("P{}", port))
         })
-        .collect();
+        .collect()
+}
+
+/// Indices of all frames in `game.frames`, skipping rollback frames
+pub fn playable_frames(game: &peppi::game::immutable::Game) -> impl Iterator<Item = usize> {
+    let rollbacks = game.frames.rollbacks(Rollbacks::ExceptLast);
+    (0..game.frames.len()).filter(move |&idx| !rollbacks[idx])
+}
+
+/// Convert a frame number to seconds (Melee runs at 60fps)
+pub fn frame_to_seconds(frame: i32) -> f64 {
+    frame as f64 / 60.0
+}
+
+/// A player's action state on a frame, or None past the end of their data
+pub fn state_at(port: &PortData, frame_idx: usize) -> Option<u16> {
+    port.leader.post.state.get(frame_idx)
+}
+
+/// A player's damage percent on a frame
+pub fn percent_at(port: &PortData, frame_idx: usize) -> Option<f32> {
+    port.leader.post.percent.get(frame_idx)
+}
+
+/// A player's remaining stocks on a frame
+pub fn stocks_at(port: &PortData, frame_idx: usize) -> Option<u8> {
+    port.leader.post.stocks.get(frame_idx)
+}
+
+/// What the hit-string tracker reads of one player on one frame
+#[derive(Debug, Clone, Copy, Default)]
+struct PortFrame {
+    state: u16,
+    percent: f32,
+    stocks: Option<u8>,
+    /// Port of whoever hit this player last
+    last_hit_by: Option<u8>,
+    /// Move this player last landed, 0 for none
+    last_attack_landed: u8,
+    /// Frames into the current action state, on replays that record it
+    state_age: Option<f32>,
+}
+
+impl PortFrame {
+    fn at(port: &PortData, frame_idx: usize) -> Option<Self> {
+        let post = &port.leader.post;
+        Some(Self {
+            state: state_at(port, frame_idx)?,
+            percent: percent_at(port, frame_idx).unwrap_or(0.0),
+            stocks: stocks_at(port, frame_idx),
+            last_hit_by: post.last_hit_by.get(frame_idx),
+            last_attack_landed: post.last_attack_landed.get(frame_idx).unwrap_or(0),
+            state_age: post.state_age.as_ref().and_then(|age| age.get(frame_idx)),
+        })
+    }
+}
+
+/// Blast zone for a death animation state, or None if the player isn't dying
+fn blast_zone(state: u16) -> Option<BlastZone> {
+    match Common::try_from(state).ok()? {
+        Common::DeadDown => Some(BlastZone::Bottom),
+        Common::DeadLeft => Some(BlastZone::Left),
+        Common::DeadRight => Some(BlastZone::Right),
+        Common::DeadUp
+        | Common::DeadUpStar
+        | Common::DeadUpStarIce
+        | Common::DeadUpFall
+        | Common::DeadUpFallHitCamera
+        | Common::DeadUpFallHitCameraFlat
+        | Common::DeadUpFallIce
+        | Common::DeadUpFallHitCameraIce => Some(BlastZone::Top),
+        _ => None,
+    }
+}
+
+/// Extract death events from a parsed game
+///
+/// This function iterates through all frames and detects when a player enters
+/// a death animation state, recording the frame number and player information
+/// along with the percent they died at, who killed them and with what.
+pub fn extract_death_events(game: &peppi::game::immutable::Game) -> Result<Vec<GameEvent>, Error> {
+    log::info!("🔍 Extracting death events from game data");
+
+    let mut events = Vec::new();
+    let mut is_dead = vec![false; game.frames.ports.len()];
+
+    // Get player tags from metadata for event data
+    let player_tags = player_tags(game);
+    let mut prev_idx = None;
 
     // Iterate through frames looking for death states
-    for frame_idx in 0..game.frames.len() {
-        // Skip rollback frames
-        if rollbacks[frame_idx] {
-            continue;
-        }
+    for frame_idx in playable_frames(game) {
+        let before = prev_idx.unwrap_or(frame_idx);
+        prev_idx = Some(frame_idx);
 
         for (port_idx, port_data) in game.frames.ports.iter().enumerate() {
             // Check if player entered a death state
-            let death_detected = match port_data
+            let blast_zone = state_at(port_data, frame_idx).and_then(blast_zone);
+
+            let Some(blast_zone) = blast_zone else {
+                is_dead[port_idx] = false;
+                continue;
+            };
+            if is_dead[port_idx] {
+                continue;
+            }
+            is_dead[port_idx] = true;
+
+            let frame = game.frames.id.get(frame_idx).unwrap_or(0);
+            let timestamp = frame_to_seconds(frame);
+            let port = u8::from(game.start.players[port_idx].port);
+            let player_tag = player_tags
+                .get(port_idx)
+                .cloned()
+                .unwrap_or_else(|| format!("P{}", port));
+
+            let percent = percent_at(port_data, before).unwrap_or(0.0);
+            // Stocks tick down on the death frame on most versions, but not all
+            let stocks_before = stocks_at(port_data, before).unwrap_or(0);
+            let stocks_now = stocks_at(port_data, frame_idx).unwrap_or(stocks_before);
+            let stocks_remaining = if stocks_now == stocks_before {
+                stocks_before.saturating_sub(1)
+            } else {
+                stocks_now
+            };
+
+            // `last_hit_by` holds the port of whoever hit the victim last
+            let killer_idx = port_data
                 .leader
                 .post
-                .state
-                .get(frame_idx)
-                .and_then(|s| Common::try_from(s).ok())
-            {
-                Some(Common::DeadDown)
-                | Some(Common::DeadLeft)
-                | Some(Common::DeadRight)
-                | Some(Common::DeadUp)
-                | Some(Common::DeadUpStar)
-                | Some(Common::DeadUpStarIce)
-                | Some(Common::DeadUpFall)
-                | Some(Common::DeadUpFallHitCamera)
-                | Some(Common::DeadUpFallHitCameraFlat)
-                | Some(Common::DeadUpFallIce)
-                | Some(Common::DeadUpFallHitCameraIce) => true,
-                _ => false,
+                .last_hit_by
+                .get(before)
+                .and_then(|by| {
+                    game.start
+                        .players
+                        .iter()
+                        .position(|p| u8::from(p.port) == by)
+                })
+                .filter(|&idx| idx != port_idx);
+
+            let kill_move = killer_idx
+                .and_then(|idx| {
+                    game.frames.ports[idx]
+                        .leader
+                        .post
+                        .last_attack_landed
+                        .get(before)
+                })
+                .filter(|&id| id != 0);
+
+            let event = DeathEvent {
+                frame,
+                timestamp,
+                port,
+                player_tag,
+                percent,
+                stocks_remaining,
+                blast_zone,
+                killer_port: killer_idx.map(|idx| u8::from(game.start.players[idx].port)),
+                killer_tag: killer_idx.and_then(|idx| player_tags.get(idx).cloned()),
+                killer_character: killer_idx.map(|idx| game.start.players[idx].character as u8),
+                kill_move,
+                kill_move_name: kill_move.map(|id| move_name(id).to_string()),
             };
 
-            if death_detected && !is_dead[port_idx] {
-                is_dead[port_idx] = true;
+            log::info!(
+                "💀 Death detected on frame {} ({:.2}s): {} at {:.0}% off the {:?}",
+                frame,
+                timestamp,
+                event.player_tag,
+                event.percent,
+                event.blast_zone
+            );
 
-                let frame = game.frames.id.get(frame_idx).unwrap_or(0);
-                let timestamp = frame as f64 / 60.0; // Melee runs at 60fps
-                let port = game.start.players[port_idx].port;
-                let player_tag = player_tags
-                    .get(port_idx)
-                    .cloned()
-                    .unwrap_or_else(|| format!("P{}", u8::from(port)));
+            events.push(GameEvent::Death(event));
+        }
+    }
 
-                log::info!(
-                    "💀 Death detected: {} on frame {} ({:.2}s)",
-                    player_tag,
-                    frame,
-                    timestamp
-                );
+    log::info!("✅ Extracted {} death events", events.len());
+    Ok(events)
+}
+
+/// How a hit string is continued and terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitStringRules {
+    /// Combos are kept alive by shieldstun too, and end once the victim has
+    /// been back in control for `HIT_STRING_TIMEOUT` frames or dies
+    Combo,
+    /// Conversions (punishes) only end once the victim has been actionable
+    /// for `HIT_STRING_TIMEOUT` frames
+    Conversion,
+}
+
+/// Per attacker/victim hit-string tracking state
+#[derive(Default)]
+struct HitStringTracker {
+    string: Option<ComboEvent>,
+    current_move: Option<usize>,
+    reset_counter: u32,
+    last_hit_animation: Option<u16>,
+}
+
+impl HitStringTracker {
+    fn finish(&mut self) -> Option<ComboEvent> {
+        self.current_move = None;
+        self.reset_counter = 0;
+        self.last_hit_animation = None;
+        self.string.take()
+    }
+}
+
+/// Extract combo events from a parsed game
+///
+/// A combo starts when a victim enters hitstun or a grab and continues while
+/// they stay in hitstun, shieldstun or grabbed. It ends once the victim has
+/// been back in control for `HIT_STRING_TIMEOUT` frames or loses a stock.
+pub fn extract_combo_events(game: &peppi::game::immutable::Game) -> Result<Vec<GameEvent>, Error> {
+    log::info!("🔍 Extracting combo events from game data");
 
-                events.push(GameEvent::Death(DeathEvent {
+    let events: Vec<GameEvent> = extract_hit_strings(game, HitStringRules::Combo)
+        .into_iter()
+        .filter(|combo| combo.moves.len() >= MIN_COMBO_MOVES)
+        .map(GameEvent::Combo)
+        .collect();
+
+    log::info!("✅ Extracted {} combo events", events.len());
+    Ok(events)
+}
+
+/// Track every hit string for all attacker/victim pairs, ordered by start frame
+pub fn extract_hit_strings(
+    game: &peppi::game::immutable::Game,
+    rules: HitStringRules,
+) -> Vec<ComboEvent> {
+    let ports = &game.frames.ports;
+    let port_count = ports.len();
+    let one_on_one = port_count == 2;
+    let player_tags = player_tags(game);
+    let port_numbers: Vec<u8> = game
+        .start
+        .players
+        .iter()
+        .map(|p| u8::from(p.port))
+        .collect();
+
+    let mut trackers: Vec<HitStringTracker> = (0..port_count * port_count)
+        .map(|_| HitStringTracker::default())
+        .collect();
+    let mut strings = Vec::new();
+    let mut prev_frames: Option<Vec<Option<PortFrame>>> = None;
+    let mut last_frame = 0;
+
+    for frame_idx in playable_frames(game) {
+        let frame = game.frames.id.get(frame_idx).unwrap_or(0);
+        last_frame = frame;
+        let frames: Vec<Option<PortFrame>> = ports
+            .iter()
+            .map(|port| PortFrame::at(port, frame_idx))
+            .collect();
+
+        if let Some(prev_frames) = &prev_frames {
+            for attacker in 0..port_count {
+                for victim in 0..port_count {
+                    if attacker == victim {
+                        continue;
+                    }
+                    let (Some(attacker_now), Some(victim_now)) =
+                        (&frames[attacker], &frames[victim])
+                    else {
+                        continue;
+                    };
+
+                    let tracker = &mut trackers[attacker * port_count + victim];
+                    let finished = step_hit_string(
+                        tracker,
+                        rules,
+                        attacker_now,
+                        prev_frames[attacker].as_ref(),
+                        victim_now,
+                        prev_frames[victim].as_ref(),
+                        port_numbers[attacker],
+                        one_on_one,
+                        frame,
+                    );
+
+                    if let Some(mut string) = finished {
+                        label_hit_string(
+                            &mut string,
+                            &player_tags,
+                            attacker,
+                            victim,
+                            &port_numbers,
+                        );
+                        strings.push(string);
+                    }
+                }
+            }
+        }
+
+        prev_frames = Some(frames);
+    }
+
+    // Close out any hit string still running when the game ended
+    for attacker in 0..port_count {
+        for victim in 0..port_count {
+            if attacker == victim {
+                continue;
+            }
+            if let Some(mut string) = trackers[attacker * port_count + victim].finish() {
+                string.end_frame = last_frame;
+                string.end_timestamp = frame_to_seconds(last_frame);
+                string.damage = (string.end_percent - string.start_percent).max(0.0);
+                label_hit_string(&mut string, &player_tags, attacker, victim, &port_numbers);
+                strings.push(string);
+            }
+        }
+    }
+
+    strings.sort_by_key(|s| s.frame);
+    strings
+}
+
+fn label_hit_string(
+    string: &mut ComboEvent,
+    player_tags: &[String],
+    attacker: usize,
+    victim: usize,
+    port_numbers: &[u8],
+) {
+    string.attacker_port = port_numbers[attacker];
+    string.victim_port = port_numbers[victim];
+    string.attacker_tag = player_tags
+        .get(attacker)
+        .cloned()
+        .unwrap_or_else(|| format!("P{}", port_numbers[attacker]));
+    string.victim_tag = player_tags
+        .get(victim)
+        .cloned()
+        .unwrap_or_else(|| format!("P{}", port_numbers[victim]));
+}
+
+/// Advance one attacker/victim tracker by a frame, returning the hit string if it ended
+#[allow(clippy::too_many_arguments)]
+fn step_hit_string(
+    tracker: &mut HitStringTracker,
+    rules: HitStringRules,
+    attacker: &PortFrame,
+    prev_attacker: Option<&PortFrame>,
+    victim: &PortFrame,
+    prev_victim: Option<&PortFrame>,
+    attacker_port: u8,
+    one_on_one: bool,
+    frame: i32,
+) -> Option<ComboEvent> {
+    let victim_state = victim.state;
+    let attacker_state = attacker.state;
+    let prev_attacker_state = prev_attacker.map(|p| p.state);
+
+    let victim_percent = victim.percent;
+    let prev_victim_percent = prev_victim.map_or(victim_percent, |p| p.percent);
+    let damage_taken = (victim_percent - prev_victim_percent).max(0.0);
+
+    // Free-for-alls need to attribute the hit to the right attacker
+    let attributable = one_on_one || victim.last_hit_by == Some(attacker_port);
+
+    // Once the attacker's animation changes (or restarts) the next hit is a new move
+    let animation_restarted = tracker.last_hit_animation.is_some()
+        && match (attacker.state_age, prev_attacker.and_then(|p| p.state_age)) {
+            (Some(current), Some(previous)) => current < previous,
+            _ => false,
+        };
+    if tracker.last_hit_animation != Some(attacker_state) || animation_restarted {
+        tracker.last_hit_animation = None;
+    }
+
+    let punished = states::is_damaged(victim_state)
+        || states::is_grabbed(victim_state)
+        || states::is_command_grabbed(victim_state);
+
+    if punished && attributable {
+        let string = tracker.string.get_or_insert_with(|| ComboEvent {
+            frame,
+            timestamp: frame_to_seconds(frame),
+            end_frame: frame,
+            end_timestamp: frame_to_seconds(frame),
+            attacker_port,
+            attacker_tag: String::new(),
+            victim_port: 0,
+            victim_tag: String::new(),
+            moves: Vec::new(),
+            start_percent: prev_victim_percent,
+            end_percent: victim_percent,
+            damage: 0.0,
+            did_kill: false,
+        });
+
+        if damage_taken > 0.0 {
+            if tracker.last_hit_animation.is_none() {
+                let move_id = attacker.last_attack_landed;
+                string.moves.push(ComboMove {
                     frame,
-                    timestamp,
-                    port: u8::from(port),
-                    player_tag,
-                }));
-            } else if !death_detected {
-                is_dead[port_idx] = false;
+                    move_id,
+                    move_name: move_name(move_id).to_string(),
+                    hit_count: 0,
+                    damage: 0.0,
+                });
+                tracker.current_move = Some(string.moves.len() - 1);
             }
+
+            if let Some(current) = tracker.current_move.and_then(|i| string.moves.get_mut(i)) {
+                current.hit_count += 1;
+                current.damage += damage_taken;
+            }
+
+            tracker.last_hit_animation = prev_attacker_state;
         }
     }
 
-    log::info!("✅ Extracted {} death events", events.len());
-    Ok(events)
+    let string = tracker.string.as_mut()?;
+
+    let lost_stock = match (prev_victim.and_then(|p| p.stocks), victim.stocks) {
+        (Some(previous), Some(current)) => current < previous,
+        _ => false,
+    };
+
+    if !lost_stock {
+        string.end_percent = victim_percent;
+    }
+
+    let combo = rules == HitStringRules::Combo;
+    let pressured = combo && states::is_in_shieldstun(victim_state);
+    if punished || pressured {
+        tracker.reset_counter = 0;
+    }
+
+    let start_reset = tracker.reset_counter == 0 && states::is_in_control(victim_state);
+    if start_reset || tracker.reset_counter > 0 {
+        tracker.reset_counter += 1;
+    }
+
+    let mut terminate = tracker.reset_counter > HIT_STRING_TIMEOUT;
+    if lost_stock || (combo && states::is_dead(victim_state)) {
+        string.did_kill = true;
+        terminate = true;
+    }
+
+    if !terminate {
+        return None;
+    }
+
+    string.end_frame = frame;
+    string.end_timestamp = frame_to_seconds(frame);
+    string.damage = (string.end_percent - string.start_percent).max(0.0);
+    tracker.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: u16 = 0x00E;
+    const ATTACK_HI3: u16 = 0x039;
+    const ATTACK_AIR_F: u16 = 0x042;
+
+    fn port_frame(state: u16, percent: f32, last_attack_landed: u8, stocks: u8) -> PortFrame {
+        PortFrame {
+            state,
+            percent,
+            stocks: Some(stocks),
+            last_hit_by: None,
+            last_attack_landed,
+            state_age: None,
+        }
+    }
+
+    /// Run one attacker/victim pair through `(attacker, victim)` frames
+    fn hit_strings(rules: HitStringRules, frames: &[(PortFrame, PortFrame)]) -> Vec<ComboEvent> {
+        let mut tracker = HitStringTracker::default();
+        let mut strings = Vec::new();
+        for (i, pair) in frames.windows(2).enumerate() {
+            let (prev_attacker, prev_victim) = &pair[0];
+            let (attacker, victim) = &pair[1];
+            strings.extend(step_hit_string(
+                &mut tracker,
+                rules,
+                attacker,
+                Some(prev_attacker),
+                victim,
+                Some(prev_victim),
+                1,
+                true,
+                i as i32 + 1,
+            ));
+        }
+        strings.extend(tracker.finish());
+        strings
+    }
+
+    #[test]
+    fn test_two_moves_then_escape_make_one_combo() {
+        let mut frames = vec![(port_frame(WAIT, 0.0, 0, 4), port_frame(WAIT, 0.0, 0, 4))];
+        // Forward air, then up tilt while the victim's still in hitstun
+        for (state, percent, move_id) in [(ATTACK_AIR_F, 12.0, 14), (ATTACK_HI3, 21.0, 8)] {
+            for _ in 0..10 {
+                frames.push((
+                    port_frame(state, 0.0, move_id, 4),
+                    port_frame(states::DAMAGE_START, percent, 0, 4),
+                ));
+            }
+        }
+        // The victim gets away
+        for _ in 0..=HIT_STRING_TIMEOUT + 1 {
+            frames.push((port_frame(WAIT, 0.0, 8, 4), port_frame(WAIT, 21.0, 0, 4)));
+        }
+
+        let strings = hit_strings(HitStringRules::Combo, &frames);
+        assert_eq!(strings.len(), 1);
+        let combo = &strings[0];
+        let moves: Vec<&str> = combo.moves.iter().map(|m| m.move_name.as_str()).collect();
+        assert_eq!(moves, vec!["Forward Air", "Up Tilt"]);
+        assert_eq!(combo.frame, 1);
+        assert_eq!(combo.damage, 21.0);
+        assert!(!combo.did_kill);
+        // Ended by the timeout, not at the end of the frames
+        assert!(combo.end_frame < frames.len() as i32 - 1);
+
+        // Taking the stock ends it as a kill
+        let mut frames = frames[..21].to_vec();
+        frames.push((port_frame(WAIT, 0.0, 8, 4), port_frame(0x000, 0.0, 0, 3)));
+        let strings = hit_strings(HitStringRules::Combo, &frames);
+        assert_eq!(strings.len(), 1);
+        assert!(strings[0].did_kill);
+        assert_eq!(strings[0].end_frame, 21);
+    }
 }
@@ -1,10 +1,18 @@
 // Slippi replay file parsing and event extraction module
 
 pub mod events;
+pub mod moves;
 pub mod parser;
+pub mod states;
+pub mod stats;
+pub mod stream;
+pub mod sync;
+pub mod tech;
 pub mod types;
 
 // Re-export commonly used items
-pub use events::extract_death_events;
+pub use events::{extract_combo_events, extract_death_events};
 pub use parser::parse_slp_file;
-pub use types::GameEvent;
+pub use stats::compute_game_stats;
+pub use tech::compute_tech_stats;
+pub use types::{GameEndReason, GameEvent, GameStats, LiveEvent, TechStats};
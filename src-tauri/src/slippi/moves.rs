@@ -0,0 +1,38 @@
+// Move (attack) IDs as reported in post-frame `last_attack_landed`
+
+/// Get a human readable name for a Slippi move ID
+pub fn move_name(move_id: u8) -> &'static str {
+    match move_id {
+        1 => "Miscellaneous",
+        2 => "Jab",
+        3 => "Jab",
+        4 => "Jab",
+        5 => "Rapid Jabs",
+        6 => "Dash Attack",
+        7 => "Forward Tilt",
+        8 => "Up Tilt",
+        9 => "Down Tilt",
+        10 => "Forward Smash",
+        11 => "Up Smash",
+        12 => "Down Smash",
+        13 => "Neutral Air",
+        14 => "Forward Air",
+        15 => "Back Air",
+        16 => "Up Air",
+        17 => "Down Air",
+        18 => "Neutral B",
+        19 => "Side B",
+        20 => "Up B",
+        21 => "Down B",
+        50 => "Getup Attack",
+        51 => "Getup Attack (Slow)",
+        52 => "Grab Pummel",
+        53 => "Forward Throw",
+        54 => "Back Throw",
+        55 => "Up Throw",
+        56 => "Down Throw",
+        61 => "Edge Attack (Slow)",
+        62 => "Edge Attack",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,184 @@
+// Action state classification helpers
+//
+// Ranges follow the action state IDs used by slippi-js so that our stats line
+// up with what players see in the Slippi Launcher.
+
+/// Dying / respawn animations (DeadDown through DeadUpFallHitCameraIce)
+pub const DYING_START: u16 = 0x000;
+pub const DYING_END: u16 = 0x00A;
+
+/// Grounded actionable states (Wait through KneeBend)
+pub const GROUNDED_CONTROL_START: u16 = 0x00E;
+pub const GROUNDED_CONTROL_END: u16 = 0x018;
+
+pub const SQUAT_START: u16 = 0x027;
+pub const SQUAT_END: u16 = 0x029;
+
+pub const DAMAGE_FALL: u16 = 0x026;
+pub const GROUND_ATTACK_START: u16 = 0x02C;
+pub const GROUND_ATTACK_END: u16 = 0x040;
+
+/// Hitstun (DamageHi1 through DamageFlyRoll)
+pub const DAMAGE_START: u16 = 0x04B;
+pub const DAMAGE_END: u16 = 0x05B;
+
+/// Shield states (GuardOn through GuardReflect)
+pub const GUARD_START: u16 = 0x0B2;
+pub const GUARD_END: u16 = 0x0B6;
+pub const GUARD_SET_OFF: u16 = 0x0B5;
+
+/// Missed techs / lying on the ground (DownBoundU through DownSpotD)
+pub const DOWN_START: u16 = 0x0B7;
+pub const DOWN_END: u16 = 0x0C6;
+
+/// Techs in place, rolls and wall/ceiling techs (Passive through PassiveCeil)
+pub const TECH_START: u16 = 0x0C7;
+pub const TECH_END: u16 = 0x0CC;
+
+pub const JAB_RESET_UP: u16 = 0x0B9;
+pub const JAB_RESET_DOWN: u16 = 0x0C1;
+
+pub const GRAB: u16 = 0x0D4;
+
+/// Being held by a grab (CapturePulledHi through CaptureFoot)
+pub const CAPTURE_START: u16 = 0x0DF;
+pub const CAPTURE_END: u16 = 0x0E8;
+
+/// Character-specific command grabs (Kirby/Ganon/Yoshi/Koopa/etc.)
+pub const COMMAND_GRAB_RANGE1_START: u16 = 0x10A;
+pub const COMMAND_GRAB_RANGE1_END: u16 = 0x130;
+pub const COMMAND_GRAB_RANGE2_START: u16 = 0x147;
+pub const COMMAND_GRAB_RANGE2_END: u16 = 0x152;
+pub const BARREL_WAIT: u16 = 0x125;
+
+/// Movement states used by the tech-skill pass
+pub const TURN: u16 = 0x012;
+pub const DASH: u16 = 0x014;
+pub const KNEE_BEND: u16 = 0x018;
+pub const LANDING_FALL_SPECIAL: u16 = 0x02B;
+pub const ESCAPE_AIR: u16 = 0x0EC;
+pub const CLIFF_WAIT: u16 = 0x0FD;
+
+/// Aerial landing lag (LandingAirN through LandingAirLw), where L-cancels apply
+pub const LANDING_AIR_START: u16 = 0x046;
+pub const LANDING_AIR_END: u16 = 0x04A;
+
+/// Missed tech bounces (DownBoundU / DownBoundD)
+pub const DOWN_BOUND_UP: u16 = 0x0B7;
+pub const DOWN_BOUND_DOWN: u16 = 0x0BF;
+
+pub fn is_dead(state: u16) -> bool {
+    (DYING_START..=DYING_END).contains(&state)
+}
+
+pub fn is_damaged(state: u16) -> bool {
+    (DAMAGE_START..=DAMAGE_END).contains(&state)
+        || state == DAMAGE_FALL
+        || state == JAB_RESET_UP
+        || state == JAB_RESET_DOWN
+}
+
+pub fn is_grabbed(state: u16) -> bool {
+    (CAPTURE_START..=CAPTURE_END).contains(&state)
+}
+
+pub fn is_command_grabbed(state: u16) -> bool {
+    ((COMMAND_GRAB_RANGE1_START..=COMMAND_GRAB_RANGE1_END).contains(&state)
+        || (COMMAND_GRAB_RANGE2_START..=COMMAND_GRAB_RANGE2_END).contains(&state))
+        && state != BARREL_WAIT
+}
+
+pub fn is_down(state: u16) -> bool {
+    (DOWN_START..=DOWN_END).contains(&state)
+}
+
+pub fn is_teching(state: u16) -> bool {
+    (TECH_START..=TECH_END).contains(&state)
+}
+
+/// Shieldstun - the defender is pressured but not punished
+pub fn is_in_shieldstun(state: u16) -> bool {
+    state == GUARD_SET_OFF
+}
+
+pub fn is_shielding(state: u16) -> bool {
+    (GUARD_START..=GUARD_END).contains(&state)
+}
+
+pub fn is_aerial_landing(state: u16) -> bool {
+    (LANDING_AIR_START..=LANDING_AIR_END).contains(&state)
+}
+
+pub fn is_missed_tech(state: u16) -> bool {
+    state == DOWN_BOUND_UP || state == DOWN_BOUND_DOWN
+}
+
+/// Whether the player has regained control of their character
+pub fn is_in_control(state: u16) -> bool {
+    let ground = (GROUNDED_CONTROL_START..=GROUNDED_CONTROL_END).contains(&state);
+    let squat = (SQUAT_START..=SQUAT_END).contains(&state);
+    let ground_attack = state > GROUND_ATTACK_START && state <= GROUND_ATTACK_END;
+    ground || squat || ground_attack || state == GRAB
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_ranges_do_not_overlap() {
+        let ranges = [
+            ("dying", DYING_START, DYING_END),
+            (
+                "grounded control",
+                GROUNDED_CONTROL_START,
+                GROUNDED_CONTROL_END,
+            ),
+            ("damage fall", DAMAGE_FALL, DAMAGE_FALL),
+            ("squat", SQUAT_START, SQUAT_END),
+            ("ground attack", GROUND_ATTACK_START, GROUND_ATTACK_END),
+            ("aerial landing", LANDING_AIR_START, LANDING_AIR_END),
+            ("damage", DAMAGE_START, DAMAGE_END),
+            ("guard", GUARD_START, GUARD_END),
+            ("down", DOWN_START, DOWN_END),
+            ("tech", TECH_START, TECH_END),
+            ("grab", GRAB, GRAB),
+            ("capture", CAPTURE_START, CAPTURE_END),
+            (
+                "command grab",
+                COMMAND_GRAB_RANGE1_START,
+                COMMAND_GRAB_RANGE1_END,
+            ),
+            (
+                "command grab",
+                COMMAND_GRAB_RANGE2_START,
+                COMMAND_GRAB_RANGE2_END,
+            ),
+        ];
+        for (i, (name, start, end)) in ranges.iter().enumerate() {
+            assert!(start <= end, "{} range is backwards", name);
+            for (other, other_start, other_end) in &ranges[i + 1..] {
+                assert!(
+                    end < other_start || other_end < start,
+                    "{} overlaps {}",
+                    name,
+                    other
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_classifies_states() {
+        assert!(is_dead(0x004));
+        assert!(!is_dead(0x00E));
+        assert!(is_damaged(0x04B) && is_damaged(DAMAGE_FALL));
+        // Jab resets are hit while down
+        assert!(is_damaged(JAB_RESET_UP) && is_down(JAB_RESET_UP));
+        assert!(is_grabbed(0x0E3));
+        assert!(is_command_grabbed(0x10A) && !is_command_grabbed(BARREL_WAIT));
+        assert!(is_in_control(0x00E) && !is_in_control(0x04B));
+        assert!(is_in_shieldstun(GUARD_SET_OFF) && is_shielding(GUARD_SET_OFF));
+        assert!(is_missed_tech(DOWN_BOUND_UP) && !is_teching(DOWN_BOUND_UP));
+    }
+}
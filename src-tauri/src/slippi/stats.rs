@@ -0,0 +1,172 @@
+// Conversion (punish) tracking and per-player summary stats
+
+use super::events::{extract_hit_strings, player_tags, HitStringRules};
+use super::types::{ComboEvent, Conversion, GameStats, OpeningType, PlayerStats};
+use crate::commands::errors::Error;
+
+/// Compute every conversion in the game plus per-player summary stats
+///
+/// Conversions follow the same hit-string tracking as combos, but only end once
+/// the victim has been back in control for a while. Each conversion is then
+/// classified by how the opening was won (neutral win, counter hit or trade).
+pub fn compute_game_stats(game: &peppi::game::immutable::Game) -> Result<GameStats, Error> {
+    log::info!("📈 Computing conversion stats from game data");
+
+    let strings = extract_hit_strings(game, HitStringRules::Conversion);
+    let conversions = classify_openings(strings);
+
+    let tags = player_tags(game);
+    let players = game
+        .start
+        .players
+        .iter()
+        .enumerate()
+        .map(|(idx, player)| {
+            let port = u8::from(player.port);
+            let player_tag = tags
+                .get(idx)
+                .cloned()
+                .unwrap_or_else(|| format!("P{}", port));
+            summarize_player(port, player_tag, &conversions)
+        })
+        .collect();
+
+    log::info!("✅ Computed {} conversions", conversions.len());
+    Ok(GameStats {
+        conversions,
+        players,
+    })
+}
+
+/// Decide how each opening was won, based on what else was happening at its start frame
+fn classify_openings(strings: Vec<ComboEvent>) -> Vec<Conversion> {
+    let mut conversions: Vec<Conversion> = Vec::with_capacity(strings.len());
+
+    for (idx, string) in strings.iter().enumerate() {
+        // Both players hit each other on the same frame
+        let traded = strings.iter().enumerate().any(|(other_idx, other)| {
+            other_idx != idx
+                && other.attacker_port == string.victim_port
+                && other.victim_port == string.attacker_port
+                && other.frame == string.frame
+        });
+
+        // The attacker was still being punished by the victim when they got their hit in
+        let countered = strings.iter().any(|other| {
+            other.attacker_port == string.victim_port
+                && other.victim_port == string.attacker_port
+                && other.frame < string.frame
+                && other.end_frame >= string.frame
+        });
+
+        let opening_type = if traded {
+            OpeningType::Trade
+        } else if countered {
+            OpeningType::CounterAttack
+        } else {
+            OpeningType::NeutralWin
+        };
+
+        conversions.push(Conversion {
+            frame: string.frame,
+            timestamp: string.timestamp,
+            end_frame: string.end_frame,
+            end_timestamp: string.end_timestamp,
+            attacker_port: string.attacker_port,
+            victim_port: string.victim_port,
+            opening_type,
+            moves: string.moves.clone(),
+            start_percent: string.start_percent,
+            end_percent: string.end_percent,
+            damage: string.damage,
+            did_kill: string.did_kill,
+        });
+    }
+
+    conversions
+}
+
+fn summarize_player(port: u8, player_tag: String, conversions: &[Conversion]) -> PlayerStats {
+    let own: Vec<&Conversion> = conversions
+        .iter()
+        .filter(|c| c.attacker_port == port)
+        .collect();
+
+    let conversion_count = own.len() as u32;
+    let successful_conversions = own.iter().filter(|c| c.moves.len() > 1).count() as u32;
+    let kill_count = own.iter().filter(|c| c.did_kill).count() as u32;
+    let total_damage: f32 = own.iter().map(|c| c.damage).sum();
+    let count_opening =
+        |kind: OpeningType| own.iter().filter(|c| c.opening_type == kind).count() as u32;
+
+    PlayerStats {
+        port,
+        player_tag,
+        conversion_count,
+        successful_conversions,
+        kill_count,
+        total_damage,
+        neutral_wins: count_opening(OpeningType::NeutralWin),
+        counter_hits: count_opening(OpeningType::CounterAttack),
+        trades: count_opening(OpeningType::Trade),
+        openings_per_kill: (kill_count > 0).then(|| conversion_count as f32 / kill_count as f32),
+        damage_per_opening: (conversion_count > 0).then(|| total_damage / conversion_count as f32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(attacker_port: u8, victim_port: u8, frame: i32, end_frame: i32) -> ComboEvent {
+        ComboEvent {
+            frame,
+            timestamp: 0.0,
+            end_frame,
+            end_timestamp: 0.0,
+            attacker_port,
+            attacker_tag: String::new(),
+            victim_port,
+            victim_tag: String::new(),
+            moves: Vec::new(),
+            start_percent: 0.0,
+            end_percent: 0.0,
+            damage: 0.0,
+            did_kill: false,
+        }
+    }
+
+    fn openings(strings: Vec<ComboEvent>) -> Vec<OpeningType> {
+        classify_openings(strings)
+            .into_iter()
+            .map(|c| c.opening_type)
+            .collect()
+    }
+
+    #[test]
+    fn test_classifies_openings() {
+        // Nothing else going on
+        assert_eq!(
+            openings(vec![string(1, 2, 100, 150)]),
+            vec![OpeningType::NeutralWin]
+        );
+
+        // Hitting each other on the same frame
+        assert_eq!(
+            openings(vec![string(1, 2, 100, 150), string(2, 1, 100, 130)]),
+            vec![OpeningType::Trade, OpeningType::Trade]
+        );
+
+        // Hitting out of the victim's own punish
+        assert_eq!(
+            openings(vec![string(2, 1, 100, 150), string(1, 2, 120, 200)]),
+            vec![OpeningType::NeutralWin, OpeningType::CounterAttack]
+        );
+
+        // In doubles, one player opening up two others on the same frame isn't a trade
+        assert_eq!(
+            openings(vec![string(1, 2, 100, 150), string(1, 3, 100, 150)]),
+            vec![OpeningType::NeutralWin, OpeningType::NeutralWin]
+        );
+    }
+}
@@ -0,0 +1,483 @@
+// Incremental .slp reader for games that are still being written
+//
+// `peppi::io::slippi::read` needs a complete file, so while a game is in
+// progress we decode the raw event stream ourselves. Only the handful of fields
+// needed for live events are read; offsets follow the Slippi replay spec
+// (https://github.com/project-slippi/slippi-wiki/blob/master/SPEC.md) and
+// include the leading command byte.
+
+use super::types::{LiveEvent, LivePlayer};
+use crate::commands::errors::Error;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// `{U\x03raw[$U#l` followed by the (possibly zero) u32 length of the raw element
+const RAW_HEADER: &[u8] = b"{U\x03raw[$U#l";
+const RAW_HEADER_LEN: usize = RAW_HEADER.len() + 4;
+
+const CMD_EVENT_PAYLOADS: u8 = 0x35;
+const CMD_GAME_START: u8 = 0x36;
+const CMD_POST_FRAME: u8 = 0x38;
+const CMD_GAME_END: u8 = 0x39;
+
+const PLAYER_BLOCK_SIZE: usize = 0x24;
+/// Player type 3 means the port is empty
+const PLAYER_TYPE_EMPTY: u8 = 3;
+
+/// How often to check the file for new data
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Give up on a file that hasn't grown for this long (crashed or abandoned game)
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Read errors in a row before a replay is given up on, about `IDLE_TIMEOUT`
+const MAX_READ_ERRORS: u32 = 600;
+
+/// Last known values for a port, used to turn frame data into change events
+#[derive(Debug, Clone, Copy)]
+struct PortSnapshot {
+    percent: f32,
+    stocks: u8,
+}
+
+/// Decodes Slippi's raw event stream from arbitrarily sized chunks
+#[derive(Debug, Default)]
+pub struct SlpStreamDecoder {
+    buf: Vec<u8>,
+    pos: usize,
+    header_read: bool,
+    payload_sizes: HashMap<u8, usize>,
+    ports: HashMap<u8, PortSnapshot>,
+    game_ended: bool,
+    /// Hit a command missing from the payload sizes table, which can't be
+    /// stepped over; nothing after it can be decoded
+    desynced: bool,
+}
+
+impl SlpStreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the GameEnd event has been seen
+    pub fn game_ended(&self) -> bool {
+        self.game_ended
+    }
+
+    /// Whether decoding stopped at a command it couldn't step over. The game's
+    /// end can still be read from the finished file with `read_game_end`.
+    pub fn desynced(&self) -> bool {
+        self.desynced
+    }
+
+    /// Feed newly appended bytes and return any live events they complete
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<LiveEvent>, Error> {
+        let mut events = Vec::new();
+        if self.desynced {
+            return Ok(events);
+        }
+        self.buf.extend_from_slice(data);
+
+        if !self.header_read {
+            if self.buf.len() < RAW_HEADER_LEN {
+                return Ok(events);
+            }
+            if &self.buf[..RAW_HEADER.len()] != RAW_HEADER {
+                return Err(Error::RecordingFailed(
+                    "Not a Slippi replay: missing raw header".to_string(),
+                ));
+            }
+            self.pos = RAW_HEADER_LEN;
+            self.header_read = true;
+        }
+
+        while let Some(&command) = self.buf.get(self.pos) {
+            // The payload sizes event describes every other event, including itself
+            if command == CMD_EVENT_PAYLOADS && self.payload_sizes.is_empty() {
+                let Some(&size) = self.buf.get(self.pos + 1) else {
+                    break;
+                };
+                let size = size as usize;
+                let end = self.pos + 1 + size;
+                if self.buf.len() < end {
+                    break;
+                }
+                for entry in self.buf[self.pos + 2..end].chunks_exact(3) {
+                    let payload = u16::from_be_bytes([entry[1], entry[2]]) as usize;
+                    self.payload_sizes.insert(entry[0], payload);
+                }
+                self.pos = end;
+                continue;
+            }
+
+            // Commands this doesn't handle are stepped over using their listed size
+            let Some(&size) = self.payload_sizes.get(&command) else {
+                log::warn!(
+                    "⚠️ Slippi command 0x{:02X} isn't in the payload sizes, no more live events for this game",
+                    command
+                );
+                self.desynced = true;
+                self.buf.clear();
+                self.pos = 0;
+                return Ok(events);
+            };
+            let end = self.pos + 1 + size;
+            if self.buf.len() < end {
+                break;
+            }
+
+            let payload = self.buf[self.pos..end].to_vec();
+            self.pos = end;
+            self.handle_event(&payload, &mut events);
+        }
+
+        // Drop consumed bytes so long games don't keep the whole file in memory
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        Ok(events)
+    }
+
+    /// The Game End event of a replay Slippi has finished writing, found from
+    /// the raw element's length, which is filled in when the file is closed.
+    /// None while the game is still being written.
+    pub fn read_game_end(&mut self, file: &mut File) -> Result<Option<LiveEvent>, Error> {
+        let Some(&size) = self.payload_sizes.get(&CMD_GAME_END) else {
+            return Ok(None);
+        };
+
+        let mut length = [0u8; 4];
+        file.seek(SeekFrom::Start(RAW_HEADER.len() as u64))?;
+        file.read_exact(&mut length)?;
+        let raw_length = u32::from_be_bytes(length) as u64;
+        let event_length = size as u64 + 1;
+        if raw_length < event_length {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0u8; event_length as usize];
+        file.seek(SeekFrom::Start(
+            RAW_HEADER_LEN as u64 + raw_length - event_length,
+        ))?;
+        file.read_exact(&mut payload)?;
+        if payload[0] != CMD_GAME_END {
+            return Ok(None);
+        }
+
+        let mut events = Vec::new();
+        self.handle_event(&payload, &mut events);
+        Ok(events.pop())
+    }
+
+    fn handle_event(&mut self, payload: &[u8], events: &mut Vec<LiveEvent>) {
+        match payload[0] {
+            CMD_GAME_START => {
+                if let Some(event) = decode_game_start(payload) {
+                    self.ports.clear();
+                    events.push(event);
+                }
+            }
+            CMD_POST_FRAME => self.handle_post_frame(payload, events),
+            CMD_GAME_END => {
+                if let Some(&method) = payload.get(1) {
+                    // -1 (0xFF) when nobody LRAS'd
+                    let lras_initiator = payload.get(2).copied().filter(|&p| p != 0xFF);
+                    self.game_ended = true;
+                    events.push(LiveEvent::GameEnd {
+                        method,
+                        lras_initiator,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_post_frame(&mut self, payload: &[u8], events: &mut Vec<LiveEvent>) {
+        let (Some(frame), Some(&port), Some(&is_follower), Some(percent), Some(&stocks)) = (
+            read_i32(payload, 0x01),
+            payload.get(0x05),
+            payload.get(0x06),
+            read_f32(payload, 0x16),
+            payload.get(0x21),
+        ) else {
+            return;
+        };
+
+        // Nana mirrors Popo's stocks and percent isn't shown for her
+        if is_follower != 0 {
+            return;
+        }
+
+        let current = PortSnapshot { percent, stocks };
+        let Some(previous) = self.ports.insert(port, current) else {
+            return;
+        };
+
+        if stocks < previous.stocks {
+            events.push(LiveEvent::StockLost {
+                frame,
+                port,
+                stocks_remaining: stocks,
+                percent: previous.percent,
+            });
+        } else if percent != previous.percent {
+            events.push(LiveEvent::PercentChange {
+                frame,
+                port,
+                percent,
+            });
+        }
+    }
+}
+
+fn decode_game_start(payload: &[u8]) -> Option<LiveEvent> {
+    let stage = u16::from_be_bytes([*payload.get(0x13)?, *payload.get(0x14)?]);
+
+    let players = (0..4u8)
+        .filter_map(|port| {
+            let base = port as usize * PLAYER_BLOCK_SIZE;
+            let character_id = *payload.get(0x65 + base)?;
+            let player_type = *payload.get(0x66 + base)?;
+            let costume = *payload.get(0x68 + base)?;
+            (player_type != PLAYER_TYPE_EMPTY).then_some(LivePlayer {
+                port,
+                character_id,
+                player_type,
+                costume,
+            })
+        })
+        .collect();
+
+    Some(LiveEvent::GameStart { stage, players })
+}
+
+fn read_i32(payload: &[u8], offset: usize) -> Option<i32> {
+    let bytes = payload.get(offset..offset + 4)?;
+    Some(i32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_f32(payload: &[u8], offset: usize) -> Option<f32> {
+    let bytes = payload.get(offset..offset + 4)?;
+    Some(f32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// Follow a .slp file as Slippi writes it, calling `on_event` for each live event
+///
+/// Blocks until the game ends, `stop` is set, or the file stops growing for
+/// `IDLE_TIMEOUT`. Read errors are retried for about as long. Meant to be run
+/// on its own thread.
+pub fn tail_slp_file<F>(path: &Path, stop: &AtomicBool, mut on_event: F) -> Result<(), Error>
+where
+    F: FnMut(LiveEvent),
+{
+    log::info!("📡 Tailing in-progress replay: {:?}", path);
+
+    let mut file = File::open(path)?;
+    let mut decoder = SlpStreamDecoder::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut last_growth = Instant::now();
+    let mut read_errors = 0;
+
+    while !stop.load(Ordering::SeqCst) {
+        let read = match file.read(&mut chunk) {
+            Ok(read) => {
+                read_errors = 0;
+                read
+            }
+            Err(e) if read_errors < MAX_READ_ERRORS => {
+                if read_errors == 0 {
+                    log::warn!("⚠️ Couldn't read replay {:?}, retrying: {}", path, e);
+                }
+                read_errors += 1;
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if read == 0 {
+            if decoder.desynced() {
+                let position = file.stream_position()?;
+                let game_end = decoder.read_game_end(&mut file);
+                file.seek(SeekFrom::Start(position))?;
+                if let Some(event) = game_end? {
+                    on_event(event);
+                    log::info!("🏁 Finished replay reached game end: {:?}", path);
+                    break;
+                }
+            }
+            if last_growth.elapsed() > IDLE_TIMEOUT {
+                log::warn!("⚠️ Replay stopped growing before game end: {:?}", path);
+                break;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        last_growth = Instant::now();
+
+        for event in decoder.push(&chunk[..read])? {
+            on_event(event);
+        }
+        if decoder.game_ended() {
+            log::info!("🏁 Live replay reached game end: {:?}", path);
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME_START_SIZE: usize = 0x1A0;
+    const POST_FRAME_SIZE: usize = 0x34;
+    const GAME_END_SIZE: usize = 0x02;
+
+    /// A command from a newer Slippi that the decoder doesn't handle
+    const CMD_NEWER: u8 = 0x3F;
+    const NEWER_SIZE: usize = 0x10;
+
+    fn header() -> Vec<u8> {
+        let mut bytes = RAW_HEADER.to_vec();
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.push(CMD_EVENT_PAYLOADS);
+        bytes.push(1 + 3 * 4);
+        for (cmd, size) in [
+            (CMD_GAME_START, GAME_START_SIZE),
+            (CMD_POST_FRAME, POST_FRAME_SIZE),
+            (CMD_GAME_END, GAME_END_SIZE),
+            (CMD_NEWER, NEWER_SIZE),
+        ] {
+            bytes.push(cmd);
+            bytes.extend_from_slice(&(size as u16).to_be_bytes());
+        }
+        bytes
+    }
+
+    fn game_start(stage: u16) -> Vec<u8> {
+        let mut bytes = vec![0u8; GAME_START_SIZE + 1];
+        bytes[0] = CMD_GAME_START;
+        bytes[0x13..0x15].copy_from_slice(&stage.to_be_bytes());
+        for port in 0..4 {
+            let base = port * PLAYER_BLOCK_SIZE;
+            bytes[0x65 + base] = 2 + port as u8;
+            bytes[0x66 + base] = if port < 2 { 0 } else { PLAYER_TYPE_EMPTY };
+            bytes[0x68 + base] = 1;
+        }
+        bytes
+    }
+
+    fn post_frame(frame: i32, port: u8, percent: f32, stocks: u8) -> Vec<u8> {
+        let mut bytes = vec![0u8; POST_FRAME_SIZE + 1];
+        bytes[0] = CMD_POST_FRAME;
+        bytes[0x01..0x05].copy_from_slice(&frame.to_be_bytes());
+        bytes[0x05] = port;
+        bytes[0x16..0x1A].copy_from_slice(&percent.to_be_bytes());
+        bytes[0x21] = stocks;
+        bytes
+    }
+
+    #[test]
+    fn test_decodes_events_split_across_chunks() {
+        let mut stream = header();
+        stream.extend(game_start(31));
+        stream.extend(post_frame(-123, 0, 0.0, 4));
+        stream.extend(post_frame(-122, 0, 12.5, 4));
+        stream.extend(post_frame(-121, 0, 0.0, 3));
+        stream.extend([CMD_GAME_END, 2, 0xFF]);
+
+        // Feed a few bytes at a time, the way a growing file is read
+        let mut decoder = SlpStreamDecoder::new();
+        let mut events = Vec::new();
+        for chunk in stream.chunks(7) {
+            events.extend(decoder.push(chunk).unwrap());
+        }
+
+        assert_eq!(events.len(), 4);
+        match &events[0] {
+            LiveEvent::GameStart { stage, players } => {
+                assert_eq!(*stage, 31);
+                assert_eq!(players.len(), 2);
+                assert_eq!(players[1].character_id, 3);
+            }
+            other => panic!("expected game start, got {:?}", other),
+        }
+        assert!(matches!(
+            events[1],
+            LiveEvent::PercentChange {
+                frame: -122,
+                port: 0,
+                ..
+            }
+        ));
+        match events[2] {
+            LiveEvent::StockLost {
+                stocks_remaining,
+                percent,
+                ..
+            } => {
+                assert_eq!(stocks_remaining, 3);
+                assert_eq!(percent, 12.5);
+            }
+            ref other => panic!("expected stock lost, got {:?}", other),
+        }
+        assert!(matches!(
+            events[3],
+            LiveEvent::GameEnd {
+                method: 2,
+                lras_initiator: None
+            }
+        ));
+        assert!(decoder.game_ended());
+    }
+
+    #[test]
+    fn test_steps_over_commands_it_does_not_handle() {
+        let mut stream = header();
+        stream.extend(game_start(31));
+        stream.extend(post_frame(-123, 0, 0.0, 4));
+        // Listed in the payload sizes, so it can be skipped
+        stream.push(CMD_NEWER);
+        stream.extend([0xAB; NEWER_SIZE]);
+        stream.extend(post_frame(-122, 0, 0.0, 3));
+
+        let mut decoder = SlpStreamDecoder::new();
+        let events = decoder.push(&stream).unwrap();
+        assert!(matches!(events[1], LiveEvent::StockLost { .. }));
+        assert!(!decoder.desynced());
+
+        // Not listed, so nothing after it can be read until the file's finished
+        let mut unlisted = vec![0x4E, 1, 2, 3];
+        unlisted.extend([CMD_GAME_END, 2, 0xFF]);
+        assert!(decoder.push(&unlisted).unwrap().is_empty());
+        assert!(decoder.desynced());
+        assert!(!decoder.game_ended());
+
+        let path = std::env::temp_dir().join("buckwheat_stream_test.slp");
+        std::fs::write(&path, &stream).unwrap();
+        let mut file = File::open(&path).unwrap();
+        assert!(decoder.read_game_end(&mut file).unwrap().is_none());
+
+        // Slippi fills in the raw length once the game's written
+        stream.extend(unlisted);
+        let raw_length = (stream.len() - RAW_HEADER_LEN) as u32;
+        stream[RAW_HEADER.len()..RAW_HEADER_LEN].copy_from_slice(&raw_length.to_be_bytes());
+        std::fs::write(&path, &stream).unwrap();
+        let mut file = File::open(&path).unwrap();
+        assert!(matches!(
+            decoder.read_game_end(&mut file).unwrap(),
+            Some(LiveEvent::GameEnd { method: 2, .. })
+        ));
+        assert!(decoder.game_ended());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_rejects_non_slippi_data() {
+        let mut decoder = SlpStreamDecoder::new();
+        assert!(decoder.push(&[0u8; RAW_HEADER_LEN]).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+// Lining up replay frames with the video recorded alongside them
+//
+// Replay frame numbers become wall-clock times from when the replay started,
+// which the recorder's frame timestamps (see `recorder::frames`) turn into
+// positions in the video. When the replay started, relative to the recording,
+// is calibrated once per replay and kept next to the video as `<video>.sync.json`.
+// Without any of this, the video is assumed to start at frame 0 as before.
+
+use super::events::frame_to_seconds;
+use super::types::GameEvent;
+use crate::commands::errors::Error;
+use crate::recorder::frames::FrameTimestamps;
+use crate::recorder::segmented::SegmentManifest;
+use crate::recorder::set::SetManifest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Number of the first frame in every replay; the countdown runs up to frame 0
+pub const FIRST_FRAME: i32 = -123;
+/// How far either side of the estimate to look for the game appearing
+const SEARCH_SECONDS: f64 = 3.0;
+
+/// How the replay's start was found in the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMethod {
+    /// The replay's `startAt`, to the second
+    StartAt,
+    /// When the replay file was created
+    FileTimes,
+    /// The stage appearing after the black loading screen, 123 frames before "GO!"
+    Visual,
+    /// When the recorder saw the game start, for the later games of a set
+    Set,
+}
+
+/// Where a replay starts in the recording of it, as kept next to the video
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncOffset {
+    /// Seconds after the recording started that the replay's first frame was played
+    pub replay_start_seconds: f64,
+    pub method: SyncMethod,
+    /// File name of the replay this is for, as a video can hold more than one
+    #[serde(default)]
+    pub slp_file: Option<String>,
+}
+
+impl SyncOffset {
+    pub fn path_for(video_path: &str) -> PathBuf {
+        PathBuf::from(format!("{}.sync.json", video_path))
+    }
+
+    /// The offset kept next to `video_path`, if it was worked out for `slp_path`
+    pub fn load(video_path: &str, slp_path: &str) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path_for(video_path)).ok()?;
+        serde_json::from_str::<Self>(&contents)
+            .ok()
+            .filter(|offset| offset.slp_file.is_some() && offset.slp_file == slp_file(slp_path))
+    }
+
+    pub fn write(&self, video_path: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::RecordingFailed(format!("Failed to serialize offset: {}", e)))?;
+        std::fs::write(Self::path_for(video_path), json)?;
+        Ok(())
+    }
+}
+
+pub struct ReplaySync {
+    timestamps: Option<FrameTimestamps>,
+    offset: SyncOffset,
+}
+
+impl ReplaySync {
+    /// Sync for a replay and the video of it, calibrating it the first time
+    pub fn new(
+        video_path: &str,
+        slp_path: &str,
+        game: &peppi::game::immutable::Game,
+    ) -> Option<Self> {
+        // A split recording has no single timeline to sync against yet
+        if SegmentManifest::load(video_path).is_some() {
+            return None;
+        }
+
+        let timestamps = FrameTimestamps::load(video_path);
+        // Only the set's first game is named after the video, so only it has
+        // an offset of its own; the rest are where the set says they started
+        let later_game = SetManifest::load(video_path).and_then(|set| {
+            set.game(slp_path)
+                .filter(|(index, _)| *index > 0)
+                .map(|(_, game)| game.start_seconds)
+        });
+
+        let offset = match (later_game, SyncOffset::load(video_path, slp_path)) {
+            (Some(start_seconds), _) => SyncOffset {
+                replay_start_seconds: timestamps
+                    .as_ref()
+                    .and_then(|timestamps| timestamps.wall_at(start_seconds))
+                    .unwrap_or(start_seconds),
+                method: SyncMethod::Set,
+                slp_file: slp_file(slp_path),
+            },
+            (None, Some(offset)) => offset,
+            (None, None) => {
+                let offset = calibrate(video_path, slp_path, game)?;
+                if let Err(e) = offset.write(video_path) {
+                    log::warn!("Failed to save replay offset: {:?}", e);
+                }
+                offset
+            }
+        };
+        Some(Self { timestamps, offset })
+    }
+
+    pub fn offset(&self) -> &SyncOffset {
+        &self.offset
+    }
+
+    /// Seconds into the video that `frame` was on screen. Before the video
+    /// started this is negative, so callers should clamp it.
+    pub fn video_seconds(&self, frame: i32) -> f64 {
+        let wall = self.offset.replay_start_seconds + frame_to_seconds(frame - FIRST_FRAME);
+        video_seconds_at(self.timestamps.as_ref(), wall)
+    }
+
+    /// The replay frame on screen `seconds` into the video; the inverse of
+    /// `video_seconds`
+    pub fn frame_at(&self, seconds: f64) -> i32 {
+        let wall = self
+            .timestamps
+            .as_ref()
+            .and_then(|timestamps| timestamps.wall_at(seconds))
+            .unwrap_or(seconds);
+        FIRST_FRAME + ((wall - self.offset.replay_start_seconds) * 60.0).round() as i32
+    }
+
+    /// Seconds into the video of a clip marker. Markers count from when the
+    /// app started recording the game, which it does as the replay appears, so
+    /// they're placed from the replay's start.
+    pub fn marker_seconds(&self, elapsed: f64) -> f64 {
+        video_seconds_at(
+            self.timestamps.as_ref(),
+            self.offset.replay_start_seconds + elapsed,
+        )
+    }
+
+    /// Point each event's timestamps at the video rather than the replay
+    pub fn retime(&self, events: &mut [GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::Death(death) => {
+                    death.timestamp = self.video_seconds(death.frame).max(0.0);
+                }
+                GameEvent::Combo(combo) => {
+                    combo.timestamp = self.video_seconds(combo.frame).max(0.0);
+                    combo.end_timestamp = self.video_seconds(combo.end_frame).max(0.0);
+                }
+            }
+        }
+    }
+}
+
+/// Time events from the start of a clip cut `start` seconds into their video,
+/// leaving out those that aren't in it
+pub fn retime_to_clip(events: &mut Vec<GameEvent>, start: f64, duration: f64) {
+    events.retain_mut(|event| {
+        let (timestamp, end_timestamp) = match event {
+            GameEvent::Death(death) => (&mut death.timestamp, None),
+            GameEvent::Combo(combo) => (&mut combo.timestamp, Some(&mut combo.end_timestamp)),
+        };
+        *timestamp -= start;
+        let ends = match end_timestamp {
+            Some(end) => {
+                *end -= start;
+                *end
+            }
+            None => *timestamp,
+        };
+        ends >= 0.0 && *timestamp <= duration
+    });
+}
+
+/// Without frame timestamps the video is assumed to run with the clock
+fn video_seconds_at(timestamps: Option<&FrameTimestamps>, wall: f64) -> f64 {
+    timestamps
+        .and_then(|timestamps| timestamps.pts_at(wall))
+        .unwrap_or(wall)
+}
+
+/// Work out when the replay started in the recording: from the files' times,
+/// then, if FFmpeg can find it, from the game appearing on screen
+pub fn calibrate(
+    video_path: &str,
+    slp_path: &str,
+    game: &peppi::game::immutable::Game,
+) -> Option<SyncOffset> {
+    let timestamps = FrameTimestamps::load(video_path);
+    let recording_started = timestamps
+        .as_ref()
+        .and_then(|timestamps| timestamps.started_at())
+        .or_else(|| file_created(video_path))?;
+
+    let (replay_started, method) = match file_created(slp_path) {
+        Some(created) => (created, SyncMethod::FileTimes),
+        None => (start_at(game)?, SyncMethod::StartAt),
+    };
+    let estimate = SyncOffset {
+        replay_start_seconds: (replay_started - recording_started).num_milliseconds() as f64
+            / 1000.0,
+        method,
+        slp_file: slp_file(slp_path),
+    };
+
+    match find_game_on_screen(
+        video_path,
+        timestamps.as_ref(),
+        estimate.replay_start_seconds,
+    ) {
+        Some(replay_start_seconds) => {
+            log::info!(
+                "🎯 Found the replay start on screen {:+.3}s from the {:?} estimate",
+                replay_start_seconds - estimate.replay_start_seconds,
+                estimate.method
+            );
+            Some(SyncOffset {
+                replay_start_seconds,
+                method: SyncMethod::Visual,
+                slp_file: estimate.slp_file,
+            })
+        }
+        None => Some(estimate),
+    }
+}
+
+/// Look around `estimate` for the black loading screen giving way to the
+/// stage, the replay's first frame
+fn find_game_on_screen(
+    video_path: &str,
+    timestamps: Option<&FrameTimestamps>,
+    estimate: f64,
+) -> Option<f64> {
+    let center = video_seconds_at(timestamps, estimate);
+    let start = (center - SEARCH_SECONDS).max(0.0);
+    let ends = match crate::clip_processor::detect_black_ends(
+        video_path,
+        start,
+        center + SEARCH_SECONDS - start,
+    ) {
+        Ok(ends) => ends,
+        Err(e) => {
+            log::warn!("Couldn't look for the replay start on screen: {:?}", e);
+            return None;
+        }
+    };
+
+    let appeared = closest(&ends, center)?;
+    Some(match timestamps {
+        Some(timestamps) => timestamps.wall_at(appeared)?,
+        None => appeared,
+    })
+}
+
+fn closest(times: &[f64], target: f64) -> Option<f64> {
+    times
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - target).abs().total_cmp(&(b - target).abs()))
+}
+
+fn slp_file(slp_path: &str) -> Option<String> {
+    Path::new(slp_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+fn file_created(path: &str) -> Option<DateTime<Utc>> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.created())
+        .ok()
+        .map(DateTime::<Utc>::from)
+}
+
+fn start_at(game: &peppi::game::immutable::Game) -> Option<DateTime<Utc>> {
+    let start_at = game.metadata.as_ref()?.get("startAt")?.as_str()?;
+    DateTime::parse_from_rfc3339(start_at)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_land_where_they_were_recorded() {
+        // The replay started 1.5s into the recording, and the capture lost a
+        // fifth of a second a couple of seconds later
+        let sync = ReplaySync {
+            timestamps: Some(FrameTimestamps {
+                started_at: "2026-01-01T00:00:00.000Z".to_string(),
+                frames: vec![(0.0, 0.5), (3.0, 3.5), (4.3, 5.0), (10.3, 11.0)],
+            }),
+            offset: SyncOffset {
+                replay_start_seconds: 1.5,
+                method: SyncMethod::FileTimes,
+                slp_file: None,
+            },
+        };
+
+        assert!((sync.video_seconds(FIRST_FRAME) - 1.0).abs() < 1e-9);
+        // Frame 0 comes 123 frames after the first one
+        assert!((sync.video_seconds(0) - (1.0 + 123.0 / 60.0)).abs() < 1e-9);
+        // 360 frames on, past the dropped stretch
+        assert!((sync.video_seconds(FIRST_FRAME + 360) - 6.8).abs() < 1e-9);
+        assert_eq!(sync.frame_at(6.8), FIRST_FRAME + 360);
+        assert_eq!(sync.frame_at(sync.video_seconds(0)), 0);
+    }
+
+    #[test]
+    fn test_offsets_are_kept_per_replay() {
+        let dir = std::env::temp_dir().join("buckwheat_sync_offset_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Game_1.mp4").to_string_lossy().to_string();
+
+        SyncOffset {
+            replay_start_seconds: 2.0,
+            method: SyncMethod::Visual,
+            slp_file: Some("Game_1.slp".to_string()),
+        }
+        .write(&video)
+        .unwrap();
+
+        let offset = SyncOffset::load(&video, "/replays/Game_1.slp").unwrap();
+        assert_eq!(offset.replay_start_seconds, 2.0);
+        // Another game in the same video gets calibrated on its own
+        assert!(SyncOffset::load(&video, "/replays/Game_2.slp").is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_clip_events_count_from_the_clip_start() {
+        let death = |timestamp: f64| {
+            GameEvent::Death(crate::slippi::types::DeathEvent {
+                frame: (timestamp * 60.0) as i32,
+                timestamp,
+                port: 1,
+                player_tag: "P1".to_string(),
+                percent: 120.0,
+                stocks_remaining: 3,
+                blast_zone: crate::slippi::types::BlastZone::Left,
+                killer_port: Some(2),
+                killer_tag: Some("P2".to_string()),
+                killer_character: None,
+                kill_move: None,
+                kill_move_name: None,
+            })
+        };
+        let combo = |timestamp: f64, end_timestamp: f64| {
+            GameEvent::Combo(crate::slippi::types::ComboEvent {
+                frame: (timestamp * 60.0) as i32,
+                timestamp,
+                end_frame: (end_timestamp * 60.0) as i32,
+                end_timestamp,
+                attacker_port: 2,
+                attacker_tag: "P2".to_string(),
+                victim_port: 1,
+                victim_tag: "P1".to_string(),
+                moves: Vec::new(),
+                start_percent: 0.0,
+                end_percent: 40.0,
+                damage: 40.0,
+                did_kill: false,
+            })
+        };
+
+        // A clip of 20s-35s of the recording
+        let mut events = vec![
+            death(5.0),
+            combo(18.0, 22.0),
+            death(30.0),
+            combo(34.0, 38.0),
+            death(36.0),
+        ];
+        retime_to_clip(&mut events, 20.0, 15.0);
+
+        let times: Vec<f64> = events
+            .iter()
+            .map(|event| match event {
+                GameEvent::Death(death) => death.timestamp,
+                GameEvent::Combo(combo) => combo.timestamp,
+            })
+            .collect();
+        // The combo running into the clip stays, starting before it
+        assert_eq!(times, vec![-2.0, 10.0, 14.0]);
+        let GameEvent::Combo(combo) = &events[2] else {
+            panic!("expected the last combo");
+        };
+        assert_eq!(combo.end_timestamp, 18.0);
+    }
+
+    #[test]
+    fn test_picks_the_screen_change_nearest_the_estimate() {
+        assert_eq!(closest(&[0.4, 2.9, 5.2], 3.1), Some(2.9));
+        assert_eq!(closest(&[], 3.1), None);
+    }
+}
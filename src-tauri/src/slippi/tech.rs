@@ -0,0 +1,318 @@
+// Tech-skill metrics (L-cancels, wavedashes, dash dances, ledgedashes, techs)
+
+use super::events::{playable_frames, player_tags, state_at};
+use super::states;
+use super::types::TechStats;
+use crate::commands::errors::Error;
+use peppi::frame::immutable::PortData;
+
+/// Jumpsquat must have started this recently before an airdodge landing for it
+/// to count as a wavedash rather than a waveland
+const WAVEDASH_WINDOW: usize = 15;
+/// How recently the player must have been hanging on the ledge for an airdodge
+/// landing to count as a ledgedash
+const LEDGEDASH_WINDOW: usize = 40;
+
+/// Post-frame `l_cancel` values
+const L_CANCEL_SUCCESS: u8 = 1;
+const L_CANCEL_FAIL: u8 = 2;
+
+/// Post-frame `hurtbox_state` value for a normal, hittable hurtbox
+const HURTBOX_VULNERABLE: u8 = 0;
+
+/// What the tech pass reads of one player on one frame
+#[derive(Debug, Clone, Copy, Default)]
+struct TechFrame {
+    state: u16,
+    /// Missing on replays too old to record it
+    hurtbox: Option<u8>,
+    l_cancel: Option<u8>,
+}
+
+impl TechFrame {
+    fn at(port: &PortData, frame_idx: usize) -> Option<Self> {
+        let post = &port.leader.post;
+        Some(Self {
+            state: state_at(port, frame_idx)?,
+            hurtbox: post.hurtbox_state.as_ref().and_then(|h| h.get(frame_idx)),
+            l_cancel: post.l_cancel.as_ref().and_then(|l| l.get(frame_idx)),
+        })
+    }
+}
+
+/// Running state for one player while walking the frames
+#[derive(Default)]
+struct TechTracker {
+    stats: TechStats,
+    /// The last two distinct action states, oldest first
+    history: [Option<u16>; 2],
+    last_knee_bend: Option<usize>,
+    /// The last frame spent hanging on the ledge
+    last_cliff_wait: Option<usize>,
+    /// Set between a ledgedash landing and the end of its landing lag
+    ledgedash_landing: bool,
+    /// Intangible frames counted since a ledgedash became actionable
+    galint: Option<u32>,
+    galint_total: u32,
+    galint_samples: u32,
+}
+
+/// Compute tech-skill stats for every player in the game
+pub fn compute_tech_stats(game: &peppi::game::immutable::Game) -> Result<Vec<TechStats>, Error> {
+    log::info!("🎮 Computing tech-skill stats from game data");
+
+    let tags = player_tags(game);
+    let mut trackers: Vec<TechTracker> = game
+        .start
+        .players
+        .iter()
+        .enumerate()
+        .map(|(idx, player)| {
+            let port = u8::from(player.port);
+            TechTracker {
+                stats: TechStats {
+                    port,
+                    player_tag: tags
+                        .get(idx)
+                        .cloned()
+                        .unwrap_or_else(|| format!("P{}", port)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let mut prev_idx = None;
+    for (step, frame_idx) in playable_frames(game).enumerate() {
+        let before = prev_idx.unwrap_or(frame_idx);
+        prev_idx = Some(frame_idx);
+
+        for (tracker, port_data) in trackers.iter_mut().zip(game.frames.ports.iter()) {
+            if let Some(frame) = TechFrame::at(port_data, frame_idx) {
+                step_tech(tracker, &frame, state_at(port_data, before), step);
+            }
+        }
+    }
+
+    let stats = trackers
+        .into_iter()
+        .map(|tracker| {
+            let mut stats = tracker.stats;
+            let l_cancels = stats.l_cancel_success + stats.l_cancel_fail;
+            stats.l_cancel_rate =
+                (l_cancels > 0).then(|| stats.l_cancel_success as f32 / l_cancels as f32);
+            stats.avg_galint = (tracker.galint_samples > 0)
+                .then(|| tracker.galint_total as f32 / tracker.galint_samples as f32);
+            stats
+        })
+        .collect();
+
+    log::info!("✅ Computed tech-skill stats");
+    Ok(stats)
+}
+
+/// Advance one player's tracker by a frame
+///
+/// `step` counts playable frames, so windows are measured in frames the player
+/// actually saw rather than rolled-back ones.
+fn step_tech(tracker: &mut TechTracker, frame: &TechFrame, prev_state: Option<u16>, step: usize) {
+    let state = frame.state;
+    let hurtbox = frame.hurtbox;
+
+    // GALINT: count intangible frames left once the ledgedash landing lag is over
+    if let Some(galint) = tracker.galint.as_mut() {
+        if hurtbox.is_some_and(|h| h != HURTBOX_VULNERABLE) {
+            *galint += 1;
+        } else {
+            tracker.galint_total += *galint;
+            tracker.galint_samples += 1;
+            tracker.galint = None;
+        }
+    }
+
+    // Every frame, so the ledgedash window counts from letting go of the ledge
+    if state == states::CLIFF_WAIT {
+        tracker.last_cliff_wait = Some(step);
+    }
+
+    if prev_state == Some(state) {
+        return;
+    }
+
+    if state == states::KNEE_BEND {
+        tracker.last_knee_bend = Some(step);
+    }
+
+    if tracker.ledgedash_landing && state != states::LANDING_FALL_SPECIAL {
+        tracker.ledgedash_landing = false;
+        match hurtbox {
+            Some(HURTBOX_VULNERABLE) => tracker.galint_samples += 1,
+            Some(_) => tracker.galint = Some(1),
+            // Older replays don't record hurtbox state
+            None => {}
+        }
+    }
+
+    if states::is_aerial_landing(state) {
+        match frame.l_cancel {
+            Some(L_CANCEL_SUCCESS) => tracker.stats.l_cancel_success += 1,
+            Some(L_CANCEL_FAIL) => tracker.stats.l_cancel_fail += 1,
+            _ => {}
+        }
+    }
+
+    if state == states::LANDING_FALL_SPECIAL && prev_state == Some(states::ESCAPE_AIR) {
+        let recent = |since: Option<usize>, window: usize| {
+            since.is_some_and(|s| step.saturating_sub(s) <= window)
+        };
+        if recent(tracker.last_cliff_wait, LEDGEDASH_WINDOW) {
+            tracker.stats.ledgedashes += 1;
+            tracker.ledgedash_landing = true;
+            tracker.last_cliff_wait = None;
+        } else if recent(tracker.last_knee_bend, WAVEDASH_WINDOW) {
+            tracker.stats.wavedashes += 1;
+        } else {
+            tracker.stats.wavelands += 1;
+        }
+    }
+
+    // Dash -> Turn -> Dash is one dash dance
+    if state == states::DASH && tracker.history == [Some(states::DASH), Some(states::TURN)] {
+        tracker.stats.dash_dances += 1;
+    }
+
+    let was_down = prev_state.is_some_and(states::is_down);
+    if states::is_missed_tech(state) && !was_down {
+        tracker.stats.missed_techs += 1;
+    }
+    if states::is_teching(state) && !prev_state.is_some_and(states::is_teching) {
+        tracker.stats.techs += 1;
+    }
+
+    tracker.history = [tracker.history[1], Some(state)];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: u16 = 0x00E;
+    const JUMP_F: u16 = 0x019;
+    const FALL: u16 = 0x01D;
+    const LANDING_AIR_N: u16 = 0x046;
+    const CLIFF_JUMP: u16 = 0x101;
+    const DOWN_WAIT_U: u16 = 0x0B8;
+
+    /// Run a player through `(state, hurtbox, l_cancel)` frames
+    fn tech_stats(frames: &[(u16, Option<u8>, Option<u8>)]) -> TechTracker {
+        let mut tracker = TechTracker::default();
+        let mut prev_state = None;
+        for (step, &(state, hurtbox, l_cancel)) in frames.iter().enumerate() {
+            let frame = TechFrame {
+                state,
+                hurtbox,
+                l_cancel,
+            };
+            step_tech(&mut tracker, &frame, prev_state, step);
+            prev_state = Some(state);
+        }
+        tracker
+    }
+
+    fn states(states: &[(u16, usize)]) -> Vec<(u16, Option<u8>, Option<u8>)> {
+        states
+            .iter()
+            .flat_map(|&(state, frames)| std::iter::repeat_n((state, None, None), frames))
+            .collect()
+    }
+
+    #[test]
+    fn test_l_cancels() {
+        let mut frames = states(&[(FALL, 5)]);
+        frames.push((LANDING_AIR_N, None, Some(L_CANCEL_SUCCESS)));
+        frames.extend(states(&[(WAIT, 5), (FALL, 5)]));
+        frames.push((LANDING_AIR_N, None, Some(L_CANCEL_FAIL)));
+        let stats = tech_stats(&frames).stats;
+        assert_eq!((stats.l_cancel_success, stats.l_cancel_fail), (1, 1));
+    }
+
+    #[test]
+    fn test_airdodge_landings() {
+        // Jumpsquat straight into an airdodge is a wavedash
+        let wavedash = states(&[
+            (WAIT, 5),
+            (states::KNEE_BEND, 3),
+            (states::ESCAPE_AIR, 2),
+            (states::LANDING_FALL_SPECIAL, 10),
+        ]);
+        assert_eq!(tech_stats(&wavedash).stats.wavedashes, 1);
+
+        // Airdodging down from a jump is a waveland
+        let waveland = states(&[
+            (states::KNEE_BEND, 3),
+            (JUMP_F, 30),
+            (states::ESCAPE_AIR, 2),
+            (states::LANDING_FALL_SPECIAL, 10),
+        ]);
+        let stats = tech_stats(&waveland).stats;
+        assert_eq!((stats.wavedashes, stats.wavelands), (0, 1));
+
+        // Off the ledge it's a ledgedash, with the intangibility left after counted
+        let mut ledgedash = states(&[
+            (states::CLIFF_WAIT, 10),
+            (CLIFF_JUMP, 5),
+            (states::ESCAPE_AIR, 2),
+            (states::LANDING_FALL_SPECIAL, 10),
+        ]);
+        ledgedash.extend([(WAIT, Some(1), None); 4]);
+        ledgedash.push((WAIT, Some(HURTBOX_VULNERABLE), None));
+        let tracker = tech_stats(&ledgedash);
+        assert_eq!(tracker.stats.ledgedashes, 1);
+        assert_eq!(tracker.stats.wavelands, 0);
+        assert_eq!((tracker.galint_total, tracker.galint_samples), (4, 1));
+
+        // However long the player hung on the ledge first
+        let long_hang = states(&[
+            (states::CLIFF_WAIT, 120),
+            (CLIFF_JUMP, 5),
+            (states::ESCAPE_AIR, 2),
+            (states::LANDING_FALL_SPECIAL, 10),
+        ]);
+        let stats = tech_stats(&long_hang).stats;
+        assert_eq!((stats.ledgedashes, stats.wavelands), (1, 0));
+    }
+
+    #[test]
+    fn test_dash_dances() {
+        let frames = states(&[
+            (WAIT, 2),
+            (states::DASH, 5),
+            (states::TURN, 1),
+            (states::DASH, 5),
+            (states::TURN, 1),
+            (states::DASH, 5),
+        ]);
+        assert_eq!(tech_stats(&frames).stats.dash_dances, 2);
+    }
+
+    #[test]
+    fn test_techs_and_missed_techs() {
+        let teched = states(&[
+            (states::DAMAGE_START, 10),
+            (states::TECH_START, 20),
+            (WAIT, 2),
+        ]);
+        let stats = tech_stats(&teched).stats;
+        assert_eq!((stats.techs, stats.missed_techs), (1, 0));
+
+        // Bouncing off the ground and lying there is one missed tech
+        let missed = states(&[
+            (states::DAMAGE_START, 10),
+            (states::DOWN_BOUND_UP, 20),
+            (DOWN_WAIT_U, 30),
+        ]);
+        let stats = tech_stats(&missed).stats;
+        assert_eq!((stats.techs, stats.missed_techs), (0, 1));
+    }
+}
@@ -7,12 +7,230 @@ use serde::{Deserialize, Serialize};
 pub enum GameEvent {
     #[serde(rename = "death")]
     Death(DeathEvent),
+    #[serde(rename = "combo")]
+    Combo(ComboEvent),
 }
 
+impl GameEvent {
+    /// Frame the event starts on, used for ordering
+    pub fn frame(&self) -> i32 {
+        match self {
+            GameEvent::Death(e) => e.frame,
+            GameEvent::Combo(e) => e.frame,
+        }
+    }
+}
+
+/// A stock loss, with who took it and how
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DeathEvent {
     pub frame: i32,
     pub timestamp: f64,
     pub port: u8,
     pub player_tag: String,
+    /// Victim's percent on the frame before dying
+    pub percent: f32,
+    pub stocks_remaining: u8,
+    pub blast_zone: BlastZone,
+    /// None for self-destructs (or when the last hit is unknown)
+    pub killer_port: Option<u8>,
+    pub killer_tag: Option<String>,
+    pub killer_character: Option<u8>,
+    pub kill_move: Option<u8>,
+    pub kill_move_name: Option<String>,
+}
+
+/// Which blast zone a player died off
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlastZone {
+    Bottom,
+    Left,
+    Right,
+    Top,
+}
+
+/// A string of hits by one attacker on one victim
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComboEvent {
+    /// Frame the combo started on (first hit)
+    pub frame: i32,
+    pub timestamp: f64,
+    pub end_frame: i32,
+    pub end_timestamp: f64,
+    pub attacker_port: u8,
+    pub attacker_tag: String,
+    pub victim_port: u8,
+    pub victim_tag: String,
+    pub moves: Vec<ComboMove>,
+    pub start_percent: f32,
+    pub end_percent: f32,
+    pub damage: f32,
+    pub did_kill: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComboMove {
+    pub frame: i32,
+    pub move_id: u8,
+    pub move_name: String,
+    pub hit_count: u32,
+    pub damage: f32,
+}
+
+/// How the attacker won the opening that started a conversion
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpeningType {
+    NeutralWin,
+    CounterAttack,
+    Trade,
+}
+
+/// An opening and the punish that followed it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Conversion {
+    pub frame: i32,
+    pub timestamp: f64,
+    pub end_frame: i32,
+    pub end_timestamp: f64,
+    pub attacker_port: u8,
+    pub victim_port: u8,
+    pub opening_type: OpeningType,
+    pub moves: Vec<ComboMove>,
+    pub start_percent: f32,
+    pub end_percent: f32,
+    pub damage: f32,
+    pub did_kill: bool,
+}
+
+/// Summary of a player's punish game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerStats {
+    pub port: u8,
+    pub player_tag: String,
+    pub conversion_count: u32,
+    pub successful_conversions: u32,
+    pub kill_count: u32,
+    pub total_damage: f32,
+    pub neutral_wins: u32,
+    pub counter_hits: u32,
+    pub trades: u32,
+    /// None until the player has taken a stock
+    pub openings_per_kill: Option<f32>,
+    pub damage_per_opening: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameStats {
+    pub conversions: Vec<Conversion>,
+    pub players: Vec<PlayerStats>,
+}
+
+/// Per-player tech-skill numbers for one game
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TechStats {
+    pub port: u8,
+    pub player_tag: String,
+    pub l_cancel_success: u32,
+    pub l_cancel_fail: u32,
+    /// None if the player never landed an aerial
+    pub l_cancel_rate: Option<f32>,
+    pub wavedashes: u32,
+    pub wavelands: u32,
+    pub dash_dances: u32,
+    pub ledgedashes: u32,
+    /// Average intangibility frames left once actionable after a ledgedash
+    pub avg_galint: Option<f32>,
+    pub techs: u32,
+    pub missed_techs: u32,
+}
+
+/// Events decoded from a replay while the game is still being played
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    GameStart {
+        stage: u16,
+        players: Vec<LivePlayer>,
+    },
+    StockLost {
+        frame: i32,
+        port: u8,
+        stocks_remaining: u8,
+        /// Percent on the frame before the stock was lost
+        percent: f32,
+    },
+    PercentChange {
+        frame: i32,
+        port: u8,
+        percent: f32,
+    },
+    GameEnd {
+        /// Slippi end method (1 = TIME!, 2 = GAME!, 7 = No Contest)
+        method: u8,
+        lras_initiator: Option<u8>,
+    },
+}
+
+impl LiveEvent {
+    /// End reason, if this is the Game End event
+    pub fn end_reason(&self) -> Option<GameEndReason> {
+        match self {
+            LiveEvent::GameEnd {
+                method,
+                lras_initiator,
+            } => Some(GameEndReason::from_method(*method, *lras_initiator)),
+            _ => None,
+        }
+    }
+
+    /// Name of the Tauri event this is emitted as
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            LiveEvent::GameStart { .. } => "live-game-start",
+            LiveEvent::StockLost { .. } => "live-stock-lost",
+            LiveEvent::PercentChange { .. } => "live-percent-change",
+            LiveEvent::GameEnd { .. } => "live-game-end",
+        }
+    }
+}
+
+/// Why a game ended, from the Game End event
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameEndReason {
+    /// GAME! - all stocks taken
+    Game,
+    /// TIME! - the timer ran out
+    Time,
+    /// A player quit out with L+R+A+Start
+    Lras { initiator: u8 },
+    /// The game was ended without a result
+    NoContest,
+    /// The replay stopped growing without ever writing a Game End event
+    Abandoned,
+}
+
+impl GameEndReason {
+    /// Map Slippi's end method byte and LRAS initiator to a reason
+    pub fn from_method(method: u8, lras_initiator: Option<u8>) -> Self {
+        match (method, lras_initiator) {
+            (_, Some(initiator)) => GameEndReason::Lras { initiator },
+            (1, None) => GameEndReason::Time,
+            // 3 is "resolved" in replays from before 2.0.0
+            (2, None) | (3, None) => GameEndReason::Game,
+            _ => GameEndReason::NoContest,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LivePlayer {
+    /// 0-based port index
+    pub port: u8,
+    pub character_id: u8,
+    /// 0 = human, 1 = CPU, 2 = demo
+    pub player_type: u8,
+    pub costume: u8,
 }
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { RecordingProfile } from '$lib/types/recording';
 
 export const preventDefault = <T extends Event>(fn: (e: T) => void): ((e: T) => void) => {
     return (e: T) => {
@@ -84,6 +85,21 @@ export async function setGameProcessName(processName: string): Promise<void> {
     }
 }
 
+/**
+ * Get the profile recordings will use, custom or from the quality preset
+ */
+export async function getRecordingProfile(): Promise<RecordingProfile> {
+    return await invoke<RecordingProfile>('get_recording_profile');
+}
+
+/**
+ * Save a custom recording profile, or pass null to go back to the quality presets
+ * @param profile - the profile to record with; rejected if the backend can't record it
+ */
+export async function setRecordingProfile(profile: RecordingProfile | null): Promise<void> {
+    await invoke('set_recording_profile', { profile });
+}
+
 export enum FILES {
     GREET_FILE = 'greet.txt',
     NAME_FILE = 'name.txt'
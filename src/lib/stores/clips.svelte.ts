@@ -1,9 +1,15 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { RecordingSession } from '$lib/types/recording';
+import type { ClipMetadata, RecordingSession } from '$lib/types/recording';
+
+export type ClipSource = 'hotkey' | 'event' | 'manual';
 
 export interface ClipMarker {
 	timestamp: number;
 	recordingFile: string;
+	preRollSeconds?: number; // defaults to the clip duration setting
+	postRollSeconds?: number;
+	label?: string;
+	source?: ClipSource;
 }
 
 export interface ClipSession {
@@ -16,6 +22,7 @@ export interface ClipSession {
 	file_size: number | null;
 	slp_path: string | null;
 	slippi_metadata: any | null;
+	clip: ClipMetadata | null;
 }
 
 // Map RecordingSession from backend to ClipSession
@@ -40,6 +47,7 @@ function mapRecordingSessionToClip(session: RecordingSession): ClipSession {
 		file_size: session.file_size,
 		slp_path,
 		slippi_metadata: session.slippi_metadata,
+		clip: session.clip,
 	};
 }
 
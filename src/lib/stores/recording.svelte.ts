@@ -1,3 +1,5 @@
+import type { RecorderStats } from "$lib/types/recording";
+
 /**
  * Recording state store
  * Tracks whether a match is currently being recorded and game window status
@@ -7,10 +9,17 @@ class RecordingStore {
 	gameWindowDetected = $state(false);
 	gameActive = $state(false); // Will be set based on .slp file detection later
 	startTimestamp = $state<number | null>(null);
+	isPaused = $state(false);
+	pausedAt = $state<number | null>(null);
+	pausedTotalMs = $state(0); // Paused time isn't in the video
 	currentReplayPath = $state<string | null>(null);
+	stats = $state<RecorderStats | null>(null);
+	targetLost = $state(false); // Game window closed mid-recording; waiting for it to return
 
 	// Derived status for the indicator
 	status = $derived.by(() => {
+		if (this.isRecording && this.targetLost) return "target-lost";
+		if (this.isRecording && this.isPaused) return "paused";
 		if (this.isRecording) return "recording";
 		if (this.gameActive) return "ready";
 		if (this.gameWindowDetected) return "waiting";
@@ -20,11 +29,39 @@ class RecordingStore {
 	start(timestamp: number = Date.now()) {
 		this.isRecording = true;
 		this.startTimestamp = timestamp;
+		this.isPaused = false;
+		this.pausedAt = null;
+		this.pausedTotalMs = 0;
 	}
 
 	stop() {
 		this.isRecording = false;
 		this.startTimestamp = null;
+		this.isPaused = false;
+		this.pausedAt = null;
+		this.pausedTotalMs = 0;
+		this.stats = null;
+		this.targetLost = false;
+	}
+
+	pause(timestamp: number = Date.now()) {
+		if (this.isPaused) return;
+		this.isPaused = true;
+		this.pausedAt = timestamp;
+	}
+
+	resume(timestamp: number = Date.now()) {
+		if (!this.isPaused) return;
+		this.pausedTotalMs += timestamp - (this.pausedAt ?? timestamp);
+		this.isPaused = false;
+		this.pausedAt = null;
+	}
+
+	// Seconds into the video, leaving out paused time
+	elapsedSeconds(now: number = Date.now()): number {
+		if (!this.startTimestamp) return 0;
+		const currentPause = this.pausedAt ? now - this.pausedAt : 0;
+		return (now - this.startTimestamp - this.pausedTotalMs - currentPause) / 1000;
 	}
 
 	setGameWindow(detected: boolean) {
@@ -35,6 +72,21 @@ class RecordingStore {
 		this.gameActive = active;
 	}
 
+	setStats(stats: RecorderStats | null) {
+		this.stats = stats;
+	}
+
+	setTargetLost(lost: boolean) {
+		this.targetLost = lost;
+	}
+
+	// Share of frames dropped over the whole recording
+	dropRate = $derived.by(() => {
+		if (!this.stats) return 0;
+		const total = this.stats.frames_captured + this.stats.frames_dropped;
+		return total > 0 ? this.stats.frames_dropped / total : 0;
+	});
+
 	setReplayPath(path: string | null) {
 		this.currentReplayPath = path;
 	}
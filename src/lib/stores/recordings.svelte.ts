@@ -1,7 +1,16 @@
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { RecordingSession, RecordingWithMetadata, GameEvent } from "$lib/types/recording";
-import { handleTauriError, showSuccess } from "$lib/utils/errors";
+import type {
+	RecordingSession,
+	RecordingWithMetadata,
+	GameEvent,
+	RecordingStoppedEvent,
+	RecorderStats,
+	RecordingDegradedEvent,
+	RecoveredRecording,
+	ReplaySync,
+} from "$lib/types/recording";
+import { handleTauriError, showSuccess, showWarning } from "$lib/utils/errors";
 import { recording } from "$lib/stores/recording.svelte";
 import { settings } from "$lib/stores/settings.svelte";
 import { clipsStore, type ClipSession } from "$lib/stores/clips.svelte";
@@ -13,8 +22,10 @@ class RecordingsStore {
 	error = $state<string | null>(null);
 	isManualStarting = $state(false);
 	isManualStopping = $state(false);
+	isTogglingPause = $state(false);
 
 	private listenersActive = false;
+	private recoveryChecked = false;
 	private bootstrapRefCount = 0;
 	private eventListenerPromises: Promise<() => void>[] = [];
 	private extraCleanupFns: Array<() => void> = [];
@@ -141,7 +152,7 @@ class RecordingsStore {
 
 		if (!this.listenersActive) {
 			this.listenersActive = true;
-			void this.refresh();
+			void this.recoverInterrupted().then(() => this.refresh());
 			this.setupRecordingListeners();
 		}
 
@@ -153,6 +164,36 @@ class RecordingsStore {
 		};
 	}
 
+	// Repair recordings a crash left unfinished, once per launch
+	private async recoverInterrupted() {
+		if (this.recoveryChecked) return;
+		this.recoveryChecked = true;
+
+		try {
+			const recovered = await invoke<RecoveredRecording[]>("recover_recordings");
+			const repaired = recovered.filter((r) => r.outcome === "repaired").length;
+			const lost = recovered.filter((r) => r.outcome === "unrecoverable").length;
+			if (repaired > 0) {
+				showSuccess(`Recovered ${repaired} interrupted recording(s)`);
+			}
+			if (lost > 0) {
+				showWarning(`${lost} interrupted recording(s) couldn't be repaired - use Fragmented MP4 or MKV to avoid this`);
+			}
+		} catch (error) {
+			console.error("Failed to recover interrupted recordings:", error);
+		}
+
+		// Cut clips for markers that were never processed
+		try {
+			const clips = await invoke<string[]>("resume_clip_markers");
+			if (clips.length > 0) {
+				showSuccess(`Created ${clips.length} clip(s) marked before the app closed`);
+			}
+		} catch (error) {
+			console.error("Failed to resume clip markers:", error);
+		}
+	}
+
 	async startManualRecording() {
 		if (this.isManualStarting || recording.isRecording) {
 			return;
@@ -187,12 +228,31 @@ class RecordingsStore {
 			showSuccess("Recording stopped");
 			await this.refresh();
 		} catch (error) {
+			// The recording has ended either way; its file is recovered on the next start
+			recording.stop();
 			handleTauriError(error, "Failed to stop recording");
 		} finally {
 			this.isManualStopping = false;
 		}
 	}
 
+	async togglePause() {
+		if (this.isTogglingPause || !recording.isRecording) {
+			return;
+		}
+
+		this.isTogglingPause = true;
+
+		try {
+			// The paused/resumed events update the recording state
+			await invoke(recording.isPaused ? "resume_recording" : "pause_recording");
+		} catch (error) {
+			handleTauriError(error, recording.isPaused ? "Failed to resume recording" : "Failed to pause recording");
+		} finally {
+			this.isTogglingPause = false;
+		}
+	}
+
 	private setupRecordingListeners() {
 		invoke<string | null>("get_last_replay_path")
 			.then((path) => {
@@ -217,6 +277,47 @@ class RecordingsStore {
 			})
 		);
 
+		this.eventListenerPromises.push(
+			listen<string | null>("recording-paused", () => {
+				recording.pause();
+			})
+		);
+
+		this.eventListenerPromises.push(
+			listen<string | null>("recording-resumed", () => {
+				recording.resume();
+			})
+		);
+
+		this.eventListenerPromises.push(
+			listen<RecorderStats>("recording-stats", (event) => {
+				if (recording.isRecording) {
+					recording.setStats(event.payload);
+				}
+			})
+		);
+
+		this.eventListenerPromises.push(
+			listen<RecordingDegradedEvent>("recording-degraded", (event) => {
+				const percent = (event.payload.drop_rate * 100).toFixed(1);
+				showWarning(`Recording is dropping frames (${percent}%) - try a lower quality or frame rate`);
+			})
+		);
+
+		this.eventListenerPromises.push(
+			listen("recording-target-lost", () => {
+				recording.setTargetLost(true);
+				showWarning("Game window closed - recording will continue when it's back");
+			})
+		);
+
+		this.eventListenerPromises.push(
+			listen("recording-target-found", () => {
+				recording.setTargetLost(false);
+				showSuccess("Game window found - recording continues");
+			})
+		);
+
 		this.eventListenerPromises.push(
 			listen<string>("last-replay-updated", (event) => {
 				// Only set .slp path if we're not already recording with a video path
@@ -228,11 +329,11 @@ class RecordingsStore {
 		);
 
 		this.eventListenerPromises.push(
-			listen<string>("recording-stopped", async (event) => {
+			listen<RecordingStoppedEvent>("recording-stopped", async (event) => {
 				recording.stop();
 
 				// Use the video path from the event payload (guaranteed to be correct)
-				const videoPath = event.payload || recording.currentReplayPath;
+				const videoPath = event.payload.output_path || recording.currentReplayPath;
 				if (videoPath) {
 					try {
 						const clips = await invoke<string[]>("process_clip_markers", {
@@ -256,6 +357,14 @@ class RecordingsStore {
 			})
 		);
 
+		this.eventListenerPromises.push(
+			listen<string>("recording-failed", (event) => {
+				recording.stop();
+				recording.setReplayPath(null);
+				showWarning(`Recording couldn't be finished - it will be recovered on the next start (${event.payload})`);
+			})
+		);
+
 		const hotkeyHandler = async (event: KeyboardEvent) => {
 			const configuredHotkey = settings.createClipHotkey;
 			if (!configuredHotkey) return;
@@ -315,15 +424,22 @@ class RecordingsStore {
 
 	private async handleCreateClip() {
 		if (!recording.isRecording || !recording.startTimestamp || !recording.currentReplayPath) {
-			handleTauriError(new Error("Can only create clips during active recording"), "Not recording");
+			// Outside of a recording, save whatever the replay buffer has kept
+			try {
+				await invoke("save_replay_buffer");
+				showSuccess("Saved instant replay!");
+			} catch {
+				handleTauriError(new Error("Can only create clips during active recording"), "Not recording");
+			}
 			return;
 		}
 
 		try {
-			const timestamp = (Date.now() - recording.startTimestamp) / 1000;
+			const timestamp = recording.elapsedSeconds();
 			await invoke("mark_clip_timestamp", {
 				recordingFile: recording.currentReplayPath,
-				timestamp
+				timestamp,
+				source: "hotkey"
 			});
 			showSuccess(`Clip marked at ${Math.floor(timestamp)}s! Will be created after recording ends.`);
 		} catch (error) {
@@ -349,16 +465,29 @@ class RecordingsStore {
 		return this.recordings.find((r) => r.id === id);
 	}
 
-	// Load Slippi events from a .slp file
-	async loadSlippiEvents(slpPath: string): Promise<GameEvent[]> {
+	// Load Slippi events from a .slp file, timed against the video recorded with it
+	async loadSlippiEvents(slpPath: string, videoPath?: string | null): Promise<GameEvent[]> {
 		try {
-			return await invoke<GameEvent[]>("parse_slp_events", { slpPath });
+			return await invoke<GameEvent[]>("parse_slp_events", {
+				slpPath,
+				videoPath: videoPath ?? null,
+			});
 		} catch (error) {
 			handleTauriError(error, "Failed to parse replay events");
 			return [];
 		}
 	}
 
+	// Where a replay starts in its video; calibrated the first time it's asked for
+	async getReplaySync(videoPath: string, slpPath: string, recalibrate = false): Promise<ReplaySync | null> {
+		try {
+			return await invoke<ReplaySync | null>("get_replay_sync", { videoPath, slpPath, recalibrate });
+		} catch (error) {
+			handleTauriError(error, "Failed to sync the replay with its video");
+			return null;
+		}
+	}
+
 	// Check if a recording/clip is clip-only (no Slippi metadata)
 	isClipOnly(recording: ClipSession | RecordingWithMetadata | undefined): boolean {
 		return !recording?.slp_path;
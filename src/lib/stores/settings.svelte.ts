@@ -1,4 +1,5 @@
 import { Store } from "@tauri-apps/plugin-store";
+import type { RecordingProfile } from "$lib/types/recording";
 
 export type Settings = {
 	// Appearance
@@ -7,7 +8,14 @@ export type Settings = {
 	// Recording
 	recordingPath: string;
 	recordingQuality: "low" | "medium" | "high" | "ultra";
+	recordingBackend: "native" | "ffmpeg";
+	ffmpegEncoder: "nvenc" | "qsv" | "amf" | "videotoolbox";
+	recordingProfile: RecordingProfile | null; // null uses the quality preset
 	autoStartRecording: boolean;
+	postRollSeconds: number;
+	continueIntoNextGame: boolean;
+	segmentMinutes: number; // 0 never splits
+	segmentSizeMb: number; // 0 never splits
 	
 	// Slippi
 	slippiPath: string;
@@ -16,17 +24,28 @@ export type Settings = {
 	// Clips
 	createClipHotkey: string;
 	clipDuration: number;
+	clipCutMode: "copy" | "smart" | "reencode";
+	jobConcurrency: number; // read at startup
 };
 
 const DEFAULT_SETTINGS: Settings = {
 	theme: "system",
 	recordingPath: "",
 	recordingQuality: "high",
+	recordingBackend: "native",
+	ffmpegEncoder: "nvenc",
+	recordingProfile: null,
 	autoStartRecording: true,
+	postRollSeconds: 0,
+	continueIntoNextGame: false,
+	segmentMinutes: 0,
+	segmentSizeMb: 0,
 	slippiPath: "",
 	watchForGames: true,
 	createClipHotkey: "F9",
 	clipDuration: 30,
+	clipCutMode: "smart",
+	jobConcurrency: 2,
 };
 
 class SettingsStore {
@@ -36,11 +55,20 @@ class SettingsStore {
 	theme = $state<Settings["theme"]>("system");
 	recordingPath = $state("");
 	recordingQuality = $state<Settings["recordingQuality"]>("high");
+	recordingBackend = $state<Settings["recordingBackend"]>("native");
+	ffmpegEncoder = $state<Settings["ffmpegEncoder"]>("nvenc");
+	recordingProfile = $state<Settings["recordingProfile"]>(null);
 	autoStartRecording = $state(true);
+	postRollSeconds = $state(0);
+	continueIntoNextGame = $state(false);
+	segmentMinutes = $state(0);
+	segmentSizeMb = $state(0);
 	slippiPath = $state("");
 	watchForGames = $state(true);
 	createClipHotkey = $state("F9");
 	clipDuration = $state(30);
+	clipCutMode = $state<Settings["clipCutMode"]>("smart");
+	jobConcurrency = $state(2);
 	
 	isLoading = $state(true);
 
@@ -68,22 +96,40 @@ class SettingsStore {
 		this.theme = settings.theme;
 		this.recordingPath = settings.recordingPath;
 		this.recordingQuality = settings.recordingQuality;
+		this.recordingBackend = settings.recordingBackend;
+		this.ffmpegEncoder = settings.ffmpegEncoder;
+		this.recordingProfile = settings.recordingProfile;
 		this.autoStartRecording = settings.autoStartRecording;
+		this.postRollSeconds = settings.postRollSeconds;
+		this.continueIntoNextGame = settings.continueIntoNextGame;
+		this.segmentMinutes = settings.segmentMinutes;
+		this.segmentSizeMb = settings.segmentSizeMb;
 		this.slippiPath = settings.slippiPath;
 		this.watchForGames = settings.watchForGames;
 		this.createClipHotkey = settings.createClipHotkey;
 		this.clipDuration = settings.clipDuration;
+		this.clipCutMode = settings.clipCutMode;
+		this.jobConcurrency = settings.jobConcurrency;
 	}
 
 	private loadDefaults(): void {
 		this.theme = DEFAULT_SETTINGS.theme;
 		this.recordingPath = DEFAULT_SETTINGS.recordingPath;
 		this.recordingQuality = DEFAULT_SETTINGS.recordingQuality;
+		this.recordingBackend = DEFAULT_SETTINGS.recordingBackend;
+		this.ffmpegEncoder = DEFAULT_SETTINGS.ffmpegEncoder;
+		this.recordingProfile = DEFAULT_SETTINGS.recordingProfile;
 		this.autoStartRecording = DEFAULT_SETTINGS.autoStartRecording;
+		this.postRollSeconds = DEFAULT_SETTINGS.postRollSeconds;
+		this.continueIntoNextGame = DEFAULT_SETTINGS.continueIntoNextGame;
+		this.segmentMinutes = DEFAULT_SETTINGS.segmentMinutes;
+		this.segmentSizeMb = DEFAULT_SETTINGS.segmentSizeMb;
 		this.slippiPath = DEFAULT_SETTINGS.slippiPath;
 		this.watchForGames = DEFAULT_SETTINGS.watchForGames;
 		this.createClipHotkey = DEFAULT_SETTINGS.createClipHotkey;
 		this.clipDuration = DEFAULT_SETTINGS.clipDuration;
+		this.clipCutMode = DEFAULT_SETTINGS.clipCutMode;
+		this.jobConcurrency = DEFAULT_SETTINGS.jobConcurrency;
 	}
 
 	private async getAll(): Promise<Settings> {
@@ -93,11 +139,20 @@ class SettingsStore {
 			theme: ((await this.store.get("theme")) as Settings["theme"]) ?? DEFAULT_SETTINGS.theme,
 			recordingPath: ((await this.store.get("recordingPath")) as string) ?? DEFAULT_SETTINGS.recordingPath,
 			recordingQuality: ((await this.store.get("recordingQuality")) as Settings["recordingQuality"]) ?? DEFAULT_SETTINGS.recordingQuality,
+			recordingBackend: ((await this.store.get("recordingBackend")) as Settings["recordingBackend"]) ?? DEFAULT_SETTINGS.recordingBackend,
+			ffmpegEncoder: ((await this.store.get("ffmpegEncoder")) as Settings["ffmpegEncoder"]) ?? DEFAULT_SETTINGS.ffmpegEncoder,
+			recordingProfile: ((await this.store.get("recordingProfile")) as Settings["recordingProfile"]) ?? DEFAULT_SETTINGS.recordingProfile,
 			autoStartRecording: ((await this.store.get("autoStartRecording")) as boolean) ?? DEFAULT_SETTINGS.autoStartRecording,
+			postRollSeconds: ((await this.store.get("postRollSeconds")) as number) ?? DEFAULT_SETTINGS.postRollSeconds,
+			continueIntoNextGame: ((await this.store.get("continueIntoNextGame")) as boolean) ?? DEFAULT_SETTINGS.continueIntoNextGame,
+			segmentMinutes: ((await this.store.get("segmentMinutes")) as number) ?? DEFAULT_SETTINGS.segmentMinutes,
+			segmentSizeMb: ((await this.store.get("segmentSizeMb")) as number) ?? DEFAULT_SETTINGS.segmentSizeMb,
 			slippiPath: ((await this.store.get("slippiPath")) as string) ?? DEFAULT_SETTINGS.slippiPath,
 			watchForGames: ((await this.store.get("watchForGames")) as boolean) ?? DEFAULT_SETTINGS.watchForGames,
 			createClipHotkey: ((await this.store.get("createClipHotkey")) as string) ?? DEFAULT_SETTINGS.createClipHotkey,
 			clipDuration: ((await this.store.get("clipDuration")) as number) ?? DEFAULT_SETTINGS.clipDuration,
+			clipCutMode: ((await this.store.get("clipCutMode")) as Settings["clipCutMode"]) ?? DEFAULT_SETTINGS.clipCutMode,
+			jobConcurrency: ((await this.store.get("jobConcurrency")) as number) ?? DEFAULT_SETTINGS.jobConcurrency,
 		};
 	}
 
@@ -113,9 +168,30 @@ class SettingsStore {
 			case "recordingQuality":
 				this.recordingQuality = value as Settings["recordingQuality"];
 				break;
+			case "recordingBackend":
+				this.recordingBackend = value as Settings["recordingBackend"];
+				break;
+			case "ffmpegEncoder":
+				this.ffmpegEncoder = value as Settings["ffmpegEncoder"];
+				break;
+			case "recordingProfile":
+				this.recordingProfile = value as Settings["recordingProfile"];
+				break;
 			case "autoStartRecording":
 				this.autoStartRecording = value as boolean;
 				break;
+			case "postRollSeconds":
+				this.postRollSeconds = value as number;
+				break;
+			case "continueIntoNextGame":
+				this.continueIntoNextGame = value as boolean;
+				break;
+			case "segmentMinutes":
+				this.segmentMinutes = value as number;
+				break;
+			case "segmentSizeMb":
+				this.segmentSizeMb = value as number;
+				break;
 			case "slippiPath":
 				this.slippiPath = value as string;
 				break;
@@ -128,6 +204,12 @@ class SettingsStore {
 			case "clipDuration":
 				this.clipDuration = value as number;
 				break;
+			case "clipCutMode":
+				this.clipCutMode = value as Settings["clipCutMode"];
+				break;
+			case "jobConcurrency":
+				this.jobConcurrency = value as number;
+				break;
 		}
 		
 		// Persist to store if available
@@ -144,11 +226,20 @@ class SettingsStore {
 			"theme",
 			"recordingPath",
 			"recordingQuality",
+			"recordingBackend",
+			"ffmpegEncoder",
+			"recordingProfile",
 			"autoStartRecording",
+			"postRollSeconds",
+			"continueIntoNextGame",
+			"segmentMinutes",
+			"segmentSizeMb",
 			"slippiPath",
 			"watchForGames",
 			"createClipHotkey",
 			"clipDuration",
+			"clipCutMode",
+			"jobConcurrency",
 		];
 
 		for (const key of keys) {
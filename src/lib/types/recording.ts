@@ -70,6 +70,23 @@ export interface RecordingSession {
 	duration: number | null; // in seconds
 	file_size: number | null; // in bytes
 	slippi_metadata: SlippiMetadata | null;
+	tech_stats: TechStats[] | null;
+	segments: string[] | null; // files of a split recording; video_path is then its manifest
+	video_start_seconds: number | null; // where a later game of a set starts in the set's video
+	clip: ClipMetadata | null; // where a clip came from; null for recordings
+}
+
+// What a clip was cut from, saved next to it when it's made
+export interface ClipMetadata {
+	source_recording: string;
+	slp_path: string | null;
+	start_frame: number | null; // replay frames covered, when the recording was synced
+	end_frame: number | null;
+	video_offset_seconds: number; // where the clip starts in the source recording
+	duration_seconds: number;
+	label: string | null;
+	players: SlippiPlayer[];
+	stage: StageId | number | null;
 }
 
 // Frontend recording with parsed metadata
@@ -81,14 +98,22 @@ export interface RecordingWithMetadata extends RecordingSession {
 // Game event types
 export enum GameEventType {
 	DEATH = 'death',
-	// Future: 'combo', 'neutral_exchange', 'sd', etc.
+	COMBO = 'combo',
+	// Future: 'neutral_exchange', 'sd', etc.
 }
 
 // Base game event interface
 export interface GameEvent {
 	type: GameEventType;
 	frame: number; // Frame number when event occurred
-	timestamp: number; // Time in seconds (frame / 60)
+	timestamp: number; // Seconds into the video, or frame / 60 without a synced recording
+}
+
+// Where a replay starts in its video, from get_replay_sync
+export interface ReplaySync {
+	replay_start_seconds: number; // after the recording started
+	method: 'start_at' | 'file_times' | 'visual' | 'set';
+	slp_file: string | null; // the replay it was worked out for
 }
 
 // Death event - when a player loses a stock
@@ -96,5 +121,194 @@ export interface DeathEvent extends GameEvent {
 	type: GameEventType.DEATH;
 	port: number; // Which player died (1-4)
 	player_tag: string; // Player's tag/name
+	percent: number; // Victim's percent before dying
+	stocks_remaining: number;
+	blast_zone: BlastZone;
+	killer_port: number | null; // null for self-destructs
+	killer_tag: string | null;
+	killer_character: number | null;
+	kill_move: number | null;
+	kill_move_name: string | null;
 }
 
+// Which blast zone a player died off
+export type BlastZone = 'bottom' | 'left' | 'right' | 'top';
+
+
+// A single move within a combo
+export interface ComboMove {
+	frame: number;
+	move_id: number;
+	move_name: string;
+	hit_count: number;
+	damage: number;
+}
+
+// Combo event - a string of hits by one player on another
+export interface ComboEvent extends GameEvent {
+	type: GameEventType.COMBO;
+	end_frame: number;
+	end_timestamp: number;
+	attacker_port: number;
+	attacker_tag: string;
+	victim_port: number;
+	victim_tag: string;
+	moves: ComboMove[];
+	start_percent: number;
+	end_percent: number;
+	damage: number;
+	did_kill: boolean;
+}
+
+// How an opening was won
+export type OpeningType = 'neutral_win' | 'counter_attack' | 'trade';
+
+// An opening and the punish that followed it
+export interface Conversion {
+	frame: number;
+	timestamp: number;
+	end_frame: number;
+	end_timestamp: number;
+	attacker_port: number;
+	victim_port: number;
+	opening_type: OpeningType;
+	moves: ComboMove[];
+	start_percent: number;
+	end_percent: number;
+	damage: number;
+	did_kill: boolean;
+}
+
+// Per-player punish summary
+export interface PlayerStats {
+	port: number;
+	player_tag: string;
+	conversion_count: number;
+	successful_conversions: number;
+	kill_count: number;
+	total_damage: number;
+	neutral_wins: number;
+	counter_hits: number;
+	trades: number;
+	openings_per_kill: number | null;
+	damage_per_opening: number | null;
+}
+
+// Stats returned by parse_slp_stats
+export interface GameStats {
+	conversions: Conversion[];
+	players: PlayerStats[];
+}
+
+// Per-player tech-skill numbers returned by parse_slp_tech_stats
+export interface TechStats {
+	port: number;
+	player_tag: string;
+	l_cancel_success: number;
+	l_cancel_fail: number;
+	l_cancel_rate: number | null;
+	wavedashes: number;
+	wavelands: number;
+	dash_dances: number;
+	ledgedashes: number;
+	avg_galint: number | null; // Intangible frames left once actionable
+	techs: number;
+	missed_techs: number;
+}
+
+// Live events emitted while a replay is still being written
+export interface LivePlayer {
+	port: number; // 0-based
+	character_id: number;
+	player_type: number; // 0 = human, 1 = CPU, 2 = demo
+	costume: number;
+}
+
+export interface LiveGameStart {
+	type: 'game_start';
+	stage: number;
+	players: LivePlayer[];
+}
+
+export interface LiveStockLost {
+	type: 'stock_lost';
+	frame: number;
+	port: number;
+	stocks_remaining: number;
+	percent: number;
+}
+
+export interface LivePercentChange {
+	type: 'percent_change';
+	frame: number;
+	port: number;
+	percent: number;
+}
+
+export interface LiveGameEnd {
+	type: 'game_end';
+	method: number; // 1 = TIME!, 2 = GAME!, 7 = No Contest
+	lras_initiator: number | null;
+}
+
+// Why a game ended, from the replay's Game End event
+export type GameEndReason =
+	| { kind: 'game' }
+	| { kind: 'time' }
+	| { kind: 'lras'; initiator: number }
+	| { kind: 'no_contest' }
+	| { kind: 'abandoned' };
+
+// Payload of the recording-stopped event
+export interface RecordingStoppedEvent {
+	output_path: string;
+	end_reason: GameEndReason | null; // null for manual stops
+}
+
+// Game lifecycle phase, emitted as game-lifecycle transitions
+export type GamePhase =
+	| { phase: 'idle' }
+	| { phase: 'game_starting'; slp_path: string }
+	| { phase: 'in_game'; slp_path: string }
+	| { phase: 'game_ended'; slp_path: string; reason: GameEndReason }
+	| { phase: 'finalizing'; slp_path: string };
+
+export interface GameLifecycleTransition {
+	from: GamePhase;
+	to: GamePhase;
+}
+
+// A recording a crash left unfinished, repaired at startup
+export interface RecoveredRecording {
+	video_path: string;
+	slp_path: string | null;
+	outcome: 'repaired' | 'unrecoverable' | 'missing';
+}
+
+// Live numbers about the current recording, from recording-stats
+export interface RecorderStats {
+	elapsed_seconds: number; // leaves out paused time
+	frames_captured: number;
+	frames_dropped: number;
+	encoder_queue_depth: number;
+	bytes_written: number;
+	audio_underruns: number;
+	paused: boolean;
+}
+
+export interface RecordingDegradedEvent {
+	drop_rate: number; // share of frames dropped since the last stats update
+	stats: RecorderStats;
+}
+
+// How recordings are encoded; the quality levels are presets of this
+export interface RecordingProfile {
+	codec: 'h264' | 'hevc' | 'av1';
+	container: 'mp4' | 'mkv' | 'fragmented_mp4';
+	rate_control: 'cbr' | 'vbr' | 'cqp';
+	bitrate: number; // bits per second, for CBR and VBR
+	qp: number; // 0-51, for CQP
+	fps: number;
+	output_height: number | null; // null records at the window's size
+	encoder: 'auto' | 'software' | 'hardware';
+}
@@ -18,6 +18,8 @@ const ERROR_MESSAGES: Record<string, string> = {
 	unsupportedPlatform: "This feature is not supported on your platform",
 	watchError: "Failed to watch folder",
 	invalidPath: "Invalid file path",
+	invalidGameTransition: "Unexpected game state",
+	unsupportedRecordingProfile: "Recording settings not supported",
 	permissionError: "Permission denied"
 };
 
@@ -95,3 +97,12 @@ export function showInfo(message: string): void {
 	});
 }
 
+
+/**
+ * Show warning toast
+ */
+export function showWarning(message: string): void {
+	toast.warning(message, {
+		duration: 5000
+	});
+}